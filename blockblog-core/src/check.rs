@@ -0,0 +1,614 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use colored::*;
+use log::{info, warn};
+
+use crate::data::{
+    assets,
+    blocks::{BlockItem, GalleryImage, IncludeTarget, LinkStyle, PageRegion, StyleSource},
+    components,
+    config::{SiteConfig, SITE_CONFIG_FILE},
+    data_files, error, plugins,
+};
+use crate::generator::{self, GenerateOptions};
+
+/// Something wrong with a block definition, found without constructing any page, or an
+/// accessibility problem of a constructed page with strict accessibility checks.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Problem {
+    /// Relative to the input directory.
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file.display(), line, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+struct BlockFile {
+    path: PathBuf,
+    contents: String,
+    item: BlockItem,
+}
+
+/// Parses every block definition and checks that includes resolve without cycles, that
+/// loops are well formed and that the files blocks refer to exist. With accessibility
+/// checks on, every page is then constructed to check it as `generate` would. Returns every
+/// problem found instead of stopping at the first.
+pub fn check(options: &GenerateOptions) -> Result<Vec<Problem>> {
+    let input = &options.input;
+    let site_config = generator::load_site_config(options)?;
+
+    let mut problems = Vec::new();
+    let mut blocks = BTreeMap::new();
+
+    // the blocks of a component set are named relative to its directory, under its namespace
+    let mut files = block_files(input, input, &site_config)?
+        .into_iter()
+        .map(|path| (path.clone(), path))
+        .collect::<Vec<_>>();
+
+    for (namespace, dir) in components::component_dirs(input, &site_config)? {
+        let root = input.join(&dir);
+
+        for path in block_files(&root, &root, &site_config)? {
+            files.push((dir.join(&path), Path::new(&namespace).join(path)));
+        }
+    }
+
+    for (path, name) in files {
+        let contents = std::fs::read_to_string(input.join(&path))?;
+        let name = name
+            .with_extension("")
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let parsed = error::parse_yaml_resolved::<BlockItem>(&path, &contents, |value| {
+            site_config.theme.resolve_references(value)
+        });
+
+        match parsed {
+            Ok(_) if blocks.contains_key(&name) => problems.push(Problem {
+                file: path,
+                line: None,
+                message: format!("Block {} has the same name as a block of the site", name),
+            }),
+            Ok(item) => {
+                blocks.insert(
+                    name,
+                    BlockFile {
+                        path,
+                        contents,
+                        item,
+                    },
+                );
+            }
+            Err(why) => problems.push(Problem {
+                line: why.line(),
+                file: path,
+                message: match why.yaml_path.as_str() {
+                    "" => why.message,
+                    yaml_path => format!("{} (at {})", why.message, yaml_path),
+                },
+            }),
+        }
+    }
+
+    for name in [&site_config.header, &site_config.footer]
+        .into_iter()
+        .flatten()
+    {
+        if !blocks.contains_key(name) {
+            problems.push(Problem {
+                file: PathBuf::from(SITE_CONFIG_FILE),
+                line: std::fs::read_to_string(input.join(SITE_CONFIG_FILE))
+                    .ok()
+                    .and_then(|contents| find_line(&contents, name)),
+                message: format!("Included block {} does not exist", name),
+            });
+        }
+    }
+
+    for snippet in site_config.snippets.iter() {
+        for profile in snippet.profiles.iter() {
+            if !site_config.profiles.contains_key(profile) {
+                problems.push(Problem {
+                    file: PathBuf::from(SITE_CONFIG_FILE),
+                    line: None,
+                    message: format!("Snippet profile {} does not exist", profile),
+                });
+            }
+        }
+    }
+
+    let mut includes = BTreeMap::new();
+
+    for (name, block) in blocks.iter() {
+        let mut checker = Checker {
+            input,
+            site_config: &site_config,
+            blocks: &blocks,
+            block,
+            includes: BTreeSet::new(),
+            problems: Vec::new(),
+        };
+
+        checker.item(&block.item);
+
+        includes.insert(name.clone(), checker.includes);
+        problems.extend(checker.problems);
+    }
+
+    for cycle in include_cycles(&includes) {
+        let block = &blocks[&cycle[0]];
+
+        problems.push(Problem {
+            file: block.path.clone(),
+            line: find_line(&block.contents, &cycle[1]),
+            message: format!("Circular include: {}", cycle.join(" -> ")),
+        });
+    }
+
+    if let Some(a11y) = &options.a11y {
+        if problems.is_empty() {
+            problems.extend(a11y_problems(options, &site_config, &blocks, a11y.strict)?);
+        } else {
+            info!("Skipping accessibility checks until the block definitions have no problems");
+        }
+    }
+
+    problems.sort();
+    Ok(problems)
+}
+
+/// Constructs every page to find its accessibility problems, which are warnings unless
+/// `strict` is set. Pages that fail to construct are left for `generate` to report.
+fn a11y_problems(
+    options: &GenerateOptions,
+    site_config: &SiteConfig,
+    blocks: &BTreeMap<String, BlockFile>,
+    strict: bool,
+) -> Result<Vec<Problem>> {
+    let asset_map = generator::load_asset_map(options, site_config)?;
+    let mut block_builder = generator::new_block_builder(options, site_config, &asset_map)?;
+    let mut problems = Vec::new();
+
+    for (_, page) in block_builder.construct_all()? {
+        let findings = match page {
+            Ok(page) => page.a11y_findings,
+            Err(_) => continue,
+        };
+
+        for finding in findings {
+            if !strict {
+                warn!("{} {}", "Accessibility:".yellow().bold(), finding);
+                continue;
+            }
+
+            problems.push(Problem {
+                file: blocks.get(&finding.block).map_or_else(
+                    || PathBuf::from(format!("{}.yml", finding.block)),
+                    |block| block.path.clone(),
+                ),
+                line: None,
+                message: format!("Accessibility: {}", finding),
+            });
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Paths of the block definition files under `dir`, relative to `input`.
+fn block_files(dir: &Path, input: &Path, site_config: &SiteConfig) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let relative = path.strip_prefix(input)?;
+
+        if site_config.is_ignored(relative) || data_files::is_data_file(relative) {
+            continue;
+        }
+
+        if path.is_dir() {
+            files.extend(block_files(&path, input, site_config)?);
+        } else if path.extension().is_some_and(|ext| ext == "yml")
+            && path != input.join(SITE_CONFIG_FILE)
+        {
+            files.push(relative.to_path_buf());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+struct Checker<'a> {
+    input: &'a Path,
+    site_config: &'a SiteConfig,
+    blocks: &'a BTreeMap<String, BlockFile>,
+    block: &'a BlockFile,
+    includes: BTreeSet<String>,
+    problems: Vec<Problem>,
+}
+
+impl Checker<'_> {
+    fn problem(&mut self, needle: &str, message: String) {
+        self.problems.push(Problem {
+            file: self.block.path.clone(),
+            line: find_line(&self.block.contents, needle),
+            message,
+        });
+    }
+
+    fn items(&mut self, items: &[BlockItem]) {
+        for item in items {
+            self.item(item);
+        }
+    }
+
+    fn item(&mut self, item: &BlockItem) {
+        match item {
+            BlockItem::Html {
+                body,
+                header,
+                footer,
+                ..
+            } => {
+                for region in [header, footer].into_iter().flatten() {
+                    if let PageRegion::Block(name) = region {
+                        self.include(name);
+                    }
+                }
+
+                self.items(body.as_deref().unwrap_or_default())
+            }
+            BlockItem::Include(IncludeTarget::Plain(name)) => self.include(name),
+            BlockItem::Include(IncludeTarget::Verbose {
+                path: name,
+                slots,
+                children,
+                ..
+            }) => {
+                self.include(name);
+                self.items(children);
+
+                for items in slots.values() {
+                    self.items(items);
+                }
+            }
+            BlockItem::Layout { name, slots, .. } => {
+                self.include(name);
+
+                for items in slots.values() {
+                    self.items(items);
+                }
+            }
+            BlockItem::Template { params, items } => {
+                for param in params {
+                    if let Some(default) = param.default() {
+                        if !param.param_type().accepts(default) {
+                            self.problem(
+                                param.name(),
+                                format!("Default of param {} is {:?}", param.describe(), default),
+                            );
+                        }
+                    }
+                }
+
+                self.items(items)
+            }
+            BlockItem::Block { items, .. }
+            | BlockItem::List { items, .. }
+            | BlockItem::Paginate { items, .. }
+            | BlockItem::Form { items, .. }
+            | BlockItem::Details { items, .. }
+            | BlockItem::Callout { items, .. }
+            | BlockItem::Quote { items, .. }
+            | BlockItem::Footnote { content: items, .. } => self.items(items),
+            BlockItem::ForEach {
+                pattern,
+                values,
+                items,
+                ..
+            } => {
+                if pattern.is_some() == values.is_some() {
+                    self.problem(
+                        "$for_each",
+                        "$for_each needs exactly one of values and pattern".to_string(),
+                    );
+                }
+
+                self.items(items);
+            }
+            BlockItem::If {
+                then, otherwise, ..
+            } => {
+                self.items(then);
+                self.items(otherwise);
+            }
+            BlockItem::Table {
+                csv: Some(path), ..
+            } => self.file("CSV file", path),
+            BlockItem::StyleInline(StyleSource::File { path }) => self.file("Style file", path),
+            BlockItem::MarkdownFile(path) | BlockItem::Toc { path, .. } => {
+                self.file("Markdown file", path)
+            }
+            BlockItem::CodeFile { path, .. } => self.file("Code file", path),
+            BlockItem::Date {
+                file: Some(path), ..
+            } => self.file("Date file", path),
+            BlockItem::Summary {
+                file: Some(path), ..
+            } => self.file("Summary file", path),
+            BlockItem::Image { path, .. } => self.asset("Image", path),
+            BlockItem::Figure { image, items, .. } => {
+                if let Some(image) = image {
+                    self.asset("Image", image);
+                }
+
+                self.items(items);
+            }
+            BlockItem::Gallery {
+                pattern, images, ..
+            } => {
+                if pattern.is_some() == images.is_some() {
+                    self.problem(
+                        "gallery",
+                        "gallery needs exactly one of pattern and images".to_string(),
+                    );
+                }
+
+                for image in images.iter().flatten() {
+                    match image {
+                        GalleryImage::Plain(path) | GalleryImage::Verbose { path, .. } => {
+                            self.asset("Image", path)
+                        }
+                    }
+                }
+            }
+            BlockItem::Tabs { tabs, .. } => {
+                for tab in tabs {
+                    self.items(&tab.items);
+                }
+            }
+            BlockItem::Video { path, poster, .. } => {
+                self.asset("Video", path);
+
+                if let Some(poster) = poster {
+                    self.asset("Image", poster);
+                }
+            }
+            BlockItem::Audio { path, .. } => self.asset("Audio", path),
+            BlockItem::Link {
+                link_style: LinkStyle::Preset(name),
+                ..
+            } => {
+                if !self.site_config.link_styles.contains_key(name) {
+                    self.problem(name, format!("Link style preset {} does not exist", name));
+                }
+            }
+            BlockItem::Heading {
+                level: Some(level), ..
+            } if !(1..=6).contains(level) => self.problem(
+                "level",
+                format!("Heading level {} is not between 1 and 6", level),
+            ),
+            BlockItem::Diagram { source, file, .. } => {
+                if source.is_some() == file.is_some() {
+                    self.problem(
+                        "diagram",
+                        "Diagram needs exactly one of source and file".to_string(),
+                    );
+                }
+
+                if let Some(file) = file {
+                    self.file("Diagram file", file);
+                }
+            }
+            BlockItem::Plugin { item_type, .. } => {
+                if plugins::find_item_plugin(&self.site_config.plugins, item_type).is_none() {
+                    self.problem(
+                        item_type,
+                        format!("No plugin renders items of type {}", item_type),
+                    );
+                }
+            }
+            BlockItem::Table { .. }
+            | BlockItem::Title(_)
+            | BlockItem::Heading { .. }
+            | BlockItem::Nav { .. }
+            | BlockItem::RawHtml(_)
+            | BlockItem::StyleInline(_)
+            | BlockItem::Markdown(_)
+            | BlockItem::Code(_)
+            | BlockItem::Text(_)
+            | BlockItem::Link { .. }
+            | BlockItem::Date { .. }
+            | BlockItem::Summary { .. }
+            | BlockItem::Search { .. }
+            | BlockItem::Breadcrumbs { .. }
+            | BlockItem::Embed { .. }
+            | BlockItem::Youtube { .. }
+            | BlockItem::Button { .. }
+            | BlockItem::Input { .. }
+            | BlockItem::Textarea { .. }
+            | BlockItem::Select { .. }
+            | BlockItem::Comment(_)
+            | BlockItem::Br
+            | BlockItem::Separator(_)
+            | BlockItem::Spacer { .. }
+            | BlockItem::Math { .. }
+            | BlockItem::FootnoteRef { .. }
+            | BlockItem::Slot(_)
+            | BlockItem::Children
+            | BlockItem::LoopValue
+            | BlockItem::LoopValueFileName => {}
+        }
+    }
+
+    fn include(&mut self, name: &str) {
+        // names built from special values are only known while constructing
+        if name.contains('$') {
+            return;
+        }
+
+        if self.blocks.contains_key(name) {
+            self.includes.insert(name.to_string());
+        } else {
+            self.problem(name, format!("Included block {} does not exist", name));
+        }
+    }
+
+    /// Checks a file referenced relative to the input directory.
+    fn file(&mut self, kind: &str, path: &str) {
+        if !path.contains('$') && !self.input.join(path).is_file() {
+            self.problem(path, format!("{} {} does not exist", kind, path));
+        }
+    }
+
+    /// Checks the URL of an image or another asset. It is relative to the page the block ends
+    /// up in, which isn't known here, so it may be relative to the block's own directory or
+    /// the input root.
+    fn asset(&mut self, kind: &str, url: &str) {
+        if url.contains('$') {
+            return;
+        }
+
+        let block_dir = self
+            .block
+            .path
+            .parent()
+            .map(|dir| dir.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+
+        let exists = [block_dir.as_str(), ""].iter().any(|dir| {
+            match assets::resolve_url(url.split(['?', '#']).next().unwrap_or(url), dir) {
+                Some(path) => self.input.join(path).is_file(),
+                // external URLs aren't checked
+                None => true,
+            }
+        });
+
+        if !exists {
+            self.problem(url, format!("{} {} does not exist", kind, url));
+        }
+    }
+}
+
+/// Finds the include cycles in a graph of block names to the blocks they include, each as
+/// the path around it starting and ending with its smallest block name.
+fn include_cycles(includes: &BTreeMap<String, BTreeSet<String>>) -> BTreeSet<Vec<String>> {
+    fn visit(
+        name: &str,
+        includes: &BTreeMap<String, BTreeSet<String>>,
+        stack: &mut Vec<String>,
+        done: &mut BTreeSet<String>,
+        cycles: &mut BTreeSet<Vec<String>>,
+    ) {
+        if let Some(start) = stack.iter().position(|block| block == name) {
+            let mut cycle = stack[start..].to_vec();
+            let smallest = (0..cycle.len()).min_by_key(|i| &cycle[*i]).unwrap_or(0);
+            cycle.rotate_left(smallest);
+            cycle.push(cycle[0].clone());
+            cycles.insert(cycle);
+            return;
+        }
+
+        if done.contains(name) {
+            return;
+        }
+
+        stack.push(name.to_string());
+        for included in includes.get(name).into_iter().flatten() {
+            visit(included, includes, stack, done, cycles);
+        }
+        stack.pop();
+
+        done.insert(name.to_string());
+    }
+
+    let mut cycles = BTreeSet::new();
+    let mut done = BTreeSet::new();
+
+    for name in includes.keys() {
+        visit(name, includes, &mut Vec::new(), &mut done, &mut cycles);
+    }
+
+    cycles
+}
+
+/// Line number of the first line of `contents` containing `needle`, to point at roughly
+/// where a problem is.
+fn find_line(contents: &str, needle: &str) -> Option<usize> {
+    contents
+        .lines()
+        .position(|line| line.contains(needle))
+        .map(|index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{a11y::A11yConfig, colors::Color};
+
+    fn check_site(files: &[(&str, &str)], strict: bool) -> Vec<Problem> {
+        let input = tempfile::tempdir().unwrap();
+
+        for (path, contents) in files {
+            std::fs::write(input.path().join(path), contents).unwrap();
+        }
+
+        let mut options =
+            GenerateOptions::new(input.path().to_path_buf(), input.path().join("out"));
+        options.a11y = Some(A11yConfig {
+            background: "#fff".parse::<Color>().unwrap(),
+            strict,
+        });
+
+        check(&options).unwrap()
+    }
+
+    #[test]
+    fn strict_a11y_findings_are_problems() {
+        let problems = check_site(
+            &[
+                (
+                    "index.yml",
+                    "html:\n  head:\n    lang: en\n  body:\n    - image:\n        path: photo.png\n",
+                ),
+                ("photo.png", ""),
+            ],
+            true,
+        );
+
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert_eq!(problems[0].file, PathBuf::from("index.yml"));
+        assert!(
+            problems[0].message.contains("has no alt text"),
+            "{}",
+            problems[0]
+        );
+    }
+
+    #[test]
+    fn a11y_findings_are_warnings_by_default() {
+        let problems = check_site(&[("index.yml", "html:\n  body:\n    - text: hi\n")], false);
+
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt::{Display, Formatter};
+
+use super::colors::Color;
+
+/// Minimum contrast ratio for normal text under WCAG AA.
+pub const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct A11yFinding {
+    pub page: String,
+    pub block: String,
+    pub message: String,
+}
+
+impl Display for A11yFinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.page == self.block {
+            write!(f, "{}: {}", self.page, self.message)
+        } else {
+            write!(
+                f,
+                "{} (in block {}): {}",
+                self.page, self.block, self.message
+            )
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct A11yConfig {
+    pub background: Color,
+    pub strict: bool,
+}
+
+pub fn heading_level(html_type: &str) -> Option<usize> {
+    match html_type {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+pub fn looks_like_url(text: &str) -> bool {
+    let text = text.trim();
+
+    text.starts_with("http://")
+        || text.starts_with("https://")
+        || text.starts_with("www.")
+        || text.starts_with("mailto:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::block_builder::{BlockBuilder, BlockBuilderConfig};
+
+    /// Messages of the findings of a page with a `lang` and the given body items.
+    fn findings(body: &str) -> Vec<String> {
+        page_findings(&format!("html:\n  head:\n    lang: en\n  body:\n{}", body))
+    }
+
+    fn page_findings(page: &str) -> Vec<String> {
+        let input = tempfile::tempdir().unwrap();
+        std::fs::write(input.path().join("photo.png"), "").unwrap();
+
+        let mut config =
+            BlockBuilderConfig::new(input.path().to_path_buf(), input.path().join("out"));
+        config.a11y = Some(A11yConfig {
+            background: "#ffffff".parse().unwrap(),
+            strict: false,
+        });
+
+        let mut builder = BlockBuilder::new(config).unwrap();
+        builder.add_block("page", serde_yaml::from_str(page).unwrap());
+
+        builder
+            .construct_page("page")
+            .unwrap()
+            .a11y_findings
+            .into_iter()
+            .map(|finding| finding.message)
+            .collect()
+    }
+
+    #[test]
+    fn clean_page() {
+        let body = "    - block:\n        html_type: h1\n        items: [text: Title]\n    - image:\n        path: photo.png\n        alt: A photo\n    - link:\n        text: Docs\n        url: /docs\n        link_style:\n          style: plain\n";
+
+        assert_eq!(findings(body), Vec::<String>::new());
+    }
+
+    #[test]
+    fn image_without_alt() {
+        assert_eq!(
+            findings("    - image:\n        path: photo.png\n"),
+            ["Image photo.png has no alt text"]
+        );
+    }
+
+    #[test]
+    fn link_without_text() {
+        assert_eq!(
+            findings("    - link:\n        text: ' '\n        url: /docs\n        link_style:\n          style: plain\n"),
+            ["Link to /docs has no visible text"]
+        );
+    }
+
+    #[test]
+    fn link_text_is_url() {
+        assert_eq!(
+            findings("    - link:\n        text: https://example.com\n        url: https://example.com\n        link_style:\n          style: plain\n"),
+            ["Link text https://example.com is just a URL"]
+        );
+    }
+
+    #[test]
+    fn heading_level_jump() {
+        assert_eq!(
+            findings("    - block:\n        html_type: h1\n        items: [text: Title]\n    - block:\n        html_type: h3\n        items: [text: Section]\n"),
+            ["Heading level jumps from h1 to h3"]
+        );
+    }
+
+    #[test]
+    fn missing_lang() {
+        assert_eq!(
+            page_findings("html:\n  body:\n    - text: hi\n"),
+            ["<html> has no lang attribute (set `lang` in the head)"]
+        );
+    }
+
+    #[test]
+    fn low_contrast_link() {
+        assert_eq!(
+            findings("    - link:\n        text: Docs\n        url: /docs\n        link_style:\n          explicit:\n            underline: true\n            color:\n              normal: '#cccccc'\n"),
+            ["Link color #ccc has a contrast ratio of 1.61:1 against #fff, below the 4.5:1 minimum"]
+        );
+    }
+}
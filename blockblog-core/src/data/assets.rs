@@ -0,0 +1,224 @@
+use std::{
+    collections::BTreeMap,
+    fs::{read_dir, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+
+use super::config::SiteConfig;
+use super::data_files;
+use super::hash;
+use super::transforms;
+
+pub const ASSET_MANIFEST_FILE: &str = "asset-manifest.json";
+
+const FINGERPRINTED_EXTENSIONS: &[&str] = &[
+    "css", "js", "mjs", "png", "jpg", "jpeg", "gif", "svg", "webp", "avif", "ico", "woff", "woff2",
+    "ttf", "otf", "eot", "mp4", "webm", "mp3", "ogg",
+];
+
+/// Maps copied asset paths to their content-hashed names. Both sides are relative to the
+/// input/output root and use `/` as the separator.
+#[derive(Debug, Clone, Default)]
+pub struct AssetMap {
+    input_dir: PathBuf,
+    assets: BTreeMap<String, String>,
+}
+
+impl AssetMap {
+    /// Names the asset files under `input` that are copied to the output after their
+    /// hashes, leaving out ignored paths and data files. `hashes` are the known hashes of
+    /// input files, by their key in the map. Only the files missing from it are read.
+    ///
+    /// Files going through a transform are mapped under the name the transform outputs, and
+    /// hashed along with its command, as their output is only known once it ran.
+    pub fn fingerprint(
+        input: &Path,
+        site_config: &SiteConfig,
+        hashes: &BTreeMap<String, String>,
+    ) -> Result<Self> {
+        let mut asset_map = AssetMap {
+            input_dir: input.to_owned(),
+            assets: BTreeMap::new(),
+        };
+
+        asset_map.fingerprint_dir(input, site_config, hashes)?;
+
+        Ok(asset_map)
+    }
+
+    fn fingerprint_dir(
+        &mut self,
+        dir: &Path,
+        site_config: &SiteConfig,
+        hashes: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        for entry in read_dir(dir)? {
+            let path = entry?.path();
+            let relative = path.strip_prefix(&self.input_dir)?;
+
+            if site_config.is_ignored(relative) || data_files::is_data_file(relative) {
+                continue;
+            }
+
+            if path.is_dir() {
+                self.fingerprint_dir(&path, site_config, hashes)?;
+                continue;
+            } else if !path.is_file() {
+                continue;
+            }
+
+            let transform = transforms::find(&site_config.transforms, relative)?;
+            let output = match transform {
+                Some(transform) => {
+                    let file_name = path.file_name().unwrap().to_string_lossy();
+                    relative.with_file_name(transform.output_file_name(&file_name))
+                }
+                None => relative.to_owned(),
+            };
+
+            let ext = match output.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) if FINGERPRINTED_EXTENSIONS.contains(&ext.to_lowercase().as_str()) => {
+                    ext.to_string()
+                }
+                _ => continue,
+            };
+
+            let hash = match hashes.get(&relative_key(relative)) {
+                Some(hash) => hash.clone(),
+                None => hash::short_hash(&std::fs::read(&path)?),
+            };
+            let hash = match transform {
+                Some(transform) => {
+                    hash::short_hash(format!("{}\n{}", transform.command, hash).as_bytes())
+                }
+                None => hash,
+            };
+
+            let original = relative_key(&output);
+            let stem = output.file_stem().unwrap().to_string_lossy();
+            let hashed_name = format!("{}.{}.{}", stem, hash, ext);
+
+            let hashed = match original.rsplit_once('/') {
+                Some((dir, _)) => format!("{}/{}", dir, hashed_name),
+                None => hashed_name,
+            };
+
+            self.assets.insert(original, hashed);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the hashed file name for an input file, if it was fingerprinted. The path of a
+    /// transformed file is the one it has once transformed.
+    pub fn hashed_file_name(&self, source: &Path) -> Option<&str> {
+        let original = relative_key(source.strip_prefix(&self.input_dir).ok()?);
+
+        self.assets
+            .get(&original)
+            .map(|hashed| hashed.rsplit('/').next().unwrap_or(hashed))
+    }
+
+    /// Rewrites a URL pointing at a fingerprinted asset to its hashed name, keeping it
+    /// relative or root-relative like the original. `page_dir` is the directory of the page
+    /// the URL appears in, relative to the output root.
+    pub fn rewrite_url(&self, url: &str, page_dir: &str) -> String {
+        if self.assets.is_empty() || is_external(url) {
+            return url.to_string();
+        }
+
+        let split_at = url.find(['?', '#']).unwrap_or(url.len());
+        let (path, suffix) = url.split_at(split_at);
+
+        let hashed = match resolve_url(path, page_dir).and_then(|key| self.assets.get(&key)) {
+            Some(hashed) => hashed,
+            None => return url.to_string(),
+        };
+        let hashed_name = hashed.rsplit('/').next().unwrap_or(hashed);
+
+        match path.rsplit_once('/') {
+            Some((dir, _)) => format!("{}/{}{}", dir, hashed_name, suffix),
+            None => format!("{}{}", hashed_name, suffix),
+        }
+    }
+
+    pub fn write_manifest(&self, output: &Path) -> Result<()> {
+        let mut manifest = File::create(output.join(ASSET_MANIFEST_FILE))?;
+
+        manifest.write_all(serde_json::to_string_pretty(&self.assets)?.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Resolves a local URL against the directory of the page it appears in, returning the path
+/// it points to relative to the output root. External URLs resolve to `None`.
+pub fn resolve_url(url: &str, page_dir: &str) -> Option<String> {
+    if is_external(url) {
+        return None;
+    }
+
+    match url.strip_prefix('/') {
+        Some(root_relative) => normalize(root_relative),
+        None if page_dir.is_empty() => normalize(url),
+        None => normalize(&format!("{}/{}", page_dir, url)),
+    }
+}
+
+/// `path` with `/` as the separator, as the keys of the map use.
+pub fn relative_key(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_external(url: &str) -> bool {
+    url.contains("://")
+        || url.starts_with("//")
+        || url.starts_with("data:")
+        || url.starts_with("mailto:")
+        || url.starts_with('#')
+}
+
+fn normalize(path: &str) -> Option<String> {
+    let mut segments: Vec<&str> = Vec::new();
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    Some(segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_uses_known_hashes() {
+        let input = tempfile::tempdir().unwrap();
+        std::fs::create_dir(input.path().join("css")).unwrap();
+        std::fs::write(input.path().join("css/a.css"), "a").unwrap();
+        std::fs::write(input.path().join("b.css"), "b").unwrap();
+
+        let hashes = BTreeMap::from([("css/a.css".to_string(), "known".to_string())]);
+        let asset_map =
+            AssetMap::fingerprint(input.path(), &SiteConfig::default(), &hashes).unwrap();
+
+        assert_eq!(asset_map.assets["css/a.css"], "css/a.known.css");
+        assert_eq!(
+            asset_map.assets["b.css"],
+            format!("b.{}.css", hash::short_hash(b"b"))
+        );
+    }
+}
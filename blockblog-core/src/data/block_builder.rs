@@ -0,0 +1,5705 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    ffi::OsStr,
+    io::{self, Error, Read},
+    path::{Component, Path, PathBuf},
+    sync::LazyLock,
+};
+
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use color_eyre::{eyre::WrapErr, Result};
+use colored::Colorize;
+
+use super::a11y::{self, A11yConfig, A11yFinding};
+use super::assets::{self, AssetMap};
+use super::blocks::{
+    BlockItem, EscapableText, FileQuery, ForEachValues, FormField, GalleryImage, Head,
+    IncludeTarget, LinkStyle, MediaOptions, PageRegion, Script, SelectOption, Separator, SortOrder,
+    StyleSource, Tab, TemplateParam, TextContent,
+};
+use super::cache::Cache;
+use super::callout::{self, CalloutKind};
+use super::colors::{Color, LinkColor};
+use super::components;
+use super::config::{SiteConfig, SnippetPosition, SITE_CONFIG_FILE};
+use super::data_files::{self, DataFiles};
+use super::diagrams::{self, DiagramKind};
+use super::error;
+use super::feed;
+use super::frontmatter::{self, Frontmatter};
+use super::hash;
+use super::html::{self, Heading};
+use super::i18n::Translations;
+use super::images::{self, ImageVariant};
+use super::interpolate;
+use super::math;
+use super::nav::MenuEntry;
+use super::plugins;
+use super::search;
+use super::shortcodes::{self, Shortcode};
+use super::styles::{self, DarkMode, StyleRule, StyleRules};
+use super::taxonomy::{self, Tag};
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use log::{info, warn};
+
+/// An `$if` condition comparing two values with `==`, `!=` or `contains`.
+static COMPARISON_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.*?)\s*(==|!=|\scontains\s)\s*(.*)$").unwrap());
+
+static HTML_TYPE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9-]*$").unwrap());
+static ATTRIBUTE_NAME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z_:][A-Za-z0-9_.:-]*$").unwrap());
+static DATA_ATTRIBUTE_NAME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-z][a-z0-9-]*$").unwrap());
+static CLASS_NAME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^-?[A-Za-z_][A-Za-z0-9_-]*$").unwrap());
+static BLANK_LINE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\n[[:blank:]]*\n").unwrap());
+
+// The special values substituted in text. Each matches an optional escaping `\` along with
+// the value, so adjacent values don't share a boundary character.
+static LOOP_FIELD_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$loop_value((?:\.[A-Za-z0-9_]+)+)").unwrap());
+static LOOP_VALUE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$loop_value\b").unwrap());
+static LOOP_FILENAME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$loop_value_filename\b").unwrap());
+static ENV_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$env_([A-Z_][A-Z0-9_]*)\b").unwrap());
+static PARAM_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$param\.([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+static SITE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$site\.([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+static THEME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$theme\.([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+static BASE_URL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$base_url\b").unwrap());
+static DATE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$(build_date|file\.modified)\b").unwrap());
+static DATA_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$data((?:\.[A-Za-z0-9_]+)+)").unwrap());
+static TAG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$tag\.([A-Za-z_]+)").unwrap());
+static PAGINATION_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$pagination\.([A-Za-z_]+)").unwrap());
+static TRANSLATION_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$t\(([A-Za-z0-9_.-]+)\)").unwrap());
+
+pub struct BlockBuilderConfig<'a> {
+    pub input_dir: PathBuf,
+    pub output_dir: PathBuf,
+    pub indent_string: &'a str,
+    pub debug: bool,
+    pub inline_generated_css: bool,
+    pub css_vars: bool,
+    pub a11y: Option<A11yConfig>,
+    pub asset_map: AssetMap,
+    pub drafts: bool,
+    /// Include markdown files dated in the future.
+    pub future: bool,
+    pub site_config: SiteConfig,
+    /// Profile of the build, which decides the snippets added to pages.
+    pub profile: Option<String>,
+}
+
+impl<'a> BlockBuilderConfig<'a> {
+    pub fn new(input_dir: PathBuf, output_dir: PathBuf) -> Self {
+        Self {
+            input_dir,
+            output_dir,
+            indent_string: "    ",
+            debug: false,
+            inline_generated_css: false,
+            css_vars: false,
+            a11y: None,
+            asset_map: AssetMap::default(),
+            drafts: false,
+            future: false,
+            site_config: SiteConfig::default(),
+            profile: None,
+        }
+    }
+}
+
+pub struct BlockBuilder<'a> {
+    pub block_items: HashMap<String, BlockItem>,
+    /// Names of the blocks that come from component sets, which are never built as pages.
+    pub component_blocks: BTreeSet<String>,
+    pub config: BlockBuilderConfig<'a>,
+    pub cache: Cache,
+    pub data: DataFiles,
+    pub translations: Translations,
+
+    indent_level: usize,
+    current_page: String,
+    /// Output path of the first page of the page being constructed, relative to the output
+    /// directory.
+    current_path: PathBuf,
+    current_file: String,
+    current_loop_value: serde_yaml::Value,
+    /// The file a `$for_each` or `$paginate` over a file pattern is at.
+    current_loop_file: Option<PathBuf>,
+    current_params: BTreeMap<String, String>,
+    current_frontmatter: Option<Frontmatter>,
+    current_tag: Option<Tag>,
+    /// Frontmatter of the first markdown with frontmatter the page renders outside of a
+    /// loop, which the head takes defaults from.
+    page_frontmatter: Option<Frontmatter>,
+    /// The markdown file `page_frontmatter` is from, if it is from a file.
+    page_file: Option<PathBuf>,
+    /// Permalink of the first `html` item the page renders.
+    page_permalink: Option<String>,
+    /// Whether the page's markdown is a draft or dated in the future, and left out.
+    page_hidden: bool,
+
+    footnote_counter: usize,
+    footnotes: Vec<(String, String)>,
+    emitted_footnotes: HashSet<String>,
+    /// The page has math, so KaTeX is loaded in its head.
+    page_has_math: bool,
+    /// The page has diagrams that aren't pre-rendered, so Mermaid is loaded in its head.
+    page_has_mermaid: bool,
+
+    generated_styles: StyleRules,
+    /// Number of `tabs` items on the page so far, to give each its own radio group.
+    tab_groups: usize,
+
+    last_heading_level: usize,
+    a11y_findings: Vec<A11yFinding>,
+
+    dependencies: Dependencies,
+    images: BTreeSet<ImageVariant>,
+    generated_files: BTreeMap<PathBuf, String>,
+    /// Blocks being constructed, outermost first, to catch blocks including themselves.
+    include_stack: Vec<String>,
+    /// Slots of the included blocks being constructed, innermost last.
+    slot_stack: Vec<SlotFrame>,
+
+    pagination: Pagination,
+    /// When the build started, the same for every page.
+    build_date: DateTime<FixedOffset>,
+}
+
+pub struct Page {
+    pub html: String,
+    pub generated_styles: StyleRules,
+    pub a11y_findings: Vec<A11yFinding>,
+    pub dependencies: Dependencies,
+    /// The further pages of a paginated page, as paths relative to the output directory
+    /// and their HTML.
+    pub extra_pages: Vec<(PathBuf, String)>,
+    /// Resized and converted images the page uses.
+    pub images: BTreeSet<ImageVariant>,
+    /// Other files the page uses, like pre-rendered diagrams, by their path relative to the
+    /// output directory.
+    pub files: BTreeMap<PathBuf, String>,
+    /// Where the page is written, relative to the output directory.
+    pub path: PathBuf,
+    /// The page shows a draft or future-dated markdown file that is left out, so the page
+    /// is too.
+    pub hidden: bool,
+}
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Slot `$children` refers to.
+const CHILDREN_SLOT: &str = "children";
+
+/// The items a block fills the slots of a block it includes with, and the block and params
+/// they are constructed in.
+struct SlotFrame {
+    slots: BTreeMap<String, Vec<BlockItem>>,
+    file: String,
+    params: BTreeMap<String, String>,
+}
+
+/// Where the page being constructed is among the pages its `$paginate` blocks split it into.
+struct Pagination {
+    /// Number of the page being constructed, starting at 1.
+    number: usize,
+    /// Number of pages, once an earlier pass over the page found it.
+    page_count: Option<usize>,
+    /// Number of pages the `$paginate` blocks seen so far need.
+    found_page_count: Option<usize>,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            page_count: None,
+            found_page_count: None,
+        }
+    }
+}
+
+/// Output path, relative to the output directory, of page `number` of a paginated page
+/// whose first page is at `first`. Later pages go in a `page` directory, next to an
+/// `index.html` or inside a directory named after any other page. With pretty URLs each
+/// of them is an `index.html` of its own, in a directory named after its number.
+fn pagination_path(first: &Path, number: usize, pretty_urls: bool) -> PathBuf {
+    if number <= 1 {
+        return first.to_path_buf();
+    }
+
+    let dir = if first.file_stem() == Some(OsStr::new("index")) {
+        first
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_path_buf()
+    } else {
+        first.with_extension("")
+    };
+
+    if pretty_urls {
+        dir.join("page").join(number.to_string()).join("index.html")
+    } else {
+        dir.join("page").join(format!("{}.html", number))
+    }
+}
+
+/// Output path, relative to the output directory, that a permalink pattern gives the page
+/// `block_name`.
+fn permalink_path(
+    pattern: &str,
+    block_name: &str,
+    frontmatter: Option<&Frontmatter>,
+) -> Result<PathBuf> {
+    let (dir, stem) = block_name.rsplit_once('/').unwrap_or(("", block_name));
+    let field = |name| {
+        frontmatter
+            .and_then(|frontmatter| frontmatter.get(name))
+            .filter(|value| !value.is_empty())
+    };
+
+    let slug = field("slug")
+        .unwrap_or_else(|| taxonomy::slug(&field("title").unwrap_or_else(|| stem.to_string())));
+
+    let mut path = pattern
+        .replace("{name}", block_name)
+        .replace("{dir}", dir)
+        .replace("{stem}", stem)
+        .replace("{slug}", &slug);
+
+    if path.contains("{date}") {
+        let date = field("date").ok_or_else(|| {
+            Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} uses {{date}} in its permalink but has no date in its frontmatter",
+                    block_name
+                ),
+            )
+        })?;
+        path = path.replace("{date}", &date);
+    }
+
+    if path.is_empty() || path.ends_with('/') {
+        path.push_str("index.html");
+    }
+
+    let path: PathBuf = path
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+
+    if path
+        .components()
+        .any(|component| component.as_os_str() == "..")
+    {
+        return Err(Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid permalink {} for {}", pattern, block_name),
+        )
+        .into());
+    }
+
+    Ok(path)
+}
+
+/// URL of `to` relative to the page at `from`, both relative to the output directory.
+fn relative_url(from: &Path, to: &Path) -> String {
+    let from_dir = from
+        .parent()
+        .map(|dir| dir.components().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let to = to.components().collect::<Vec<_>>();
+
+    let common = from_dir
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    std::iter::repeat_n("..".to_string(), from_dir.len() - common)
+        .chain(
+            to[common..]
+                .iter()
+                .map(|component| component.as_os_str().to_string_lossy().to_string()),
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// URL of a menu entry as linked from the page at `page_path`. Menu URLs are relative to the
+/// output root, so they are rewritten to be relative to the page instead.
+fn menu_url(url: &str, page_path: &Path) -> String {
+    if url.starts_with('/') || assets::resolve_url(url, "").is_none() {
+        return url.to_string();
+    }
+
+    let split_at = url.find(['?', '#']).unwrap_or(url.len());
+    let (path, suffix) = url.split_at(split_at);
+
+    let mut relative = relative_url(page_path, Path::new(path));
+    if relative.is_empty() {
+        relative.push('.');
+    }
+    if path.is_empty() || path.ends_with('/') {
+        relative.push('/');
+    }
+
+    relative + suffix
+}
+
+/// The blocks, `ForEach` file patterns and other files a page was constructed from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Dependencies {
+    pub blocks: BTreeSet<String>,
+    pub patterns: BTreeSet<String>,
+    pub files: BTreeSet<PathBuf>,
+}
+
+impl Dependencies {
+    /// Whether changing the given blocks or files could change the page. `paths` are
+    /// relative to the input directory.
+    pub fn affected_by(&self, block_names: &[String], paths: &[PathBuf]) -> bool {
+        if block_names.iter().any(|name| self.blocks.contains(name))
+            || paths.iter().any(|path| self.files.contains(path))
+        {
+            return true;
+        }
+
+        self.patterns
+            .iter()
+            .any(|pattern| match glob::Pattern::new(pattern) {
+                Ok(pattern) => paths
+                    .iter()
+                    .any(|path| pattern.matches_path_with(path, for_each_match_options())),
+                Err(_) => true,
+            })
+    }
+}
+
+fn for_each_match_options() -> glob::MatchOptions {
+    glob::MatchOptions {
+        case_sensitive: false,
+        require_literal_separator: false,
+        require_literal_leading_dot: false,
+    }
+}
+
+impl<'a> BlockBuilder<'a> {
+    pub fn new(config: BlockBuilderConfig<'a>) -> Result<Self> {
+        let mut block_items =
+            Self::get_block_definitions(&config.input_dir, &config.input_dir, &config.site_config)?;
+        let component_blocks = Self::add_component_blocks(&config, &mut block_items)?;
+
+        Ok(Self {
+            block_items,
+            component_blocks,
+            data: DataFiles::load(&config.input_dir, &config.site_config)?,
+            translations: Translations::load(&config.input_dir, &config.site_config.i18n)?,
+            cache: Cache::new(config.site_config.typography),
+            config,
+            indent_level: 0,
+            generated_styles: StyleRules::new(),
+            tab_groups: 0,
+            current_page: String::new(),
+            current_path: PathBuf::new(),
+            current_file: String::new(),
+            current_loop_value: serde_yaml::Value::Null,
+            current_loop_file: None,
+            current_params: BTreeMap::new(),
+            current_frontmatter: None,
+            current_tag: None,
+            page_frontmatter: None,
+            page_file: None,
+            page_permalink: None,
+            page_hidden: false,
+            footnote_counter: 0,
+            footnotes: Vec::new(),
+            emitted_footnotes: HashSet::new(),
+            page_has_math: false,
+            page_has_mermaid: false,
+            generated_files: BTreeMap::new(),
+            last_heading_level: 0,
+            a11y_findings: Vec::new(),
+            dependencies: Dependencies::default(),
+            images: BTreeSet::new(),
+            include_stack: Vec::new(),
+            slot_stack: Vec::new(),
+            pagination: Pagination::default(),
+            build_date: chrono::Local::now().fixed_offset(),
+        })
+    }
+
+    pub fn construct_by_name(&mut self, block_name: &str) -> Result<String> {
+        let block = {
+            let block = self.block_items.get(block_name);
+            let block = block.ok_or_else(|| {
+                Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Block {} not found", block_name),
+                )
+            })?;
+            block.clone()
+        };
+
+        if self.include_stack.iter().any(|name| name == block_name) {
+            let cycle = self
+                .include_stack
+                .iter()
+                .skip_while(|name| *name != block_name)
+                .chain(std::iter::once(&block_name.to_string()))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Circular include: {}", cycle.join(" -> ")),
+            )
+            .into());
+        }
+
+        self.current_file = block_name.to_string();
+        self.dependencies.blocks.insert(block_name.to_string());
+
+        self.include_stack.push(block_name.to_string());
+        let constructed = self.construct_block(&block);
+        self.include_stack.pop();
+
+        constructed.wrap_err_with(|| format!("In block {}", block_name))
+    }
+
+    /// Constructs a top-level block as a standalone page, along with the generated
+    /// style rules that page needs.
+    pub fn construct_page(&mut self, block_name: &str) -> Result<Page> {
+        self.pagination = Pagination::default();
+        self.current_path = self.config.site_config.page_path(block_name);
+
+        let mut page = self.construct_single_page(block_name)?;
+
+        // a permalink can use frontmatter only known once the page is constructed, and the
+        // links in the page depend on where it ends up
+        let path = self.permalink(block_name)?;
+
+        if path != self.current_path {
+            self.current_path = path;
+            page = self.construct_single_page(block_name)?;
+        }
+
+        // how many pages there are is only known once every $paginate block was seen, so
+        // a paginated page is constructed again with the count known
+        let page_count = match self.pagination.found_page_count {
+            Some(page_count) => page_count,
+            None => return Ok(page),
+        };
+
+        let mut pages = Vec::new();
+        for number in 1..=page_count {
+            self.pagination = Pagination {
+                number,
+                page_count: Some(page_count),
+                found_page_count: None,
+            };
+            pages.push(self.construct_single_page(block_name)?);
+        }
+        self.pagination = Pagination::default();
+
+        let mut pages = pages.into_iter();
+        let mut page = pages.next().unwrap_or(page);
+
+        for (number, extra) in (2..).zip(pages) {
+            page.generated_styles.merge(&extra.generated_styles);
+            page.a11y_findings.extend(extra.a11y_findings);
+            page.images.extend(extra.images);
+            page.files.extend(extra.files);
+            page.extra_pages.push((
+                pagination_path(
+                    &self.current_path,
+                    number,
+                    self.config.site_config.pretty_urls,
+                ),
+                extra.html,
+            ));
+        }
+
+        Ok(page)
+    }
+
+    fn construct_single_page(&mut self, block_name: &str) -> Result<Page> {
+        self.generated_styles.clear();
+        self.tab_groups = 0;
+        self.a11y_findings.clear();
+        self.last_heading_level = 0;
+        self.indent_level = 0;
+        self.current_frontmatter = None;
+        self.page_frontmatter = None;
+        self.page_file = None;
+        self.page_permalink = None;
+        self.page_hidden = false;
+        self.dependencies = Dependencies::default();
+        self.images.clear();
+        self.generated_files.clear();
+        self.current_page = block_name.to_string();
+
+        let theme = &self.config.site_config.theme;
+        if !theme.is_empty() {
+            let dark_variables = theme.dark_variables();
+
+            self.generated_styles
+                .insert(":root".to_string(), theme.variables());
+
+            if !dark_variables.is_empty() {
+                self.insert_dark_style(":root", dark_variables);
+            }
+        }
+
+        let (source_block, tag) = self.page_source(block_name)?;
+        self.current_tag = tag;
+        self.note_page_meta(&source_block)?;
+
+        let html = self.construct_by_name(&source_block);
+
+        self.current_tag = None;
+        let html = html?;
+
+        Ok(Page {
+            html,
+            generated_styles: std::mem::take(&mut self.generated_styles),
+            a11y_findings: std::mem::take(&mut self.a11y_findings),
+            dependencies: std::mem::take(&mut self.dependencies),
+            extra_pages: Vec::new(),
+            images: std::mem::take(&mut self.images),
+            files: std::mem::take(&mut self.generated_files),
+            path: self.current_path.clone(),
+            hidden: self.page_hidden,
+        })
+    }
+
+    /// Output path of the page just constructed, from the permalink of its `html` item or
+    /// its frontmatter.
+    fn permalink(&self, block_name: &str) -> Result<PathBuf> {
+        let frontmatter = self.page_frontmatter.as_ref();
+        let pattern = self
+            .page_permalink
+            .clone()
+            .or_else(|| frontmatter.and_then(|frontmatter| frontmatter.get("permalink")));
+
+        match pattern {
+            Some(pattern) => permalink_path(&pattern, block_name, frontmatter),
+            None => Ok(self.current_path.clone()),
+        }
+    }
+
+    /// Output path of the page being constructed, relative to the output directory.
+    fn page_path(&self) -> PathBuf {
+        pagination_path(
+            &self.current_path,
+            self.pagination.number,
+            self.config.site_config.pretty_urls,
+        )
+    }
+
+    /// Returns the sorted names of the blocks that are built as pages. Templates are left
+    /// out, as they can only be constructed through an include that passes their params, and
+    /// so are the blocks of component sets.
+    /// Tag pages are included under their output path, in place of the blocks they are
+    /// constructed from.
+    pub fn page_names(&mut self) -> Result<Vec<String>> {
+        let tags_config = self.config.site_config.tags.clone();
+        let is_tag_template = |name: &String| {
+            tags_config.as_ref().is_some_and(|tags| {
+                *name == tags.template || Some(name) == tags.index_template.as_ref()
+            })
+        };
+
+        let mut block_names: Vec<String> = self
+            .block_items
+            .iter()
+            .filter(|(_, block)| !matches!(block, BlockItem::Template { .. }))
+            .map(|(name, _)| name.clone())
+            .filter(|name| !is_tag_template(name) && !self.component_blocks.contains(name))
+            .collect();
+
+        if let Some(tags_config) = &tags_config {
+            for tag in self.collect_tags()? {
+                block_names.push(tags_config.page_name(&tag.slug));
+            }
+
+            if tags_config.index_template.is_some() {
+                block_names.push(tags_config.index_page_name());
+            }
+        }
+
+        block_names.sort();
+        block_names.dedup();
+
+        Ok(block_names)
+    }
+
+    /// Returns the block a page is constructed from, and the tag it lists if it is a tag
+    /// page.
+    fn page_source(&mut self, page_name: &str) -> Result<(String, Option<Tag>)> {
+        if self.block_items.contains_key(page_name) {
+            return Ok((page_name.to_string(), None));
+        }
+
+        if let Some(tags_config) = self.config.site_config.tags.clone() {
+            if let Some(index_template) = &tags_config.index_template {
+                if page_name == tags_config.index_page_name() {
+                    return Ok((index_template.clone(), None));
+                }
+            }
+
+            if let Some(tag) = self
+                .collect_tags()?
+                .into_iter()
+                .find(|tag| tags_config.page_name(&tag.slug) == page_name)
+            {
+                return Ok((tags_config.template, Some(tag)));
+            }
+        }
+
+        Ok((page_name.to_string(), None))
+    }
+
+    /// Collects the frontmatter tags of the markdown files the tags config matches, sorted
+    /// by name.
+    fn collect_tags(&mut self) -> Result<Vec<Tag>> {
+        let pattern = match &self.config.site_config.tags {
+            Some(tags_config) => tags_config.pattern.clone(),
+            None => return Ok(Vec::new()),
+        };
+
+        let mut counts = BTreeMap::new();
+
+        for (_, _, page) in self.query_files(&pattern, &FileQuery::default())? {
+            for tag in page.map(|page| page.tags).unwrap_or_default() {
+                *counts.entry(tag).or_insert(0) += 1;
+            }
+        }
+
+        Ok(counts
+            .into_iter()
+            .map(|(name, count)| Tag {
+                slug: taxonomy::slug(&name),
+                name,
+                count,
+            })
+            .collect())
+    }
+
+    /// Looks up a `$tag.name` value on a tag page.
+    fn tag_value(&self, name: &str) -> Option<String> {
+        let tag = self.current_tag.as_ref()?;
+
+        match name {
+            "name" => Some(tag.name.clone()),
+            "slug" => Some(tag.slug.clone()),
+            "count" => Some(tag.count.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Every tag as a map of its name, slug, count and the URL of its page.
+    fn tag_values(&mut self) -> Result<Vec<serde_yaml::Value>> {
+        let tags_config = match self.config.site_config.tags.clone() {
+            Some(tags_config) => tags_config,
+            None => {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "$tags used in {} but no tags are set up in {}",
+                        self.current_file, SITE_CONFIG_FILE
+                    ),
+                )
+                .into())
+            }
+        };
+
+        let current = self.page_path();
+
+        Ok(self
+            .collect_tags()?
+            .into_iter()
+            .map(|tag| {
+                let url = self.config.site_config.page_url(relative_url(
+                    &current,
+                    &self
+                        .config
+                        .site_config
+                        .page_path(&tags_config.page_name(&tag.slug)),
+                ));
+
+                let mut map = serde_yaml::Mapping::new();
+                for (key, value) in [
+                    ("name", tag.name),
+                    ("slug", tag.slug),
+                    ("count", tag.count.to_string()),
+                    ("url", url),
+                ] {
+                    map.insert(key.into(), value.into());
+                }
+
+                serde_yaml::Value::Mapping(map)
+            })
+            .collect())
+    }
+
+    /// The posts sharing the most frontmatter values with the markdown being rendered, as
+    /// maps of their file name, path, title, date, summary and score, most related first.
+    fn related_values(&mut self) -> Result<Vec<serde_yaml::Value>> {
+        let related_config = match self.config.site_config.related.clone() {
+            Some(related_config) => related_config,
+            None => return Ok(Vec::new()),
+        };
+
+        let (file, page) = match (&self.current_loop_file, &self.current_frontmatter) {
+            (Some(file), Some(page)) => (file.clone(), page.clone()),
+            _ => match (&self.page_file, &self.page_frontmatter) {
+                (Some(file), Some(page)) => (file.clone(), page.clone()),
+                _ => {
+                    return Err(Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "$page.related used in {}, but no markdown file is being rendered",
+                            self.current_file
+                        ),
+                    )
+                    .into())
+                }
+            },
+        };
+
+        let file = without_cur_dir(&file);
+        let input_dir = without_cur_dir(&self.config.input_dir);
+
+        let mut related = self
+            .query_files(&related_config.pattern, &FileQuery::default())?
+            .into_iter()
+            .filter(|(path, _, _)| without_cur_dir(path) != file)
+            .filter_map(|(path, name, other)| {
+                let other = other?;
+                let score = related_config.score(&page, &other);
+                (score > 0).then_some((score, path, name, other))
+            })
+            .collect::<Vec<_>>();
+
+        // newer posts first among equally related ones
+        related.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| b.3.date.cmp(&a.3.date))
+                .then_with(|| a.2.cmp(&b.2))
+        });
+        related.truncate(related_config.count);
+
+        Ok(related
+            .into_iter()
+            .map(|(score, path, name, other)| {
+                let path = without_cur_dir(&path);
+                let path = path
+                    .strip_prefix(&input_dir)
+                    .unwrap_or(&path)
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                let mut map = serde_yaml::Mapping::new();
+                for (key, value) in [
+                    ("name", name),
+                    ("path", path),
+                    ("title", other.title.clone().unwrap_or_default()),
+                    ("date", other.date.clone().unwrap_or_default()),
+                    ("summary", other.summary_text()),
+                    ("score", score.to_string()),
+                ] {
+                    map.insert(key.into(), value.into());
+                }
+
+                serde_yaml::Value::Mapping(map)
+            })
+            .collect())
+    }
+
+    /// Constructs every page, returning the generated page for each one keyed by block
+    /// name, sorted by name.
+    pub fn construct_all(&mut self) -> Result<Vec<(String, Result<Page>)>> {
+        let page_names = self.page_names()?;
+
+        Ok(self.construct_pages(page_names))
+    }
+
+    pub fn construct_pages(&mut self, block_names: Vec<String>) -> Vec<(String, Result<Page>)> {
+        block_names
+            .into_iter()
+            .map(|block_name| {
+                let constructed = self.construct_page(&block_name);
+                (block_name, constructed)
+            })
+            .collect()
+    }
+
+    /// Returns the sorted names of all pages matching any of the given glob patterns,
+    /// failing if a pattern matches no page at all.
+    pub fn block_names_matching(&mut self, patterns: &[String]) -> Result<Vec<String>> {
+        let page_names = self.page_names()?;
+        let mut block_names = Vec::new();
+
+        for pattern in patterns {
+            let glob = glob::Pattern::new(pattern)?;
+
+            let matching = page_names
+                .iter()
+                .filter(|name| glob.matches(name))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            if matching.is_empty() {
+                return Err(Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No block matches {}", pattern),
+                )
+                .into());
+            }
+
+            block_names.extend(matching);
+        }
+
+        block_names.sort();
+        block_names.dedup();
+
+        Ok(block_names)
+    }
+
+    /// Adds a block definition that did not come from the input directory, replacing any
+    /// block with the same name.
+    pub fn add_block(&mut self, block_name: &str, block: BlockItem) {
+        self.block_items.insert(block_name.to_string(), block);
+    }
+
+    fn construct_block(&mut self, block: &BlockItem) -> Result<String> {
+        let mut output = String::new();
+
+        match block {
+            BlockItem::Include(IncludeTarget::Plain(name)) => {
+                let name = self.process_special_values(name)?;
+                output.push_str(
+                    self.include(&name, BTreeMap::new(), BTreeMap::new())?
+                        .as_str(),
+                );
+            }
+            BlockItem::Include(IncludeTarget::Verbose {
+                path,
+                params,
+                slots,
+                children,
+            }) => {
+                let path = self.process_special_values(path)?;
+                let mut processed_params = BTreeMap::new();
+                for (name, value) in params {
+                    processed_params.insert(name.clone(), self.process_special_values(value)?);
+                }
+
+                let mut slots = slots.clone();
+                if !children.is_empty() {
+                    slots
+                        .entry(CHILDREN_SLOT.to_string())
+                        .or_default()
+                        .extend(children.iter().cloned());
+                }
+
+                output.push_str(self.include(&path, processed_params, slots)?.as_str());
+            }
+            BlockItem::Layout {
+                name,
+                params,
+                slots,
+                ..
+            } => {
+                let name = self.process_special_values(name)?;
+                let mut processed_params = BTreeMap::new();
+                for (param, value) in params {
+                    processed_params.insert(param.clone(), self.process_special_values(value)?);
+                }
+                output.push_str(
+                    self.include(&name, processed_params, slots.clone())?
+                        .as_str(),
+                );
+            }
+            BlockItem::Slot(name) => {
+                let name = self.process_special_values(name)?;
+                output.push_str(self.slot(&name)?.as_str());
+            }
+            BlockItem::Children => output.push_str(self.slot(CHILDREN_SLOT)?.as_str()),
+            BlockItem::Template { params, items } => {
+                output.push_str(self.template(params, items)?.as_str());
+            }
+            BlockItem::Title(text) => {
+                let text = self.escapable_text(text)?;
+                output.push_str(&self.get_indent());
+                output.push_str(self.title(&text)?.as_str());
+            }
+            BlockItem::Heading {
+                text,
+                level,
+                id,
+                anchor,
+                style,
+            } => {
+                output.push_str(&self.get_indent());
+                output.push_str(
+                    self.heading(text, level.unwrap_or(2), id, *anchor, style)?
+                        .as_str(),
+                );
+            }
+            BlockItem::Block {
+                style,
+                id,
+                items,
+                html_type,
+                data_attrs,
+                attrs,
+            } => {
+                output.push_str(
+                    self.block(style, id, html_type, data_attrs, attrs, items)?
+                        .as_str(),
+                );
+            }
+            BlockItem::List {
+                ordered,
+                style,
+                items,
+            } => {
+                output.push_str(self.list(*ordered, style, items)?.as_str());
+            }
+            BlockItem::RawHtml(raw_html) => {
+                let raw_html = self.process_special_values(raw_html)?;
+                output.push_str(&self.get_indent());
+                output.push_str(&raw_html);
+            }
+            BlockItem::StyleInline(source) => {
+                output.push_str(self.style_inline(source)?.as_str());
+            }
+            BlockItem::Nav { style } => {
+                output.push_str(self.nav(style)?.as_str());
+            }
+            BlockItem::Table {
+                rows,
+                csv,
+                header,
+                style,
+            } => {
+                let rows = match (rows, csv) {
+                    (Some(_), Some(_)) => {
+                        return Err(Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("Table in {}: rows and csv are both set", self.current_file),
+                        )
+                        .into());
+                    }
+                    (Some(rows), None) => rows
+                        .iter()
+                        .map(|row| row.iter().map(frontmatter::value_to_string).collect())
+                        .collect(),
+                    (None, Some(path)) => {
+                        let path = self.process_special_values(path)?;
+                        self.csv_rows(&path)?
+                    }
+                    (None, None) => Vec::new(),
+                };
+
+                output.push_str(self.table(&rows, *header, style)?.as_str());
+            }
+            BlockItem::Markdown(markdown) => {
+                output.push_str(self.markdown(markdown)?.as_str());
+            }
+            BlockItem::MarkdownFile(path) => {
+                let path = self.process_special_values(path)?;
+                output.push_str(self.markdown_file(&path)?.as_str());
+            }
+            BlockItem::Toc {
+                path,
+                min_level,
+                max_level,
+                ordered,
+                style,
+            } => {
+                let path = self.process_special_values(path)?;
+                output.push_str(
+                    self.toc(&path, (*min_level, *max_level), *ordered, style)?
+                        .as_str(),
+                );
+            }
+            BlockItem::Code(code) => {
+                let code = self.escapable_text(code)?;
+                output.push_str(&self.get_indent());
+                output.push_str(self.code(&code)?.as_str());
+            }
+            BlockItem::CodeFile {
+                path,
+                language,
+                line_numbers,
+            } => {
+                let path = self.process_special_values(path)?;
+                output.push_str(&self.get_indent());
+                output.push_str(self.code_file(&path, language, *line_numbers)?.as_str());
+            }
+            BlockItem::Image {
+                path,
+                alt,
+                width,
+                height,
+                responsive,
+                format,
+            } => {
+                let path = self.process_special_values(path)?;
+                let image =
+                    if width.is_none() && height.is_none() && !responsive && format.is_none() {
+                        self.image(&path, alt)?
+                    } else {
+                        self.processed_image(&path, alt, (*width, *height), *responsive, format)?
+                    };
+                output.push_str(&self.get_indent());
+                output.push_str(image.as_str());
+            }
+            BlockItem::Text(TextContent::Plain(raw_text)) => {
+                let raw_text = self.process_special_values(raw_text)?;
+                output.push_str(&self.get_indent());
+                output.push_str(self.text(&raw_text)?.as_str());
+            }
+            BlockItem::Text(TextContent::Options {
+                text,
+                markdown,
+                paragraphs,
+                raw,
+            }) => {
+                let text = self.process_special_values(text)?;
+                output.push_str(&self.get_indent());
+                output.push_str(
+                    self.text_with_options(&text, *markdown, *paragraphs, *raw)?
+                        .as_str(),
+                );
+            }
+            BlockItem::Link {
+                text,
+                url,
+                link_style,
+                raw,
+            } => {
+                let text = self.process_special_values(text)?;
+                let text = if *raw { text } else { html::escape(&text) };
+                let url = self.process_special_values(url)?;
+                let url = html::escape(&self.asset_url(&url));
+                output.push_str(&self.get_indent());
+                output.push_str(self.link(&text, &url, link_style)?.as_str());
+            }
+            BlockItem::Date {
+                value,
+                file,
+                format,
+                style,
+            } => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.date(value, file, format, style)?.as_str());
+            }
+            BlockItem::Search { placeholder, style } => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.search(placeholder, style)?.as_str());
+            }
+            BlockItem::Summary { file, style } => {
+                output.push_str(self.summary(file, style)?.as_str());
+            }
+            BlockItem::Button {
+                text,
+                url,
+                action,
+                style,
+            } => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.button(text, url, action, style)?.as_str());
+            }
+            BlockItem::Form {
+                action,
+                method,
+                style,
+                items,
+            } => {
+                output.push_str(self.form(action, method, style, items)?.as_str());
+            }
+            BlockItem::Input {
+                field,
+                input_type,
+                placeholder,
+                value,
+            } => {
+                output.push_str(self.input(field, input_type, placeholder, value)?.as_str());
+            }
+            BlockItem::Textarea {
+                field,
+                placeholder,
+                rows,
+            } => {
+                output.push_str(self.textarea(field, placeholder, rows)?.as_str());
+            }
+            BlockItem::Select { field, options } => {
+                output.push_str(self.select(field, options)?.as_str());
+            }
+            BlockItem::Figure {
+                image,
+                alt,
+                width,
+                link_full_size,
+                items,
+                caption,
+                style,
+            } => {
+                let image = match image {
+                    Some(image) => Some(self.figure_image(image, alt, *width, *link_full_size)?),
+                    None if *link_full_size => {
+                        return Err(Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "Figure in {} has link_full_size but no image",
+                                self.current_file
+                            ),
+                        )
+                        .into());
+                    }
+                    None => None,
+                };
+
+                output.push_str(self.figure(image, items, caption, style)?.as_str());
+            }
+            BlockItem::Gallery {
+                pattern,
+                images,
+                columns,
+                thumbnail_width,
+                style,
+            } => {
+                let images = match (pattern, images) {
+                    (Some(pattern), None) => {
+                        let pattern = self.process_special_values(pattern)?;
+                        self.gallery_pattern_images(&pattern)?
+                    }
+                    (None, Some(images)) => images.clone(),
+                    _ => {
+                        return Err(Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "Gallery in {} needs exactly one of pattern and images",
+                                self.current_file
+                            ),
+                        )
+                        .into());
+                    }
+                };
+
+                output.push_str(
+                    self.gallery(
+                        &images,
+                        columns.unwrap_or(3),
+                        thumbnail_width.unwrap_or(300),
+                        style.as_deref().unwrap_or("gallery"),
+                    )?
+                    .as_str(),
+                );
+            }
+            BlockItem::Breadcrumbs {
+                home,
+                title,
+                separator,
+                style,
+            } => {
+                output.push_str(self.breadcrumbs(home, title, separator, style)?.as_str());
+            }
+            BlockItem::Details {
+                summary,
+                open,
+                style,
+                items,
+            } => {
+                output.push_str(self.details(summary, *open, style, items)?.as_str());
+            }
+            BlockItem::Callout {
+                kind,
+                title,
+                style,
+                items,
+            } => {
+                output.push_str(self.callout(*kind, title, style, items)?.as_str());
+            }
+            BlockItem::Quote {
+                text,
+                items,
+                author,
+                cite,
+                style,
+            } => {
+                output.push_str(self.quote(text, items, author, cite, style)?.as_str());
+            }
+            BlockItem::Tabs { tabs, style } => {
+                output.push_str(self.tabs(tabs, style)?.as_str());
+            }
+            BlockItem::Video {
+                path,
+                poster,
+                options,
+            } => {
+                let mut attributes = String::new();
+
+                if let Some(poster) = poster {
+                    let poster = self.process_special_values(poster)?;
+                    attributes.push_str(&format!(
+                        " poster=\"{}\"",
+                        html::escape(&self.asset_url(&poster))
+                    ));
+                }
+
+                output.push_str(&self.get_indent());
+                output.push_str(self.media("video", path, &attributes, options)?.as_str());
+            }
+            BlockItem::Audio { path, options } => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.media("audio", path, "", options)?.as_str());
+            }
+            BlockItem::Embed {
+                url,
+                title,
+                width,
+                height,
+                style,
+            } => {
+                let url = self.process_special_values(url)?;
+                output.push_str(&self.get_indent());
+                output.push_str(self.embed(&url, title, (*width, *height), style)?.as_str());
+            }
+            BlockItem::Youtube {
+                id,
+                title,
+                start,
+                style,
+            } => {
+                let id = self.process_special_values(id)?;
+                let mut url = format!(
+                    "https://www.youtube-nocookie.com/embed/{}",
+                    interpolate::url_encode(&id)
+                );
+
+                if let Some(start) = start {
+                    url.push_str(&format!("?start={}", start));
+                }
+
+                output.push_str(&self.get_indent());
+                output.push_str(
+                    self.embed(&url, title, (Some(560), Some(315)), style)?
+                        .as_str(),
+                );
+            }
+            BlockItem::Comment(comment) => {
+                // not even an empty line is left
+                if !self.config.debug {
+                    return Ok(String::new());
+                }
+
+                output.push_str(&self.get_indent());
+                // no run of `-` can end the comment early, and the reference isn't decoded in
+                // a comment, so the text stays readable
+                output.push_str(&format!("<!-- {} -->", comment.replace('-', "&#45;")));
+            }
+            BlockItem::Br => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.br()?.as_str());
+            }
+            BlockItem::Separator(separator) => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.separator(separator)?.as_str());
+            }
+            BlockItem::Spacer { height, style } => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.spacer(height, style)?.as_str());
+            }
+            BlockItem::Math {
+                tex,
+                display,
+                style,
+            } => {
+                let tex = self.process_special_values(tex)?;
+                self.page_has_math = true;
+
+                output.push_str(&self.get_indent());
+                output.push_str(&math::markup(
+                    &tex,
+                    display.unwrap_or(true),
+                    style.as_deref(),
+                ));
+            }
+            BlockItem::Diagram {
+                source,
+                file,
+                kind,
+                title,
+                style,
+            } => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.diagram(source, file, *kind, title, style)?.as_str());
+            }
+            BlockItem::Plugin { item_type, fields } => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.plugin_item(item_type, fields)?.trim_end());
+            }
+            BlockItem::ForEach {
+                values,
+                pattern,
+                query,
+                items,
+            } => {
+                if values.is_some() && pattern.is_some() {
+                    return Err(Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "ForEach: values and pattern are both set",
+                    )
+                    .into());
+                }
+
+                if pattern.is_none() && !query.is_empty() {
+                    return Err(Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "ForEach: sort_by, order, limit and filter can only be used with pattern",
+                    )
+                    .into());
+                }
+
+                if let Some(what) = values {
+                    let values = self.for_each_values(what)?;
+                    output.push_str(self.for_each(&values, items)?.as_str());
+                }
+
+                if let Some(what) = pattern {
+                    output.push_str(self.for_each_file(what, query, items)?.as_str());
+                }
+            }
+            BlockItem::Paginate {
+                pattern,
+                per_page,
+                query,
+                items,
+            } => {
+                output.push_str(self.paginate(pattern, *per_page, query, items)?.as_str());
+            }
+            BlockItem::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let items = if self.condition(condition)? {
+                    then
+                } else {
+                    otherwise
+                };
+
+                for item in items {
+                    output.push_str(&self.construct_block(item)?);
+                }
+            }
+            BlockItem::LoopValue => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.loop_value()?.as_str())
+            }
+            BlockItem::LoopValueFileName => {
+                output.push_str(&self.get_indent());
+                output.push_str(self.loop_value_filename()?.as_str())
+            }
+            BlockItem::FootnoteRef { id, text } => {
+                let id = self.process_special_values(id)?;
+                let text = self.process_special_values(text)?;
+                output.push_str(&self.get_indent());
+                output.push_str(self.footnote_ref(&id, &text)?.as_str());
+            }
+            BlockItem::Footnote { id, content } => {
+                let id = self.process_special_values(id)?;
+                output.push_str(self.footnote(&id, content)?.as_str());
+            }
+            BlockItem::Html {
+                head,
+                body,
+                permalink,
+                header,
+                footer,
+                ..
+            } => output.push_str(self.html(head, body, permalink, header, footer)?.as_str()),
+        }
+
+        output.push('\n');
+
+        Ok(output)
+    }
+
+    /// Adds the blocks of every component set under its namespace, returning their names.
+    fn add_component_blocks(
+        config: &BlockBuilderConfig,
+        block_items: &mut HashMap<String, BlockItem>,
+    ) -> Result<BTreeSet<String>> {
+        let mut names = BTreeSet::new();
+
+        for (namespace, dir) in components::component_dirs(&config.input_dir, &config.site_config)?
+        {
+            let dir = config.input_dir.join(dir);
+
+            for (name, item) in Self::get_block_definitions(&dir, &dir, &config.site_config)? {
+                let block_name = format!("{}/{}", namespace, name);
+
+                if block_items.contains_key(&block_name) {
+                    return Err(Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!(
+                            "Block {} of component set {} has the same name as a block of the site",
+                            block_name, namespace
+                        ),
+                    )
+                    .into());
+                }
+
+                block_items.insert(block_name.clone(), item);
+                names.insert(block_name);
+            }
+        }
+
+        Ok(names)
+    }
+
+    fn get_block_definitions(
+        input: &Path,
+        base_input: &Path,
+        site_config: &SiteConfig,
+    ) -> Result<HashMap<String, BlockItem>> {
+        let mut definitions = HashMap::new();
+
+        let dir = input.exists() && input.is_dir();
+
+        if dir {
+            for entry in std::fs::read_dir(input)? {
+                let entry = entry?;
+                let path = entry.path();
+                let path_relative_to_input = path.clone();
+                let path_relative_to_input = path_relative_to_input.strip_prefix(base_input)?;
+
+                if site_config.is_ignored(path_relative_to_input)
+                    || data_files::is_data_file(path_relative_to_input)
+                {
+                    continue;
+                }
+
+                if path.is_dir() {
+                    let mut block_items =
+                        Self::get_block_definitions(&path, base_input, site_config)?;
+
+                    for (name, item) in block_items.drain() {
+                        let block_name =
+                            format!("{}/{}", path_relative_to_input.to_str().unwrap(), name);
+                        definitions.insert(block_name, item);
+                    }
+                } else if path.is_file() {
+                    // anything else, like a Makefile or LICENSE, is an asset or not used
+                    let is_yml = path.extension().is_some_and(|ext| ext == "yml");
+
+                    if is_yml && path != base_input.join(SITE_CONFIG_FILE) {
+                        let mut file = std::fs::File::open(path.clone())?;
+                        let mut contents = String::new();
+                        file.read_to_string(&mut contents)?;
+
+                        let item: BlockItem = error::parse_yaml_resolved(
+                            path_relative_to_input,
+                            &contents,
+                            |value| site_config.theme.resolve_references(value),
+                        )?;
+                        definitions
+                            .insert(path.file_stem().unwrap().to_str().unwrap().into(), item);
+                    }
+                }
+            }
+        }
+
+        Ok(definitions)
+    }
+
+    fn html(
+        &mut self,
+        head: &Option<Head>,
+        body: &Option<Vec<BlockItem>>,
+        permalink: &Option<String>,
+        header: &Option<PageRegion>,
+        footer: &Option<PageRegion>,
+    ) -> Result<String> {
+        let mut output = String::new();
+        let header = page_region(&self.config.site_config.header, header);
+        let footer = page_region(&self.config.site_config.footer, footer);
+
+        if let (Some(permalink), None) = (permalink, &self.page_permalink) {
+            self.page_permalink = Some(self.process_special_values(permalink)?);
+        }
+
+        self.footnote_counter = 0;
+        self.footnotes.clear();
+        self.emitted_footnotes.clear();
+        self.page_has_math = false;
+        self.page_has_mermaid = false;
+
+        // the body is built first so that the head can carry the styles it generated
+        let mut body_output = String::new();
+
+        self.indent_level += 2;
+        if let Some(header) = &header {
+            body_output.push_str(self.construct_block(header)?.as_str());
+        }
+        if let Some(body) = body {
+            for item in body {
+                body_output.push_str(self.construct_block(item)?.as_str());
+            }
+        }
+        body_output.push_str(&self.emit_footnotes());
+        if let Some(footer) = &footer {
+            body_output.push_str(self.construct_block(footer)?.as_str());
+        }
+        self.indent_level -= 2;
+
+        let head = match head {
+            Some(head) => Some(self.head_values(head)?),
+            None => None,
+        };
+        let head = self.merged_head(&head);
+
+        output.push_str("<!DOCTYPE html>\n");
+        match &head.lang {
+            Some(lang) => output.push_str(&format!("<html lang=\"{}\">\n", html::escape(lang))),
+            None => {
+                self.a11y_finding("<html> has no lang attribute (set `lang` in the head)");
+                output.push_str("<html>\n");
+            }
+        }
+
+        self.indent_level += 1;
+        output.push_str(&self.get_indent());
+
+        output.push_str("<head>\n");
+        self.indent_level += 1;
+        output.push_str(&self.get_indent());
+        output.push_str("<meta charset=\"utf-8\">\n");
+
+        if let Some(title) = &head.title {
+            output.push_str(&self.get_indent());
+            output.push_str(&format!("<title>{}</title>\n", html::escape(title)));
+        }
+
+        output.push_str(&self.head_meta(&head));
+
+        if let Some(icon) = &head.icon {
+            output.push_str(&self.get_indent());
+            output.push_str(&format!(
+                "<link rel=\"icon\" href=\"{}\" type=\"image/x-icon\" />\n",
+                html::escape(&self.asset_url(icon))
+            ));
+        }
+
+        let page_styles = self.config.site_config.page_styles && !self.config.inline_generated_css;
+        // generated_style.css isn't written when the generated styles go elsewhere
+        let shared_styles_written = !page_styles && !self.config.inline_generated_css;
+
+        if let Some(styles) = &head.styles {
+            for style in styles {
+                if !shared_styles_written && is_shared_style_url(style) {
+                    continue;
+                }
+
+                output.push_str(&self.get_indent());
+                output.push_str(&format!(
+                    "<link rel=\"stylesheet\" href=\"{}\" />\n",
+                    html::escape(&self.asset_url(style))
+                ));
+            }
+        }
+
+        if page_styles && !self.generated_styles.is_empty() {
+            let style_path = styles::page_style_path(&self.current_path);
+
+            output.push_str(&self.get_indent());
+            output.push_str(&format!(
+                "<link rel=\"stylesheet\" href=\"{}\" />\n",
+                html::escape(&relative_url(&self.page_path(), &style_path))
+            ));
+        }
+
+        if let Some(scripts) = &head.scripts {
+            for script in scripts {
+                output.push_str(&self.get_indent());
+                output.push_str(&self.script(script));
+                output.push('\n');
+            }
+        }
+
+        if self.page_has_math {
+            let (katex_css, katex_js) = self.config.site_config.math.katex_urls();
+
+            for tag in [
+                format!(
+                    "<link rel=\"stylesheet\" href=\"{}\" />",
+                    html::escape(&self.asset_url(&katex_css))
+                ),
+                self.script(&Script::Options {
+                    src: katex_js,
+                    defer: true,
+                    is_async: false,
+                    module: false,
+                    integrity: None,
+                    crossorigin: None,
+                }),
+                math::render_script(),
+            ] {
+                output.push_str(&self.get_indent());
+                output.push_str(&tag);
+                output.push('\n');
+            }
+        }
+
+        if self.page_has_mermaid {
+            output.push_str(&self.get_indent());
+            output.push_str(&diagrams::mermaid_script(
+                &self.asset_url(&self.config.site_config.diagrams.mermaid),
+            ));
+            output.push('\n');
+        }
+
+        output.push_str(&self.snippets(SnippetPosition::Head));
+
+        if self.config.inline_generated_css && !self.generated_styles.is_empty() {
+            output.push_str(&self.get_indent());
+            output.push_str("<style>\n");
+
+            self.indent_level += 1;
+            let indent = self.get_indent();
+            for line in self.generated_styles.render().trim_end().lines() {
+                if !line.is_empty() {
+                    output.push_str(&indent);
+                    output.push_str(line);
+                }
+                output.push('\n');
+            }
+            self.indent_level -= 1;
+
+            output.push_str(&self.get_indent());
+            output.push_str("</style>\n");
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</head>\n");
+        output.push_str(&self.get_indent());
+        output.push_str("<body>\n");
+
+        output.push_str(&body_output);
+
+        self.indent_level += 1;
+        output.push_str(&self.snippets(SnippetPosition::Body));
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str("</body>\n");
+        self.indent_level -= 1;
+
+        output.push_str("</html>\n");
+        debug_assert_eq!(self.indent_level, 0);
+        Ok(output)
+    }
+
+    fn script(&self, script: &Script) -> String {
+        let (src, defer, is_async, module, integrity, crossorigin) = match script {
+            Script::Plain(src) => (src, false, false, false, &None, &None),
+            Script::Options {
+                src,
+                defer,
+                is_async,
+                module,
+                integrity,
+                crossorigin,
+            } => (src, *defer, *is_async, *module, integrity, crossorigin),
+        };
+
+        let mut attributes = format!(" src=\"{}\"", html::escape(&self.asset_url(src)));
+
+        if module {
+            attributes.push_str(" type=\"module\"");
+        }
+        if defer {
+            attributes.push_str(" defer");
+        }
+        if is_async {
+            attributes.push_str(" async");
+        }
+        if let Some(integrity) = integrity {
+            attributes.push_str(&format!(" integrity=\"{}\"", html::escape(integrity)));
+        }
+        if let Some(crossorigin) = crossorigin {
+            attributes.push_str(&format!(" crossorigin=\"{}\"", html::escape(crossorigin)));
+        }
+
+        // a self-closed <script /> is not closed at all in HTML
+        format!("<script{}></script>", attributes)
+    }
+
+    /// The description, canonical link, Open Graph and Twitter card tags and other meta tags
+    /// of a merged head.
+    fn head_meta(&self, head: &Head) -> String {
+        let mut tags = Vec::new();
+        let meta = |attribute: &str, name: &str, content: &str| {
+            format!(
+                "<meta {}=\"{}\" content=\"{}\" />",
+                attribute,
+                html::escape(name),
+                html::escape(content)
+            )
+        };
+
+        if let Some(description) = &head.description {
+            tags.push(meta("name", "description", description));
+        }
+
+        if let Some(canonical) = &head.canonical {
+            tags.push(format!(
+                "<link rel=\"canonical\" href=\"{}\" />",
+                html::escape(canonical)
+            ));
+        }
+
+        tags.extend(self.language_alternates());
+
+        // shared links are fetched from elsewhere, so the image needs an absolute URL if
+        // one can be made
+        let image = head.image.as_ref().map(|image| {
+            let page_dir = self.page_dir();
+
+            let url = self.asset_url(image);
+            match assets::resolve_url(&url, &page_dir) {
+                Some(path) => self
+                    .config
+                    .site_config
+                    .absolute_url(Path::new(&path))
+                    .unwrap_or(url),
+                None => url,
+            }
+        });
+
+        if head.open_graph.unwrap_or(false) {
+            let kind = if self.page_frontmatter.is_some() {
+                "article"
+            } else {
+                "website"
+            };
+            tags.push(meta("property", "og:type", kind));
+
+            if let Some(title) = &head.title {
+                tags.push(meta("property", "og:title", title));
+            }
+            if let Some(description) = &head.description {
+                tags.push(meta("property", "og:description", description));
+            }
+            if let Some(image) = &image {
+                tags.push(meta("property", "og:image", image));
+            }
+            if let Some(canonical) = &head.canonical {
+                tags.push(meta("property", "og:url", canonical));
+            }
+            if let Some(site_title) = &self.config.site_config.title {
+                tags.push(meta("property", "og:site_name", site_title));
+            }
+        }
+
+        if let Some(card) = &head.twitter_card {
+            tags.push(meta("name", "twitter:card", card));
+
+            if let Some(title) = &head.title {
+                tags.push(meta("name", "twitter:title", title));
+            }
+            if let Some(description) = &head.description {
+                tags.push(meta("name", "twitter:description", description));
+            }
+            if let Some(image) = &image {
+                tags.push(meta("name", "twitter:image", image));
+            }
+        }
+
+        for (name, content) in head.meta.iter().flatten() {
+            let attribute = if name.starts_with("og:") {
+                "property"
+            } else {
+                "name"
+            };
+            tags.push(meta(attribute, name, content));
+        }
+
+        let indent = self.get_indent();
+        tags.into_iter()
+            .map(|tag| format!("{}{}\n", indent, tag))
+            .collect()
+    }
+
+    /// Combines a page's head with the site-wide default head: page values win, and the
+    /// site's styles and scripts come before the page's own. The title, description and
+    /// image fall back to the page's markdown frontmatter before the site-wide values.
+    /// Substitutes special values in the text fields of a head, so a layout can take its
+    /// title and description as params.
+    fn head_values(&mut self, head: &Head) -> Result<Head> {
+        let mut head = head.clone();
+
+        for value in [
+            &mut head.title,
+            &mut head.description,
+            &mut head.canonical,
+            &mut head.image,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *value = self.process_special_values(value)?;
+        }
+
+        Ok(head)
+    }
+
+    fn merged_head(&self, head: &Option<Head>) -> Head {
+        let site = &self.config.site_config;
+        let default = site.head.clone().unwrap_or_default();
+        let head = head.clone().unwrap_or_default();
+
+        fn concat<T>(first: Option<Vec<T>>, second: Option<Vec<T>>) -> Option<Vec<T>> {
+            match (first, second) {
+                (Some(mut first), Some(second)) => {
+                    first.extend(second);
+                    Some(first)
+                }
+                (first, second) => first.or(second),
+            }
+        }
+
+        let meta = match (default.meta, head.meta) {
+            (Some(mut meta), Some(page_meta)) => {
+                meta.extend(page_meta);
+                Some(meta)
+            }
+            (meta, page_meta) => page_meta.or(meta),
+        };
+
+        let page = self.page_frontmatter.as_ref();
+
+        Head {
+            lang: head.lang.or_else(|| self.page_language()).or(default.lang),
+            title: head
+                .title
+                .or_else(|| page.and_then(|page| page.title.clone()))
+                .or(default.title)
+                .or_else(|| site.title.clone()),
+            description: head
+                .description
+                .or_else(|| page.and_then(|page| page.get("description")))
+                .or(default.description),
+            canonical: head.canonical.or(default.canonical).or_else(|| {
+                site.absolute_url(&self.page_path())
+                    .map(|url| site.page_url(url))
+            }),
+            image: head
+                .image
+                .or_else(|| page.and_then(|page| page.get("image")))
+                .or(default.image),
+            open_graph: head.open_graph.or(default.open_graph),
+            twitter_card: head.twitter_card.or(default.twitter_card),
+            meta,
+            icon: head.icon.or(default.icon),
+            styles: concat(default.styles, head.styles),
+            scripts: concat(default.scripts, head.scripts),
+        }
+    }
+
+    /// Constructs another block in place, with `params` for a template and `slots` filling
+    /// its `$slot` items.
+    fn include(
+        &mut self,
+        included_block_name: &str,
+        params: BTreeMap<String, String>,
+        slots: BTreeMap<String, Vec<BlockItem>>,
+    ) -> Result<String> {
+        info!(
+            "Including block {} in {}",
+            included_block_name.cyan().bold(),
+            self.current_file.cyan().bold()
+        );
+        match self.block_items.get(included_block_name) {
+            Some(BlockItem::Template { .. }) => (),
+            Some(_) if !params.is_empty() => {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Block {} is included with params in {} but is not a template",
+                        included_block_name, self.current_file
+                    ),
+                )
+                .into())
+            }
+            Some(_) => (),
+            None => {
+                return Err(Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Block {} not found", included_block_name),
+                )
+                .into())
+            }
+        }
+
+        let mut output = String::new();
+
+        if self.config.debug {
+            output.push_str(&self.get_indent());
+            output.push_str(format!("<!-- Including block {} -->\n", included_block_name).as_str());
+        }
+
+        let old_file = self.current_file.clone();
+        let old_params = std::mem::replace(&mut self.current_params, params);
+        self.current_file = included_block_name.to_string();
+
+        self.slot_stack.push(SlotFrame {
+            slots,
+            file: old_file.clone(),
+            params: old_params.clone(),
+        });
+
+        let constructed = self.construct_by_name(included_block_name);
+
+        self.slot_stack.pop();
+        self.current_file = old_file;
+        self.current_params = old_params;
+
+        output.push_str(constructed?.as_str());
+
+        Ok(output)
+    }
+
+    /// Constructs the items a slot of the innermost included block is filled with, in the
+    /// block that filled it, where `$slot` refers to the block included around that one.
+    fn slot(&mut self, name: &str) -> Result<String> {
+        let frame = self.slot_stack.pop().ok_or_else(|| {
+            Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "$slot {} used in {}, which is not included anywhere",
+                    name, self.current_file
+                ),
+            )
+        })?;
+
+        let old_file = std::mem::replace(&mut self.current_file, frame.file.clone());
+        let old_params = std::mem::replace(&mut self.current_params, frame.params.clone());
+
+        let constructed = frame
+            .slots
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|item| self.construct_block(item))
+            .collect::<Result<String>>();
+
+        self.current_file = old_file;
+        self.current_params = old_params;
+        self.slot_stack.push(frame);
+
+        constructed
+    }
+
+    /// Constructs the items of a template, after checking that the params it was included
+    /// with are exactly the ones it declares.
+    fn template(&mut self, declared: &[TemplateParam], items: &[BlockItem]) -> Result<String> {
+        for param in declared {
+            if let Some(default) = param.default() {
+                self.current_params
+                    .entry(param.name().to_string())
+                    .or_insert_with(|| default.to_string());
+            }
+        }
+
+        let missing = declared
+            .iter()
+            .filter(|param| !self.current_params.contains_key(param.name()))
+            .map(TemplateParam::describe)
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Template {} is missing params: {}",
+                    self.current_file,
+                    missing.join(", ")
+                ),
+            )
+            .into());
+        }
+
+        let unknown = self
+            .current_params
+            .keys()
+            .filter(|name| !declared.iter().any(|param| param.name() == name.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !unknown.is_empty() {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Template {} does not take params: {}",
+                    self.current_file,
+                    unknown.join(", ")
+                ),
+            )
+            .into());
+        }
+
+        for param in declared {
+            let value = &self.current_params[param.name()];
+
+            if !param.param_type().accepts(value) {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Template {} takes param {}, got {:?}",
+                        self.current_file,
+                        param.describe(),
+                        value
+                    ),
+                )
+                .into());
+            }
+        }
+
+        let mut output = String::new();
+
+        for item in items {
+            output.push_str(&self.construct_block(item)?);
+        }
+
+        Ok(output)
+    }
+
+    fn title(&mut self, title: &String) -> Result<String> {
+        self.check_heading_level(1);
+
+        Ok(format!("<h1>{}</h1>", title))
+    }
+
+    /// The site config's snippets for a place in every page, in the profile of the build.
+    fn snippets(&self, position: SnippetPosition) -> String {
+        let mut output = String::new();
+        let indent = self.get_indent();
+
+        for snippet in self.config.site_config.snippets.iter() {
+            let in_profile = snippet.profiles.is_empty()
+                || self
+                    .config
+                    .profile
+                    .as_ref()
+                    .is_some_and(|profile| snippet.profiles.contains(profile));
+
+            if snippet.position != position || !in_profile {
+                continue;
+            }
+
+            for line in snippet.html.trim_end().lines() {
+                if !line.is_empty() {
+                    output.push_str(&indent);
+                    output.push_str(line);
+                }
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+
+    fn heading(
+        &mut self,
+        text: &str,
+        level: usize,
+        id: &Option<String>,
+        anchor: bool,
+        style: &Option<String>,
+    ) -> Result<String> {
+        if !(1..=6).contains(&level) {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Heading level {} in {} is not between 1 and 6",
+                    level, self.current_file
+                ),
+            )
+            .into());
+        }
+
+        self.check_heading_level(level);
+
+        let text = self.process_special_values(text)?;
+        let id = match id {
+            Some(id) => Some(self.process_special_values(id)?),
+            None if anchor => Some(html::slugify(&text)),
+            None => None,
+        };
+        let mut attributes = String::new();
+
+        if let Some(id) = &id {
+            attributes.push_str(&format!(" id=\"{}\"", html::escape(id)));
+        }
+
+        if let Some(style) = style {
+            attributes.push_str(&format!(" class=\"{}\"", html::escape(style)));
+        }
+
+        let anchor = match (&id, anchor) {
+            (Some(id), true) => html::heading_anchor(id),
+            _ => String::new(),
+        };
+
+        Ok(format!(
+            "<h{level}{}>{}{}</h{level}>",
+            attributes,
+            html::escape(&text),
+            anchor
+        ))
+    }
+
+    fn block(
+        &mut self,
+        style: &Option<String>,
+        id: &Option<String>,
+        html_type: &Option<String>,
+        data_attrs: &Option<BTreeMap<String, String>>,
+        attrs: &Option<BTreeMap<String, String>>,
+        items: &[BlockItem],
+    ) -> Result<String> {
+        let mut output = String::new();
+        let html_type = match html_type {
+            Some(what) => what,
+            None => "div",
+        };
+
+        if !HTML_TYPE_REGEX.is_match(html_type) {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid html_type {:?} in {}: element names may only contain letters, digits and hyphens",
+                    html_type, self.current_file
+                ),
+            )
+            .into());
+        }
+
+        let is_void = html::is_void_element(html_type);
+
+        if is_void && !items.is_empty() {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Block with html_type {} in {} has items, but {} is a void element and cannot have children",
+                    html_type, self.current_file, html_type
+                ),
+            )
+            .into());
+        }
+
+        if let Some(level) = a11y::heading_level(html_type) {
+            self.check_heading_level(level);
+        }
+
+        let mut attributes = String::new();
+
+        if let Some(id) = id {
+            let id = self.process_special_values(id)?;
+            attributes.push_str(&format!(" id=\"{}\"", html::escape(&id)));
+        }
+
+        if let Some(style) = style {
+            attributes.push_str(&format!(" class=\"{}\"", html::escape(style)));
+        }
+
+        if let Some(data_attrs) = data_attrs {
+            attributes.push_str(&self.data_attributes(data_attrs)?);
+        }
+
+        if let Some(attrs) = attrs {
+            attributes.push_str(&self.attributes(attrs)?);
+        }
+
+        output.push_str(&self.get_indent());
+
+        if is_void {
+            output.push_str(&format!("<{}{} />", html_type, attributes));
+            return Ok(output);
+        }
+
+        output.push_str(&format!("<{}{}>", html_type, attributes));
+
+        output.push('\n');
+        self.indent_level += 1;
+
+        for item in items {
+            output.push_str(&self.construct_block(item)?);
+        }
+
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("</{}>", html_type));
+
+        Ok(output)
+    }
+
+    fn list(
+        &mut self,
+        ordered: bool,
+        style: &Option<String>,
+        items: &[BlockItem],
+    ) -> Result<String> {
+        let mut output = String::new();
+        let html_type = if ordered { "ol" } else { "ul" };
+
+        output.push_str(&self.get_indent());
+
+        match style {
+            Some(style) => output.push_str(&format!(
+                "<{} class=\"{}\">\n",
+                html_type,
+                html::escape(style)
+            )),
+            None => output.push_str(&format!("<{}>\n", html_type)),
+        }
+
+        self.indent_level += 1;
+
+        for item in items {
+            output.push_str(&self.get_indent());
+            output.push_str("<li>\n");
+
+            self.indent_level += 1;
+            output.push_str(&self.construct_block(item)?);
+            self.indent_level -= 1;
+
+            output.push_str(&self.get_indent());
+            output.push_str("</li>\n");
+        }
+
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("</{}>", html_type));
+
+        Ok(output)
+    }
+
+    /// Replaces the `$theme.name` references in `s`, leaving escaped `\\$theme.` as it is.
+    fn theme_values(&self, s: &str) -> Result<String> {
+        let theme = &self.config.site_config.theme;
+
+        if let Some(caps) = THEME_REGEX
+            .captures_iter(s)
+            .find(|caps| caps.get(1).is_none() && theme.value(&caps[2]).is_none())
+        {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unknown theme value {} used in {}",
+                    &caps[2], self.current_file
+                ),
+            )
+            .into());
+        }
+
+        Ok(THEME_REGEX
+            .replace_all(s, |caps: &Captures| match caps.get(1) {
+                Some(_) => format!("$theme.{}", &caps[2]),
+                None => theme.value(&caps[2]).unwrap_or_default(),
+            })
+            .to_string())
+    }
+
+    fn style_inline(&mut self, source: &StyleSource) -> Result<String> {
+        let css = match source {
+            StyleSource::Css(css) => css.clone(),
+            StyleSource::File { path } => {
+                let path = self.process_special_values(path)?;
+                let css =
+                    std::fs::read_to_string(self.config.input_dir.join(&path)).map_err(|e| {
+                        Error::new(
+                            e.kind(),
+                            format!(
+                                "Cannot read style file {} in {}: {}",
+                                path, self.current_file, e
+                            ),
+                        )
+                    })?;
+
+                self.dependencies.files.insert(PathBuf::from(path));
+                css
+            }
+        };
+        let css = self.theme_values(&css)?;
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str("<style>\n");
+
+        self.indent_level += 1;
+        let indent = self.get_indent();
+        for line in css.trim().lines() {
+            if !line.trim().is_empty() {
+                output.push_str(&indent);
+                output.push_str(line.trim_end());
+            }
+            output.push('\n');
+        }
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str("</style>");
+
+        Ok(output)
+    }
+
+    fn nav(&mut self, style: &Option<String>) -> Result<String> {
+        let mut output = String::new();
+        let menu = self.config.site_config.menu.clone();
+        let page_path = self.page_path();
+
+        output.push_str(&self.get_indent());
+
+        match style {
+            Some(style) => output.push_str(&format!("<nav class=\"{}\">\n", html::escape(style))),
+            None => output.push_str("<nav>\n"),
+        }
+
+        self.indent_level += 1;
+        output.push_str(&self.menu_list(&menu, &page_path));
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str("</nav>");
+
+        Ok(output)
+    }
+
+    fn breadcrumbs(
+        &mut self,
+        home: &Option<String>,
+        title: &Option<String>,
+        separator: &Option<String>,
+        style: &Option<String>,
+    ) -> Result<String> {
+        let page_path = self.page_path();
+        let style = style.as_deref().unwrap_or("breadcrumbs");
+        let separator = separator.as_deref().unwrap_or("/");
+        self.check_class_name(style)?;
+        // the generated styles are shared by every page, so the separator gets a class of its own
+        let separator_class = format!("breadcrumbs-{}", hash::short_hash(separator.as_bytes()));
+        let pretty_urls = self.config.site_config.pretty_urls;
+
+        // an index.html stands for its directory
+        let page = if page_path
+            .file_name()
+            .is_some_and(|name| name == "index.html")
+        {
+            page_path.parent().unwrap_or(Path::new("")).to_path_buf()
+        } else {
+            page_path.with_extension("")
+        };
+        let names = page
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+
+        let home = match home {
+            Some(home) => self.process_special_values(home)?,
+            None => "Home".to_string(),
+        };
+        let title = match title {
+            Some(title) => Some(self.process_special_values(title)?),
+            None => self
+                .page_frontmatter
+                .as_ref()
+                .and_then(|page| page.title.clone()),
+        };
+
+        let mut crumbs = vec![(home, String::new())];
+        for (i, name) in names.iter().enumerate() {
+            let dir = names[..=i].join("/");
+            let label = self
+                .config
+                .site_config
+                .breadcrumbs
+                .get(&dir)
+                .cloned()
+                .unwrap_or_else(|| name.clone());
+
+            crumbs.push((label, dir));
+        }
+
+        if let (Some(title), Some(last)) = (title, crumbs.last_mut()) {
+            last.0 = title;
+        }
+
+        let mut separator_rule = StyleRule::new();
+        separator_rule.insert(
+            "content".to_string(),
+            format!(
+                "\"{}\"",
+                separator.replace('\\', "\\\\").replace('"', "\\\"")
+            ),
+        );
+        separator_rule.insert("padding".to_string(), "0 0.5em".to_string());
+
+        let mut list_rule = StyleRule::new();
+        list_rule.insert("display".to_string(), "flex".to_string());
+        list_rule.insert("flex-wrap".to_string(), "wrap".to_string());
+        list_rule.insert("list-style".to_string(), "none".to_string());
+        list_rule.insert("padding".to_string(), "0".to_string());
+
+        self.generated_styles
+            .insert(format!(".{} ol", style), list_rule);
+        self.generated_styles.insert(
+            format!(".{} li + li::before", separator_class),
+            separator_rule,
+        );
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!(
+            "<nav class=\"{} {}\" aria-label=\"Breadcrumbs\">\n",
+            style, separator_class
+        ));
+        self.indent_level += 1;
+        output.push_str(&self.get_indent());
+        output.push_str("<ol>\n");
+        self.indent_level += 1;
+
+        let last = crumbs.len() - 1;
+        for (i, (label, dir)) in crumbs.iter().enumerate() {
+            output.push_str(&self.get_indent());
+
+            if i == last {
+                output.push_str(&format!(
+                    "<li aria-current=\"page\">{}</li>\n",
+                    html::escape(label)
+                ));
+                continue;
+            }
+
+            let url = match (dir.as_str(), pretty_urls) {
+                ("", true) => String::new(),
+                ("", false) => "index.html".to_string(),
+                (dir, true) => format!("{}/", dir),
+                (dir, false) => format!("{}/index.html", dir),
+            };
+
+            output.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                html::escape(&menu_url(&url, &page_path)),
+                html::escape(label)
+            ));
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</ol>\n");
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</nav>");
+
+        Ok(output)
+    }
+
+    fn details(
+        &mut self,
+        summary: &str,
+        open: bool,
+        style: &Option<String>,
+        items: &[BlockItem],
+    ) -> Result<String> {
+        let summary = self.process_special_values(summary)?;
+        let mut attributes = String::new();
+
+        if let Some(style) = style {
+            attributes.push_str(&format!(" class=\"{}\"", html::escape(style)));
+        }
+
+        if open {
+            attributes.push_str(" open");
+        }
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("<details{}>\n", attributes));
+        self.indent_level += 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("<summary>{}</summary>\n", html::escape(&summary)));
+
+        for item in items {
+            output.push_str(&self.construct_block(item)?);
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</details>");
+
+        Ok(output)
+    }
+
+    fn callout(
+        &mut self,
+        kind: CalloutKind,
+        title: &Option<String>,
+        style: &Option<String>,
+        items: &[BlockItem],
+    ) -> Result<String> {
+        let title = match title {
+            Some(title) => self.process_special_values(title)?,
+            None => kind.title().to_string(),
+        };
+
+        self.callout_styles(kind);
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str(&kind.opening_tag(style.as_deref()));
+        output.push('\n');
+        self.indent_level += 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str(&callout::title_paragraph(&title));
+        output.push('\n');
+
+        for item in items {
+            output.push_str(&self.construct_block(item)?);
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</aside>");
+
+        Ok(output)
+    }
+
+    fn quote(
+        &mut self,
+        text: &Option<String>,
+        items: &[BlockItem],
+        author: &Option<String>,
+        cite: &Option<String>,
+        style: &Option<String>,
+    ) -> Result<String> {
+        let cite = match cite {
+            Some(cite) => Some(self.process_special_values(cite)?),
+            None => None,
+        };
+        let mut attributes = String::new();
+
+        if let Some(style) = style {
+            attributes.push_str(&format!(" class=\"{}\"", html::escape(style)));
+        }
+
+        if let Some(cite) = &cite {
+            attributes.push_str(&format!(" cite=\"{}\"", html::escape(cite)));
+        }
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("<blockquote{}>\n", attributes));
+        self.indent_level += 1;
+
+        if let Some(text) = text {
+            let text = self.process_special_values(text)?;
+
+            output.push_str(&self.get_indent());
+            output.push_str(&format!("<p>{}</p>\n", html::escape(&text)));
+        }
+
+        for item in items {
+            output.push_str(&self.construct_block(item)?);
+        }
+
+        let author = match author {
+            Some(author) => Some(self.process_special_values(author)?),
+            None => None,
+        };
+
+        // without an author the source itself is credited
+        let credit = match (author, &cite) {
+            (author, Some(cite)) => Some(format!(
+                "<a href=\"{}\">{}</a>",
+                html::escape(cite),
+                html::escape(author.as_deref().unwrap_or(cite))
+            )),
+            (Some(author), None) => Some(html::escape(&author)),
+            (None, None) => None,
+        };
+
+        if let Some(credit) = credit {
+            output.push_str(&self.get_indent());
+            output.push_str(&format!("<footer>— <cite>{}</cite></footer>\n", credit));
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</blockquote>");
+
+        Ok(output)
+    }
+
+    fn callout_styles(&mut self, kind: CalloutKind) {
+        for (selector, rule) in kind.rules() {
+            self.generated_styles.insert(selector, rule);
+        }
+    }
+
+    /// Adds the styles of the callouts that alerts in converted markdown became.
+    fn markdown_callout_styles(&mut self, html: &str) {
+        for kind in callout::CALLOUT_KINDS {
+            if html.contains(&format!("class=\"callout {}\"", kind.class())) {
+                self.callout_styles(kind);
+            }
+        }
+    }
+
+    /// Checks that a `style` used in generated selectors is a single class name.
+    fn check_class_name(&self, style: &str) -> Result<()> {
+        if !CLASS_NAME_REGEX.is_match(style) {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid style {:?} in {}: a generated style needs a single class name",
+                    style, self.current_file
+                ),
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Tabs made of a radio button and a label for each panel, switched with generated CSS
+    /// alone.
+    fn tabs(&mut self, tabs: &[Tab], style: &Option<String>) -> Result<String> {
+        let style = style.as_deref().unwrap_or("tabs");
+        self.check_class_name(style)?;
+        let class = format!(".{}", style);
+
+        self.tab_groups += 1;
+        let group = format!("{}-{}", taxonomy::slug(style), self.tab_groups);
+
+        let rules = [
+            (
+                class.clone(),
+                vec![("display", "flex"), ("flex-wrap", "wrap")],
+            ),
+            (
+                format!("{} > input", class),
+                vec![("position", "absolute"), ("opacity", "0")],
+            ),
+            (
+                format!("{} > label", class),
+                vec![("cursor", "pointer"), ("padding", "0.5em 1em")],
+            ),
+            (
+                format!("{} > input:checked + label", class),
+                vec![("font-weight", "bold")],
+            ),
+            (
+                format!("{} > input:focus-visible + label", class),
+                vec![("outline", "2px solid")],
+            ),
+            (
+                format!("{} > .tab-panel", class),
+                vec![("display", "none"), ("order", "1"), ("width", "100%")],
+            ),
+            (
+                format!("{} > input:checked + label + .tab-panel", class),
+                vec![("display", "block")],
+            ),
+        ];
+
+        for (selector, declarations) in rules {
+            self.generated_styles.insert(
+                selector,
+                declarations
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            );
+        }
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("<div class=\"{}\">\n", html::escape(style)));
+        self.indent_level += 1;
+
+        for (i, tab) in tabs.iter().enumerate() {
+            let label = self.process_special_values(&tab.label)?;
+            let id = format!("{}-{}", group, i + 1);
+
+            output.push_str(&self.get_indent());
+            output.push_str(&format!(
+                "<input type=\"radio\" name=\"{}\" id=\"{}\"{} />\n",
+                group,
+                id,
+                if i == 0 { " checked" } else { "" }
+            ));
+            output.push_str(&self.get_indent());
+            output.push_str(&format!(
+                "<label for=\"{}\">{}</label>\n",
+                id,
+                html::escape(&label)
+            ));
+
+            output.push_str(&self.get_indent());
+            output.push_str("<div class=\"tab-panel\">\n");
+            self.indent_level += 1;
+
+            for item in tab.items.iter() {
+                output.push_str(&self.construct_block(item)?);
+            }
+
+            self.indent_level -= 1;
+            output.push_str(&self.get_indent());
+            output.push_str("</div>\n");
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</div>");
+
+        Ok(output)
+    }
+
+    fn menu_list(&mut self, entries: &[MenuEntry], page_path: &Path) -> String {
+        let mut output = String::new();
+        let page_key = page_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        output.push_str(&self.get_indent());
+        output.push_str("<ul>\n");
+        self.indent_level += 1;
+
+        for entry in entries {
+            let active = entry.links_to(&page_key);
+            let link = format!(
+                "<a href=\"{}\"{}>{}</a>",
+                html::escape(&menu_url(&entry.url, page_path)),
+                if active { " aria-current=\"page\"" } else { "" },
+                html::escape(&entry.label)
+            );
+
+            output.push_str(&self.get_indent());
+            output.push_str(if active {
+                "<li class=\"active\">"
+            } else {
+                "<li>"
+            });
+
+            if entry.children.is_empty() {
+                output.push_str(&link);
+            } else {
+                self.indent_level += 1;
+                output.push('\n');
+                output.push_str(&self.get_indent());
+                output.push_str(&link);
+                output.push('\n');
+                output.push_str(&self.menu_list(&entry.children, page_path));
+                self.indent_level -= 1;
+                output.push_str(&self.get_indent());
+            }
+
+            output.push_str("</li>\n");
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</ul>\n");
+
+        output
+    }
+
+    fn csv_rows(&mut self, path: &str) -> Result<Vec<Vec<String>>> {
+        let contents = std::fs::read_to_string(self.config.input_dir.join(path)).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!(
+                    "Cannot read CSV file {} in {}: {}",
+                    path, self.current_file, e
+                ),
+            )
+        })?;
+
+        self.dependencies.files.insert(PathBuf::from(path));
+
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(contents.as_bytes())
+            .records()
+            .map(|record| {
+                record
+                    .map(|record| record.iter().map(str::to_string).collect())
+                    .map_err(|e| {
+                        Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid CSV file {} in {}: {}", path, self.current_file, e),
+                        )
+                        .into()
+                    })
+            })
+            .collect()
+    }
+
+    fn table(
+        &mut self,
+        rows: &[Vec<String>],
+        header: bool,
+        style: &Option<String>,
+    ) -> Result<String> {
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+
+        match style {
+            Some(style) => output.push_str(&format!("<table class=\"{}\">\n", html::escape(style))),
+            None => output.push_str("<table>\n"),
+        }
+
+        self.indent_level += 1;
+
+        let (head, body) = match rows.split_first() {
+            Some((first, rest)) if header => (Some(first), rest),
+            _ => (None, rows),
+        };
+
+        if let Some(head) = head {
+            output.push_str(&self.table_section("thead", "th", std::slice::from_ref(head))?);
+        }
+
+        if !body.is_empty() {
+            output.push_str(&self.table_section("tbody", "td", body)?);
+        }
+
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str("</table>");
+
+        Ok(output)
+    }
+
+    fn table_section(&mut self, section: &str, cell: &str, rows: &[Vec<String>]) -> Result<String> {
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("<{}>\n", section));
+        self.indent_level += 1;
+
+        for row in rows {
+            output.push_str(&self.get_indent());
+            output.push_str("<tr>\n");
+            self.indent_level += 1;
+
+            for value in row {
+                let value = self.process_special_values(value)?;
+                output.push_str(&self.get_indent());
+                output.push_str(&format!("<{}>{}</{}>\n", cell, html::escape(&value), cell));
+            }
+
+            self.indent_level -= 1;
+            output.push_str(&self.get_indent());
+            output.push_str("</tr>\n");
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("</{}>\n", section));
+
+        Ok(output)
+    }
+
+    fn attributes(&mut self, attrs: &BTreeMap<String, String>) -> Result<String> {
+        let mut output = String::new();
+
+        for (key, value) in attrs {
+            if !ATTRIBUTE_NAME_REGEX.is_match(key) {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid attribute name {} in {}: names must match [A-Za-z_:][A-Za-z0-9_.:-]*",
+                        key, self.current_file
+                    ),
+                )
+                .into());
+            }
+
+            if key == "class" || key == "id" {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Attribute {} in {} must be set with `{}` instead of attrs",
+                        key,
+                        self.current_file,
+                        if key == "class" { "style" } else { "id" }
+                    ),
+                )
+                .into());
+            }
+
+            let value = self.process_special_values(value)?;
+            output.push_str(&format!(" {}=\"{}\"", key, html::escape(&value)));
+        }
+
+        Ok(output)
+    }
+
+    fn data_attributes(&mut self, data_attrs: &BTreeMap<String, String>) -> Result<String> {
+        let mut output = String::new();
+
+        for (key, value) in data_attrs {
+            if !DATA_ATTRIBUTE_NAME_REGEX.is_match(key) {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid data attribute name {} in {}: names must match [a-z][a-z0-9-]*",
+                        key, self.current_file
+                    ),
+                )
+                .into());
+            }
+
+            let value = self.process_special_values(value)?;
+
+            if value.contains('"') {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Invalid value for data attribute {} in {}: values must not contain '\"'",
+                        key, self.current_file
+                    ),
+                )
+                .into());
+            }
+
+            output.push_str(&format!(" data-{}=\"{}\"", key, value));
+        }
+
+        Ok(output)
+    }
+
+    /// Converts inline markdown. If it starts with frontmatter, its fields are what `$page`
+    /// refers to inside it, and drafts and future-dated markdown are left out unless enabled.
+    fn markdown(&mut self, markdown: &str) -> Result<String> {
+        let (page, body) = frontmatter::split(markdown)?;
+        let has_frontmatter = body.len() != markdown.len();
+
+        if has_frontmatter {
+            self.note_page_frontmatter(&page, None);
+        }
+
+        if self.is_hidden(&page) {
+            return Ok(String::new());
+        }
+
+        let old_frontmatter = if has_frontmatter {
+            self.current_frontmatter.replace(page)
+        } else {
+            self.current_frontmatter.clone()
+        };
+
+        let body = self.process_special_values(body);
+
+        self.current_frontmatter = old_frontmatter;
+
+        let html = self.markdown_html(&body?)?;
+        self.markdown_callout_styles(&html);
+        self.page_has_math |= math::has_math(&html);
+
+        Ok(format!("{}{}", self.get_indent(), html))
+    }
+
+    /// Converts markdown to HTML with its shortcodes expanded.
+    fn markdown_html(&mut self, markdown: &str) -> Result<String> {
+        let (markdown, shortcodes) = shortcodes::extract(markdown)?;
+        let html = html::markdown_to_html(&markdown, self.config.site_config.typography);
+
+        self.expand_shortcodes(&html, &shortcodes)
+    }
+
+    fn expand_shortcodes(&mut self, html: &str, shortcodes: &[Shortcode]) -> Result<String> {
+        shortcodes::expand(html, shortcodes, |shortcode| self.shortcode(shortcode))
+    }
+
+    /// Includes the block a shortcode refers to, unindented as it ends up inside markdown.
+    fn shortcode(&mut self, shortcode: &Shortcode) -> Result<String> {
+        let block_name = shortcode
+            .block_names()
+            .into_iter()
+            .find(|name| self.block_items.contains_key(name))
+            .ok_or_else(|| {
+                Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "Shortcode {} used in {} has no block {}",
+                        shortcode.name,
+                        self.current_file,
+                        shortcode.block_names().join(" or ")
+                    ),
+                )
+            })?;
+
+        let indent_level = std::mem::replace(&mut self.indent_level, 0);
+        let included = self.include(&block_name, shortcode.params.clone(), BTreeMap::new());
+        self.indent_level = indent_level;
+
+        included
+    }
+
+    /// Converts a markdown file relative to the input directory, without its frontmatter.
+    fn markdown_file(&mut self, path: &str) -> Result<String> {
+        let source = self.config.input_dir.join(path);
+
+        if !source.is_file() {
+            return Err(Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Markdown file {} used in {} does not exist",
+                    path, self.current_file
+                ),
+            )
+            .into());
+        }
+
+        self.dependencies.files.insert(PathBuf::from(path));
+
+        let parsed = self.cache.markdown(&source)?;
+
+        if parsed.has_frontmatter {
+            self.note_page_frontmatter(&parsed.frontmatter, Some(&source));
+        }
+
+        if self.is_hidden(&parsed.frontmatter) {
+            return Ok(String::new());
+        }
+
+        let html = self.expand_shortcodes(&parsed.html, &parsed.shortcodes)?;
+        self.markdown_callout_styles(&html);
+        self.page_has_math |= math::has_math(&html);
+
+        Ok(format!("{}{}", self.get_indent(), html))
+    }
+
+    /// A table of contents of a markdown file relative to the input directory, linking to
+    /// the ids its headings get when it is converted. Empty if no heading is in `levels`.
+    fn toc(
+        &mut self,
+        path: &str,
+        levels: (Option<usize>, Option<usize>),
+        ordered: bool,
+        style: &Option<String>,
+    ) -> Result<String> {
+        let source = self.config.input_dir.join(path);
+
+        if !source.is_file() {
+            return Err(Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Markdown file {} used in {} does not exist",
+                    path, self.current_file
+                ),
+            )
+            .into());
+        }
+
+        self.dependencies.files.insert(PathBuf::from(path));
+
+        let levels = levels.0.unwrap_or(1)..=levels.1.unwrap_or(6);
+        let headings = self
+            .cache
+            .markdown(&source)?
+            .headings
+            .iter()
+            .filter(|heading| levels.contains(&heading.level))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if headings.is_empty() {
+            return Ok(String::new());
+        }
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+
+        match style {
+            Some(style) => output.push_str(&format!("<nav class=\"{}\">\n", html::escape(style))),
+            None => output.push_str("<nav>\n"),
+        }
+
+        self.indent_level += 1;
+        output.push_str(&self.toc_list(&headings, ordered));
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str("</nav>");
+
+        Ok(output)
+    }
+
+    /// A list of headings, with the deeper headings after each one nested under it.
+    fn toc_list(&mut self, headings: &[Heading], ordered: bool) -> String {
+        let list_type = if ordered { "ol" } else { "ul" };
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("<{}>\n", list_type));
+        self.indent_level += 1;
+
+        let mut i = 0;
+        while i < headings.len() {
+            let heading = &headings[i];
+            let end = headings[i + 1..]
+                .iter()
+                .position(|next| next.level <= heading.level)
+                .map_or(headings.len(), |position| i + 1 + position);
+            let link = format!(
+                "<a href=\"#{}\">{}</a>",
+                html::escape(&heading.id),
+                html::escape(&heading.text)
+            );
+
+            output.push_str(&self.get_indent());
+            output.push_str("<li>");
+
+            if end == i + 1 {
+                output.push_str(&link);
+            } else {
+                self.indent_level += 1;
+                output.push('\n');
+                output.push_str(&self.get_indent());
+                output.push_str(&link);
+                output.push('\n');
+                output.push_str(&self.toc_list(&headings[i + 1..end], ordered));
+                self.indent_level -= 1;
+                output.push_str(&self.get_indent());
+            }
+
+            output.push_str("</li>\n");
+            i = end;
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str(&format!("</{}>\n", list_type));
+
+        output
+    }
+
+    fn is_hidden(&self, frontmatter: &Frontmatter) -> bool {
+        frontmatter.is_hidden(self.config.drafts, self.config.future)
+    }
+
+    /// Makes the `meta` of a page's `html` or `layout` item its frontmatter.
+    fn note_page_meta(&mut self, block_name: &str) -> Result<()> {
+        let meta = match self.block_items.get(block_name) {
+            Some(BlockItem::Html { meta, .. } | BlockItem::Layout { meta, .. })
+                if !meta.is_empty() =>
+            {
+                meta
+            }
+            _ => return Ok(()),
+        };
+
+        let mapping = meta
+            .iter()
+            .map(|(key, value)| (serde_yaml::Value::String(key.clone()), value.clone()))
+            .collect();
+        let frontmatter: Frontmatter = serde_yaml::from_value(serde_yaml::Value::Mapping(mapping))
+            .map_err(|why| {
+                Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid meta in {}: {}", block_name, why),
+                )
+            })?;
+
+        self.note_page_frontmatter(&frontmatter, None);
+        self.current_frontmatter = Some(frontmatter);
+
+        Ok(())
+    }
+
+    fn note_page_frontmatter(&mut self, frontmatter: &Frontmatter, file: Option<&Path>) {
+        if self.current_frontmatter.is_none() && self.page_frontmatter.is_none() {
+            self.page_file = file.map(Path::to_path_buf);
+            self.page_hidden = self.is_hidden(frontmatter);
+            self.page_frontmatter = Some(frontmatter.clone());
+        }
+    }
+
+    fn code(&self, code: &String) -> Result<String> {
+        Ok(format!("<pre><code>\n{}\n</code></pre>", code))
+    }
+
+    /// Reads a file relative to the input directory and shows it escaped in a code block,
+    /// optionally prefixing each line with its number.
+    fn code_file(
+        &mut self,
+        path: &str,
+        language: &Option<String>,
+        line_numbers: bool,
+    ) -> Result<String> {
+        let contents = std::fs::read_to_string(self.config.input_dir.join(path)).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!(
+                    "Cannot read code file {} in {}: {}",
+                    path, self.current_file, e
+                ),
+            )
+        })?;
+
+        self.dependencies.files.insert(PathBuf::from(path));
+
+        let contents = contents.trim_end_matches(['\n', '\r']);
+
+        let code = if line_numbers {
+            let line_count = contents.lines().count();
+            let width = line_count.to_string().len();
+
+            let mut style = StyleRule::new();
+            style.insert("user-select".to_string(), "none".to_string());
+            style.insert("opacity".to_string(), "0.6".to_string());
+            self.generated_styles
+                .insert(".line-number".to_string(), style);
+
+            contents
+                .lines()
+                .enumerate()
+                .map(|(i, line)| {
+                    format!(
+                        "<span class=\"line-number\">{:>width$}</span> {}",
+                        i + 1,
+                        html::escape(line),
+                        width = width
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            html::escape(contents)
+        };
+
+        let class = match language {
+            Some(language) => format!(" class=\"language-{}\"", html::escape(language)),
+            None => String::new(),
+        };
+
+        Ok(format!("<pre><code{}>\n{}\n</code></pre>", class, code))
+    }
+
+    fn image(&mut self, image: &String, alt: &Option<String>) -> Result<String> {
+        if alt.as_ref().is_none_or(|alt| alt.trim().is_empty()) {
+            self.a11y_finding(&format!("Image {} has no alt text", image));
+        }
+
+        let alt = match alt {
+            Some(what) => what,
+            None => "",
+        };
+
+        Ok(format!(
+            "<img src=\"{}\" alt=\"{}\" />",
+            html::escape(&self.asset_url(image)),
+            html::escape(alt)
+        ))
+    }
+
+    /// The image of a figure, resized to `width` and linked to the original if asked to.
+    fn figure_image(
+        &mut self,
+        image: &str,
+        alt: &Option<String>,
+        width: Option<u32>,
+        link_full_size: bool,
+    ) -> Result<String> {
+        let image = self.process_special_values(image)?;
+        let img = match width {
+            Some(width) => self.processed_image(&image, alt, (Some(width), None), false, &None)?,
+            None => self.image(&image, alt)?,
+        };
+
+        if !link_full_size {
+            return Ok(img);
+        }
+
+        Ok(format!(
+            "<a href=\"{}\">{}</a>",
+            html::escape(&self.asset_url(&image)),
+            img
+        ))
+    }
+
+    /// A `<figure>` of the already built image, if there is one, and the items.
+    fn figure(
+        &mut self,
+        image: Option<String>,
+        items: &[BlockItem],
+        caption: &Option<String>,
+        style: &Option<String>,
+    ) -> Result<String> {
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        match style {
+            Some(style) => {
+                output.push_str(&format!("<figure class=\"{}\">\n", html::escape(style)))
+            }
+            None => output.push_str("<figure>\n"),
+        }
+
+        self.indent_level += 1;
+
+        if let Some(image) = image {
+            output.push_str(&self.get_indent());
+            output.push_str(&image);
+            output.push('\n');
+        }
+
+        for item in items {
+            output.push_str(&self.construct_block(item)?);
+        }
+
+        if let Some(caption) = caption {
+            let caption = self.process_special_values(caption)?;
+
+            output.push_str(&self.get_indent());
+            output.push_str(&format!(
+                "<figcaption>{}</figcaption>\n",
+                html::escape(&caption)
+            ));
+        }
+
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str("</figure>");
+
+        Ok(output)
+    }
+
+    /// The images matching a pattern relative to the input directory, as URLs relative to
+    /// the page.
+    fn gallery_pattern_images(&mut self, pattern: &str) -> Result<Vec<GalleryImage>> {
+        let input_dir = without_cur_dir(&self.config.input_dir);
+        let page_path = self.page_path();
+
+        Ok(self
+            .query_files(pattern, &FileQuery::default())?
+            .into_iter()
+            .filter_map(|(path, _, _)| {
+                let relative = without_cur_dir(&path)
+                    .strip_prefix(&input_dir)
+                    .ok()?
+                    .to_path_buf();
+
+                Some(GalleryImage::Plain(relative_url(&page_path, &relative)))
+            })
+            .collect())
+    }
+
+    fn gallery(
+        &mut self,
+        images: &[GalleryImage],
+        columns: u32,
+        thumbnail_width: u32,
+        style: &str,
+    ) -> Result<String> {
+        let columns_class = format!("gallery-columns-{}", columns);
+
+        let mut grid = StyleRule::new();
+        grid.insert("display".to_string(), "grid".to_string());
+        grid.insert(
+            "grid-template-columns".to_string(),
+            format!("repeat({}, 1fr)", columns),
+        );
+        grid.insert("gap".to_string(), "0.5rem".to_string());
+        self.generated_styles
+            .insert(format!(".{}", columns_class), grid);
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!(
+            "<div class=\"{} {}\">\n",
+            html::escape(style),
+            columns_class
+        ));
+        self.indent_level += 1;
+
+        for image in images {
+            let (path, alt, caption) = match image {
+                GalleryImage::Plain(path) => (path, &None, &None),
+                GalleryImage::Verbose { path, alt, caption } => (path, alt, caption),
+            };
+            let path = self.process_special_values(path)?;
+            let thumbnail =
+                self.processed_image(&path, alt, (Some(thumbnail_width), None), false, &None)?;
+
+            output.push_str(&self.get_indent());
+            output.push_str("<figure>\n");
+            self.indent_level += 1;
+
+            output.push_str(&self.get_indent());
+            output.push_str(&format!(
+                "<a href=\"{}\" data-gallery=\"{}\">{}</a>\n",
+                html::escape(&self.asset_url(&path)),
+                html::escape(style),
+                thumbnail
+            ));
+
+            if let Some(caption) = caption {
+                let caption = self.process_special_values(caption)?;
+
+                output.push_str(&self.get_indent());
+                output.push_str(&format!(
+                    "<figcaption>{}</figcaption>\n",
+                    html::escape(&caption)
+                ));
+            }
+
+            self.indent_level -= 1;
+            output.push_str(&self.get_indent());
+            output.push_str("</figure>\n");
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</div>");
+
+        Ok(output)
+    }
+
+    /// A `<video>` or `<audio>` element. `attributes` are any the element has besides the
+    /// playback options.
+    fn media(
+        &mut self,
+        element: &str,
+        path: &str,
+        attributes: &str,
+        options: &MediaOptions,
+    ) -> Result<String> {
+        let path = self.process_special_values(path)?;
+        let mut output = format!(
+            "<{} src=\"{}\"{}",
+            element,
+            html::escape(&self.asset_url(&path)),
+            attributes
+        );
+
+        for (attribute, on) in [
+            ("controls", options.controls),
+            ("autoplay", options.autoplay),
+            ("loop", options.looped),
+            ("muted", options.muted),
+        ] {
+            if on {
+                output.push_str(&format!(" {}", attribute));
+            }
+        }
+
+        if let Some(style) = &options.style {
+            output.push_str(&format!(" class=\"{}\"", html::escape(style)));
+        }
+
+        output.push_str(&format!("></{}>", element));
+
+        Ok(output)
+    }
+
+    fn embed(
+        &mut self,
+        url: &str,
+        title: &Option<String>,
+        (width, height): (Option<u32>, Option<u32>),
+        style: &Option<String>,
+    ) -> Result<String> {
+        let mut attributes = format!(" src=\"{}\"", html::escape(&self.asset_url(url)));
+
+        match title {
+            Some(title) => {
+                let title = self.process_special_values(title)?;
+                attributes.push_str(&format!(" title=\"{}\"", html::escape(&title)));
+            }
+            None => self.a11y_finding(&format!("Embed of {} has no title", url)),
+        }
+
+        if let Some(width) = width {
+            attributes.push_str(&format!(" width=\"{}\"", width));
+        }
+
+        if let Some(height) = height {
+            attributes.push_str(&format!(" height=\"{}\"", height));
+        }
+
+        if let Some(style) = style {
+            attributes.push_str(&format!(" class=\"{}\"", html::escape(style)));
+        }
+
+        Ok(format!(
+            "<iframe{} loading=\"lazy\" referrerpolicy=\"strict-origin-when-cross-origin\" allowfullscreen></iframe>",
+            attributes
+        ))
+    }
+
+    /// An image resized or converted to the requested size and format. Responsive images
+    /// get a `srcset` of smaller variants as well.
+    fn processed_image(
+        &mut self,
+        image: &str,
+        alt: &Option<String>,
+        (width, height): (Option<u32>, Option<u32>),
+        responsive: bool,
+        format: &Option<String>,
+    ) -> Result<String> {
+        let page_dir = self.page_dir();
+
+        let source = match assets::resolve_url(image, &page_dir) {
+            Some(source) => PathBuf::from(source),
+            None => return self.image(&image.to_string(), alt),
+        };
+        let source_path = self.config.input_dir.join(&source);
+
+        if !source_path.is_file() {
+            return Err(Error::new(
+                io::ErrorKind::NotFound,
+                format!("Image {} used in {} not found", image, self.current_file),
+            )
+            .into());
+        }
+
+        self.dependencies.files.insert(source.clone());
+
+        if alt.as_ref().is_none_or(|alt| alt.trim().is_empty()) {
+            self.a11y_finding(&format!("Image {} has no alt text", image));
+        }
+
+        let original = images::dimensions(&source_path)?;
+        let largest = images::fit(original, width, height);
+
+        let mut sizes = Vec::new();
+        if responsive {
+            sizes.extend(
+                images::RESPONSIVE_WIDTHS
+                    .iter()
+                    .filter(|width| **width < largest.0)
+                    .map(|width| images::fit(largest, Some(*width), None)),
+            );
+        }
+        sizes.push(largest);
+
+        let file_name = source
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut urls = Vec::new();
+        for size in sizes.iter() {
+            let variant_name = images::variant_file_name(&file_name, *size, format.as_deref());
+
+            self.images.insert(ImageVariant {
+                output: source.with_file_name(&variant_name),
+                source: source.clone(),
+                width: size.0,
+                height: size.1,
+            });
+
+            urls.push(match image.rsplit_once('/') {
+                Some((dir, _)) => format!("{}/{}", dir, variant_name),
+                None => variant_name,
+            });
+        }
+
+        let mut output = format!("<img src=\"{}\"", html::escape(urls.last().unwrap()));
+
+        if responsive {
+            let srcset = urls
+                .iter()
+                .zip(sizes.iter())
+                .map(|(url, (width, _))| format!("{} {}w", url, width))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            output.push_str(&format!(
+                " srcset=\"{}\" sizes=\"(max-width: {}px) 100vw, {}px\"",
+                html::escape(&srcset),
+                largest.0,
+                largest.0
+            ));
+        }
+
+        output.push_str(&format!(
+            " alt=\"{}\" width=\"{}\" height=\"{}\" />",
+            html::escape(alt.as_deref().unwrap_or("")),
+            largest.0,
+            largest.1
+        ));
+
+        Ok(output)
+    }
+
+    fn text(&self, text: &str) -> Result<String> {
+        Ok(html::escape(
+            &self.config.site_config.typography.apply(text),
+        ))
+    }
+
+    /// Substitutes special values in a string and escapes it, unless it is marked raw.
+    fn escapable_text(&mut self, text: &EscapableText) -> Result<String> {
+        match text {
+            EscapableText::Plain(text) => Ok(html::escape(&self.process_special_values(text)?)),
+            EscapableText::Options { text, raw: false } => {
+                Ok(html::escape(&self.process_special_values(text)?))
+            }
+            EscapableText::Options { text, raw: true } => self.process_special_values(text),
+        }
+    }
+
+    fn text_with_options(
+        &self,
+        text: &str,
+        markdown: bool,
+        paragraphs: bool,
+        raw: bool,
+    ) -> Result<String> {
+        let typography = self.config.site_config.typography;
+        let render = |chunk: &str| {
+            if markdown {
+                html::inline_markdown(chunk, typography)
+            } else if raw {
+                chunk.to_string()
+            } else {
+                html::escape(&typography.apply(chunk))
+            }
+        };
+
+        if !paragraphs {
+            return Ok(render(text.trim()));
+        }
+
+        let chunks = BLANK_LINE_REGEX
+            .split(text)
+            .map(|chunk| {
+                chunk
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|chunk| !chunk.is_empty())
+            .map(|chunk| format!("<p>{}</p>", render(&chunk)))
+            .collect::<Vec<_>>();
+
+        Ok(chunks.join(&format!("\n{}", self.get_indent())))
+    }
+
+    fn link(&mut self, text: &String, url: &String, link_style: &LinkStyle) -> Result<String> {
+        if text.trim().is_empty() {
+            self.a11y_finding(&format!("Link to {} has no visible text", url));
+        } else if a11y::looks_like_url(text) {
+            self.a11y_finding(&format!("Link text {} is just a URL", text));
+        }
+
+        match link_style {
+            LinkStyle::Preset(name) => {
+                let preset = self
+                    .config
+                    .site_config
+                    .link_styles
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "Unknown link style preset {} used in {}",
+                                name, self.current_file
+                            ),
+                        )
+                    })?;
+
+                self.styled_link(text, url, &preset)
+            }
+            link_style => self.styled_link(text, url, link_style),
+        }
+    }
+
+    fn styled_link(
+        &mut self,
+        text: &String,
+        url: &String,
+        link_style: &LinkStyle,
+    ) -> Result<String> {
+        match link_style {
+            LinkStyle::Explicit {
+                color,
+                underline,
+                visited_color,
+                dark_color,
+                dark_visited_color,
+            } => {
+                self.check_link_contrast(color, visited_color);
+
+                let dark = (dark_color.is_some() || dark_visited_color.is_some()).then(|| {
+                    (
+                        dark_color.as_ref().unwrap_or(color),
+                        dark_visited_color.as_ref(),
+                    )
+                });
+
+                if self.config.css_vars {
+                    return self.link_css_vars(text, url, color, *underline, visited_color, dark);
+                }
+
+                let mut class = format!(
+                    "link-{}-{}",
+                    color.normal.to_string().trim_start_matches('#'),
+                    {
+                        if *underline {
+                            "underline"
+                        } else {
+                            "none"
+                        }
+                    }
+                );
+
+                if let Some((dark_color, _)) = dark {
+                    class.push_str(&format!(
+                        "-dark-{}",
+                        dark_color.normal.to_string().trim_start_matches('#')
+                    ));
+                }
+
+                let rules = link_rules(color, *underline, visited_color.as_ref());
+
+                for (state, rule) in ["link", "visited", "hover", "active"].iter().zip(rules) {
+                    self.generated_styles
+                        .insert(format!(".{}:{}", &class, state), rule);
+                }
+
+                if let Some((dark_color, dark_visited_color)) = dark {
+                    let rules = link_rules(dark_color, *underline, dark_visited_color);
+
+                    for (state, rule) in ["link", "visited", "hover", "active"].iter().zip(rules) {
+                        self.insert_dark_style(&format!(".{}:{}", &class, state), rule);
+                    }
+                }
+
+                Ok(format!(
+                    "<a href=\"{}\" class=\"{}\">{}</a>",
+                    url, class, text
+                ))
+            }
+            LinkStyle::Style(style) => Ok(format!(
+                "<a href=\"{}\" class=\"{}\">{}</a>",
+                url,
+                html::escape(style),
+                text
+            )),
+            // presets are resolved by `link`, so this is a preset naming another
+            LinkStyle::Preset(name) => Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Link style preset used in {} refers to another preset, {}",
+                    self.current_file, name
+                ),
+            )
+            .into()),
+        }
+    }
+
+    fn link_css_vars(
+        &mut self,
+        text: &String,
+        url: &String,
+        color: &LinkColor,
+        underline: bool,
+        visited_color: &Option<LinkColor>,
+        dark: Option<(&LinkColor, Option<&LinkColor>)>,
+    ) -> Result<String> {
+        let variable_colors = |color: &LinkColor, visited_color: Option<&LinkColor>| {
+            let hover_color = color.hover.as_ref().unwrap_or(&color.normal).clone();
+            let visited_color = visited_color
+                .map(|visited| &visited.normal)
+                .unwrap_or(&color.normal)
+                .clone();
+
+            [color.normal.clone(), hover_color, visited_color]
+        };
+
+        let colors = variable_colors(color, visited_color.as_ref());
+        let dark_colors = dark.map(|(color, visited_color)| variable_colors(color, visited_color));
+
+        let mut key = format!("{}|{}|{}|{}", colors[0], colors[1], colors[2], underline);
+        if let Some(dark_colors) = &dark_colors {
+            key.push_str(&format!(
+                "|{}|{}|{}",
+                dark_colors[0], dark_colors[1], dark_colors[2]
+            ));
+        }
+
+        let class = format!("link-{}", hash::short_hash(key.as_bytes()));
+
+        let variables = |colors: &[Color; 3]| {
+            ["--link-color", "--link-hover-color", "--link-visited-color"]
+                .iter()
+                .zip(colors)
+                .map(|(name, color)| (name.to_string(), color.to_css()))
+                .collect::<StyleRule>()
+        };
+
+        // the rules reading the variables are shared by every link, each link class only
+        // sets them
+        let mut normal_style = StyleRule::new();
+        normal_style.insert("color".to_string(), "var(--link-color)".to_string());
+        normal_style.insert(
+            "text-decoration".to_string(),
+            "var(--link-decoration)".to_string(),
+        );
+
+        let mut visited_style = StyleRule::new();
+        visited_style.insert("color".to_string(), "var(--link-visited-color)".to_string());
+
+        let mut hover_style = StyleRule::new();
+        hover_style.insert("color".to_string(), "var(--link-hover-color)".to_string());
+        hover_style.insert("text-decoration".to_string(), "underline".to_string());
+
+        let mut link_variables = variables(&colors);
+        link_variables.insert(
+            "--link-decoration".to_string(),
+            if underline { "underline" } else { "none" }.to_string(),
+        );
+
+        self.generated_styles
+            .insert(".link-vars".to_string(), normal_style);
+        self.generated_styles
+            .insert(".link-vars:visited".to_string(), visited_style);
+        self.generated_styles.insert(
+            ".link-vars:hover, .link-vars:active".to_string(),
+            hover_style,
+        );
+        self.generated_styles
+            .insert(format!(".{}", &class), link_variables);
+        if let Some(dark_colors) = &dark_colors {
+            self.insert_dark_style(&format!(".{}", &class), variables(dark_colors));
+        }
+
+        Ok(format!(
+            "<a href=\"{}\" class=\"link-vars {}\">{}</a>",
+            url, class, text
+        ))
+    }
+
+    /// Inserts the dark variant of a generated rule, following the site's dark mode.
+    fn insert_dark_style(&mut self, selector: &str, rule: StyleRule) {
+        match self.config.site_config.dark_mode {
+            DarkMode::Media => {
+                self.generated_styles
+                    .insert_media(styles::DARK_MEDIA, selector.to_string(), rule)
+            }
+            DarkMode::Class => self
+                .generated_styles
+                .insert(styles::dark_class_selector(selector), rule),
+        }
+    }
+
+    fn a11y_finding(&mut self, message: &str) {
+        if self.config.a11y.is_none() {
+            return;
+        }
+
+        self.a11y_findings.push(A11yFinding {
+            page: self.current_page.clone(),
+            block: self.current_file.clone(),
+            message: message.to_string(),
+        });
+    }
+
+    fn check_heading_level(&mut self, level: usize) {
+        if self.last_heading_level != 0 && level > self.last_heading_level + 1 {
+            self.a11y_finding(&format!(
+                "Heading level jumps from h{} to h{}",
+                self.last_heading_level, level
+            ));
+        }
+
+        self.last_heading_level = level;
+    }
+
+    fn check_link_contrast(&mut self, color: &LinkColor, visited_color: &Option<LinkColor>) {
+        let background = match &self.config.a11y {
+            Some(a11y) => a11y.background.clone(),
+            None => return,
+        };
+
+        let colors = [Some(&color.normal), color.hover.as_ref()]
+            .into_iter()
+            .chain(visited_color.iter().map(|visited| Some(&visited.normal)))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for link_color in colors {
+            let ratio = link_color.contrast_ratio(&background);
+
+            if ratio < a11y::MIN_CONTRAST_RATIO {
+                self.a11y_finding(&format!(
+                    "Link color {} has a contrast ratio of {:.2}:1 against {}, below the {}:1 minimum",
+                    link_color,
+                    ratio,
+                    background,
+                    a11y::MIN_CONTRAST_RATIO
+                ));
+            }
+        }
+    }
+
+    fn date(
+        &mut self,
+        value: &Option<String>,
+        file: &Option<String>,
+        format: &Option<String>,
+        style: &Option<String>,
+    ) -> Result<String> {
+        let date = match (value, file) {
+            (Some(value), _) => {
+                let value = self.process_special_values(value)?;
+
+                feed::parse_date(&value).ok_or_else(|| {
+                    Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Invalid date {} used in {}", value, self.current_file),
+                    )
+                })?
+            }
+            (None, Some(file)) => {
+                let file = self.process_special_values(file)?;
+                self.file_date(Path::new(&file))?
+            }
+            (None, None) => self
+                .current_frontmatter
+                .as_ref()
+                .or(self.page_frontmatter.as_ref())
+                .and_then(|page| page.date.as_deref())
+                .and_then(feed::parse_date)
+                .ok_or_else(|| {
+                    Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "date in {} has no value or file, and no markdown with a date is being rendered",
+                            self.current_file
+                        ),
+                    )
+                })?,
+        };
+
+        let format = match format {
+            Some(format) => self.process_special_values(format)?,
+            None => DEFAULT_DATE_FORMAT.to_string(),
+        };
+
+        let class = match style {
+            Some(style) => format!(" class=\"{}\"", html::escape(style)),
+            None => String::new(),
+        };
+
+        Ok(format!(
+            "<time datetime=\"{}\"{}>{}</time>",
+            date.to_rfc3339_opts(SecondsFormat::Secs, true),
+            class,
+            html::escape(&interpolate::format_date(&date, &format)?)
+        ))
+    }
+
+    fn search(&mut self, placeholder: &Option<String>, style: &Option<String>) -> Result<String> {
+        if !self.config.site_config.search_index {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "search used in {} but search_index is not turned on in {}",
+                    self.current_file, SITE_CONFIG_FILE
+                ),
+            )
+            .into());
+        }
+
+        let placeholder = match placeholder {
+            Some(placeholder) => self.process_special_values(placeholder)?,
+            None => "Search".to_string(),
+        };
+        let index_url = relative_url(&self.page_path(), Path::new(search::SEARCH_INDEX_FILE));
+        let search_box = search::search_box(
+            &index_url,
+            &placeholder,
+            style.as_deref().unwrap_or("search"),
+        );
+
+        let indent = self.get_indent();
+
+        Ok(search_box
+            .lines()
+            .map(|line| match line {
+                "" => String::new(),
+                line => format!("{}{}", indent, line),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim_start()
+            .to_string())
+    }
+
+    fn summary(&mut self, file: &Option<String>, style: &Option<String>) -> Result<String> {
+        let markdown = match file {
+            Some(file) => {
+                let file = self.process_special_values(file)?;
+                let source = self.config.input_dir.join(&file);
+
+                if !source.is_file() {
+                    return Err(Error::new(
+                        io::ErrorKind::NotFound,
+                        format!(
+                            "Summary file {} used in {} does not exist",
+                            file, self.current_file
+                        ),
+                    )
+                    .into());
+                }
+
+                self.dependencies.files.insert(PathBuf::from(file));
+                self.cache
+                    .markdown(&source)?
+                    .frontmatter
+                    .summary_markdown()
+                    .to_string()
+            }
+            None => self
+                .current_frontmatter
+                .as_ref()
+                .or(self.page_frontmatter.as_ref())
+                .map(|page| page.summary_markdown().to_string())
+                .ok_or_else(|| {
+                    Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "summary in {} has no file, and no markdown is being rendered",
+                            self.current_file
+                        ),
+                    )
+                })?,
+        };
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+
+        match style {
+            Some(style) => output.push_str(&format!("<div class=\"{}\">\n", html::escape(style))),
+            None => output.push_str("<div>\n"),
+        }
+
+        let html = self.markdown_html(&markdown)?;
+        self.markdown_callout_styles(&html);
+        self.page_has_math |= math::has_math(&html);
+
+        self.indent_level += 1;
+        for line in html.lines() {
+            output.push_str(&self.get_indent());
+            output.push_str(line);
+            output.push('\n');
+        }
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str("</div>");
+
+        Ok(output)
+    }
+
+    /// The frontmatter `date` of a markdown file relative to the input directory, or when
+    /// the file was last modified.
+    fn file_date(&mut self, path: &Path) -> Result<DateTime<FixedOffset>> {
+        let source = self.config.input_dir.join(path);
+        self.dependencies.files.insert(path.to_path_buf());
+
+        if source.extension().is_some_and(|ext| ext == "md") {
+            let date = self.cache.markdown(&source)?.frontmatter.date.clone();
+
+            if let Some(date) = date.as_deref().and_then(feed::parse_date) {
+                return Ok(date);
+            }
+        }
+
+        modified_date(&source).wrap_err_with(|| {
+            format!(
+                "Can't read the date of {} used in {}",
+                path.display(),
+                self.current_file
+            )
+        })
+    }
+
+    /// When the file `$file.modified` refers to was last modified: the one a loop is at, or
+    /// else the page's block file.
+    fn file_modified(&self) -> Result<DateTime<FixedOffset>> {
+        let path = match &self.current_loop_file {
+            Some(path) => path.clone(),
+            None => self
+                .config
+                .input_dir
+                .join(format!("{}.yml", self.current_page)),
+        };
+
+        modified_date(&path).wrap_err_with(|| {
+            format!(
+                "Can't read when {} was modified for $file.modified in {}",
+                path.display(),
+                self.current_file
+            )
+        })
+    }
+
+    fn br(&self) -> Result<String> {
+        Ok("<br />".into())
+    }
+
+    fn button(
+        &mut self,
+        text: &str,
+        url: &Option<String>,
+        action: &Option<String>,
+        style: &Option<String>,
+    ) -> Result<String> {
+        let text = html::escape(&self.process_special_values(text)?);
+        let class = html::escape(style.as_deref().unwrap_or("button"));
+
+        if text.trim().is_empty() {
+            self.a11y_finding("Button has no visible text");
+        }
+
+        match (url, action) {
+            (Some(_), Some(_)) => Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Button in {}: url and action are both set",
+                    self.current_file
+                ),
+            )
+            .into()),
+            (Some(url), None) => {
+                let url = self.process_special_values(url)?;
+
+                Ok(format!(
+                    "<a href=\"{}\" class=\"{}\">{}</a>",
+                    html::escape(&self.asset_url(&url)),
+                    class,
+                    text
+                ))
+            }
+            (None, action) => {
+                let action = action.as_deref().unwrap_or("submit");
+
+                if !matches!(action, "submit" | "reset" | "button") {
+                    return Err(Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "Button in {} has action {}, but it can only be submit, reset or button",
+                            self.current_file, action
+                        ),
+                    )
+                    .into());
+                }
+
+                Ok(format!(
+                    "<button type=\"{}\" class=\"{}\">{}</button>",
+                    action, class, text
+                ))
+            }
+        }
+    }
+
+    fn form(
+        &mut self,
+        action: &str,
+        method: &Option<String>,
+        style: &Option<String>,
+        items: &[BlockItem],
+    ) -> Result<String> {
+        let action = self.process_special_values(action)?;
+        let method = method.as_deref().unwrap_or("post").to_lowercase();
+
+        if method != "get" && method != "post" {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Form in {} has method {}, but it can only be get or post",
+                    self.current_file, method
+                ),
+            )
+            .into());
+        }
+
+        let mut output = String::new();
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!(
+            "<form action=\"{}\" method=\"{}\"",
+            html::escape(&self.asset_url(&action)),
+            method
+        ));
+
+        if let Some(style) = style {
+            output.push_str(&format!(" class=\"{}\"", html::escape(style)));
+        }
+
+        output.push_str(">\n");
+        self.indent_level += 1;
+
+        for item in items {
+            output.push_str(&self.construct_block(item)?);
+        }
+
+        self.indent_level -= 1;
+        output.push_str(&self.get_indent());
+        output.push_str("</form>");
+
+        Ok(output)
+    }
+
+    fn input(
+        &mut self,
+        field: &FormField,
+        input_type: &Option<String>,
+        placeholder: &Option<String>,
+        value: &Option<String>,
+    ) -> Result<String> {
+        let input_type = match input_type {
+            Some(input_type) => self.process_special_values(input_type)?,
+            None => "text".to_string(),
+        };
+        let mut attributes = format!(" type=\"{}\"", html::escape(&input_type));
+
+        if let Some(placeholder) = placeholder {
+            let placeholder = self.process_special_values(placeholder)?;
+            attributes.push_str(&format!(" placeholder=\"{}\"", html::escape(&placeholder)));
+        }
+
+        if let Some(value) = value {
+            let value = self.process_special_values(value)?;
+            attributes.push_str(&format!(" value=\"{}\"", html::escape(&value)));
+        }
+
+        // these don't need a label: they are either not shown or show their value
+        let labelled = !matches!(
+            input_type.as_str(),
+            "hidden" | "submit" | "reset" | "button"
+        );
+
+        self.form_field(field, "input", &attributes, None, labelled)
+    }
+
+    fn textarea(
+        &mut self,
+        field: &FormField,
+        placeholder: &Option<String>,
+        rows: &Option<u32>,
+    ) -> Result<String> {
+        let mut attributes = String::new();
+
+        if let Some(placeholder) = placeholder {
+            let placeholder = self.process_special_values(placeholder)?;
+            attributes.push_str(&format!(" placeholder=\"{}\"", html::escape(&placeholder)));
+        }
+
+        if let Some(rows) = rows {
+            attributes.push_str(&format!(" rows=\"{}\"", rows));
+        }
+
+        self.form_field(field, "textarea", &attributes, Some(String::new()), true)
+    }
+
+    fn select(&mut self, field: &FormField, options: &[SelectOption]) -> Result<String> {
+        let mut select_options = String::from("\n");
+        self.indent_level += 1;
+
+        for option in options {
+            let (value, text) = match option {
+                SelectOption::Plain(text) => (text, text),
+                SelectOption::Verbose { value, text } => (value, text),
+            };
+            let value = self.process_special_values(value)?;
+            let text = self.process_special_values(text)?;
+
+            select_options.push_str(&format!(
+                "{}<option value=\"{}\">{}</option>\n",
+                self.get_indent(),
+                html::escape(&value),
+                html::escape(&text)
+            ));
+        }
+
+        self.indent_level -= 1;
+        select_options.push_str(&self.get_indent());
+
+        self.form_field(field, "select", "", Some(select_options), true)
+    }
+
+    /// A form field with its label. `contents` is what goes between the opening and closing
+    /// tags, or `None` for a void element like `<input>`.
+    fn form_field(
+        &mut self,
+        field: &FormField,
+        element: &str,
+        attributes: &str,
+        contents: Option<String>,
+        labelled: bool,
+    ) -> Result<String> {
+        let name = self.process_special_values(&field.name)?;
+        let id = match &field.id {
+            Some(id) => self.process_special_values(id)?,
+            None => format!("field-{}", taxonomy::slug(&name)),
+        };
+
+        let mut output = String::new();
+
+        match &field.label {
+            Some(label) => {
+                let label = self.process_special_values(label)?;
+
+                output.push_str(&self.get_indent());
+                output.push_str(&format!(
+                    "<label for=\"{}\">{}</label>\n",
+                    html::escape(&id),
+                    html::escape(&label)
+                ));
+            }
+            None if labelled => {
+                self.a11y_finding(&format!("Form field {} has no label", name));
+            }
+            None => {}
+        }
+
+        let mut attributes = format!(
+            " id=\"{}\" name=\"{}\"{}",
+            html::escape(&id),
+            html::escape(&name),
+            attributes
+        );
+
+        if let Some(style) = &field.style {
+            attributes.push_str(&format!(" class=\"{}\"", html::escape(style)));
+        }
+
+        if field.required {
+            attributes.push_str(" required");
+        }
+
+        output.push_str(&self.get_indent());
+
+        match contents {
+            Some(contents) => output.push_str(&format!(
+                "<{}{}>{}</{}>",
+                element, attributes, contents, element
+            )),
+            None => output.push_str(&format!("<{}{} />", element, attributes)),
+        }
+
+        Ok(output)
+    }
+
+    fn separator(&self, separator: &Separator) -> Result<String> {
+        let mut attributes = String::new();
+
+        if let Some(style) = &separator.style {
+            attributes.push_str(&format!(" class=\"{}\"", html::escape(style)));
+        }
+
+        // separators are usually decorative, so they are hidden from screen readers by default
+        if separator.aria_hidden.unwrap_or(true) {
+            attributes.push_str(" aria-hidden=\"true\"");
+        }
+
+        Ok(format!("<hr{} />", attributes))
+    }
+
+    /// A diagram pre-rendered to an SVG file, or else left for Mermaid to render in the
+    /// browser.
+    fn diagram(
+        &mut self,
+        source: &Option<String>,
+        file: &Option<String>,
+        kind: DiagramKind,
+        title: &Option<String>,
+        style: &Option<String>,
+    ) -> Result<String> {
+        let source = match (source, file) {
+            (Some(source), None) => self.process_special_values(source)?,
+            (None, Some(file)) => {
+                let file = self.process_special_values(file)?;
+                let path = self.config.input_dir.join(&file);
+
+                if !path.is_file() {
+                    return Err(Error::new(
+                        io::ErrorKind::NotFound,
+                        format!(
+                            "Diagram file {} used in {} does not exist",
+                            file, self.current_file
+                        ),
+                    )
+                    .into());
+                }
+
+                self.dependencies.files.insert(PathBuf::from(file));
+                std::fs::read_to_string(path)?
+            }
+            _ => {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Diagram in {} needs exactly one of source and file",
+                        self.current_file
+                    ),
+                )
+                .into())
+            }
+        };
+
+        let title = match title {
+            Some(title) => self.process_special_values(title)?,
+            None => String::new(),
+        };
+
+        let mut class = "diagram".to_string();
+        if let Some(style) = style {
+            class.push(' ');
+            class.push_str(style);
+        }
+
+        let command = self.config.site_config.diagrams.command(kind);
+
+        let content = match command {
+            Some(command) => {
+                let svg = diagrams::render_svg(&command, &self.config.input_dir, kind, &source)
+                    .wrap_err_with(|| format!("In a diagram in {}", self.current_file))?;
+                let path = diagrams::svg_path(kind, &source);
+                let url = relative_url(&self.page_path(), &path);
+
+                self.generated_files.insert(path, svg);
+
+                format!(
+                    "<img src=\"{}\" alt=\"{}\" />",
+                    html::escape(&url),
+                    html::escape(&title)
+                )
+            }
+            None if kind == DiagramKind::Mermaid => {
+                self.page_has_mermaid = true;
+
+                format!(
+                    "<pre class=\"mermaid\" role=\"img\" aria-label=\"{}\">{}</pre>",
+                    html::escape(&title),
+                    html::escape(&source)
+                )
+            }
+            None => {
+                return Err(Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                    "Diagram in {} needs Graphviz's dot installed, or a command for {} diagrams",
+                    self.current_file,
+                    kind.name()
+                ),
+                )
+                .into())
+            }
+        };
+
+        Ok(format!(
+            "<figure class=\"{}\">{}</figure>",
+            html::escape(&class),
+            content
+        ))
+    }
+
+    /// An item rendered by the plugin for its type. The special values in its string fields
+    /// are replaced before they are passed on.
+    fn plugin_item(
+        &mut self,
+        item_type: &str,
+        fields: &BTreeMap<String, serde_yaml::Value>,
+    ) -> Result<String> {
+        let plugin = plugins::find_item_plugin(&self.config.site_config.plugins, item_type)
+            .ok_or_else(|| {
+                Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "No plugin renders items of type {} used in {}",
+                        item_type, self.current_file
+                    ),
+                )
+            })?
+            .clone();
+
+        let mut item = serde_json::Map::new();
+
+        for (name, value) in fields {
+            let value = match value {
+                serde_yaml::Value::String(text) => {
+                    serde_json::Value::String(self.process_special_values(text)?)
+                }
+                value => serde_json::to_value(value)?,
+            };
+
+            item.insert(name.clone(), value);
+        }
+
+        plugin
+            .call(
+                &self.config.input_dir,
+                &serde_json::json!({
+                    "hook": "item",
+                    "type": item_type,
+                    "item": item,
+                    "page": self.current_page,
+                }),
+            )
+            .wrap_err_with(|| format!("In item {} of {}", item_type, self.current_file))
+    }
+
+    /// A decorative `<div>` with a generated class for its height, so spacers of the same
+    /// height share a rule.
+    fn spacer(&mut self, height: &Option<String>, style: &Option<String>) -> Result<String> {
+        let height = match height {
+            Some(height) => self.process_special_values(height)?,
+            None => "1em".to_string(),
+        };
+
+        if height.trim().is_empty() || height.contains([';', '{', '}']) {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid spacer height {:?} in {}",
+                    height, self.current_file
+                ),
+            )
+            .into());
+        }
+
+        let mut class = format!("spacer-{}", hash::short_hash(height.trim().as_bytes()));
+
+        self.generated_styles.insert(
+            format!(".{}", class),
+            StyleRule::from([("height".to_string(), height.trim().to_string())]),
+        );
+
+        if let Some(style) = style {
+            class.push(' ');
+            class.push_str(style);
+        }
+
+        Ok(format!(
+            "<div class=\"{}\" aria-hidden=\"true\"></div>",
+            html::escape(&class)
+        ))
+    }
+
+    fn footnote_number(&mut self, id: &str) -> usize {
+        match self
+            .footnotes
+            .iter()
+            .position(|(footnote_id, _)| footnote_id == id)
+        {
+            Some(index) => index + 1,
+            None => {
+                self.footnote_counter += 1;
+                self.footnotes.push((id.to_string(), String::new()));
+                self.footnote_counter
+            }
+        }
+    }
+
+    fn footnote_ref(&mut self, id: &str, text: &str) -> Result<String> {
+        let number = self.footnote_number(id);
+
+        let footnote = &mut self.footnotes[number - 1];
+        if footnote.1.is_empty() {
+            footnote.1 = html::escape(text);
+        }
+
+        Ok(format!(
+            "<sup><a href=\"#fn-{}\">[{}]</a></sup>",
+            html::escape(id),
+            number
+        ))
+    }
+
+    fn footnote(&mut self, id: &str, content: &[BlockItem]) -> Result<String> {
+        let mut output = String::new();
+        let number = self.footnote_number(id);
+
+        output.push_str(&self.get_indent());
+        output.push_str(&format!(
+            "<div id=\"fn-{}\">{}.\n",
+            html::escape(id),
+            number
+        ));
+
+        self.indent_level += 1;
+        for item in content {
+            output.push_str(&self.construct_block(item)?);
+        }
+        self.indent_level -= 1;
+
+        output.push_str(&self.get_indent());
+        output.push_str("</div>");
+
+        self.emitted_footnotes.insert(id.to_string());
+
+        Ok(output)
+    }
+
+    fn emit_footnotes(&mut self) -> String {
+        let mut output = String::new();
+
+        for (index, (id, text)) in self.footnotes.iter().enumerate() {
+            if self.emitted_footnotes.contains(id) {
+                continue;
+            }
+
+            output.push_str(&self.get_indent());
+            output.push_str(&format!(
+                "<div id=\"fn-{}\">{}. {}</div>\n",
+                html::escape(id),
+                index + 1,
+                text
+            ));
+        }
+
+        self.footnote_counter = 0;
+        self.footnotes.clear();
+        self.emitted_footnotes.clear();
+
+        output
+    }
+
+    fn asset_url(&self, url: &str) -> String {
+        self.config.asset_map.rewrite_url(url, &self.page_dir())
+    }
+
+    /// Directory the page being constructed is output to, relative to the output directory
+    /// with `/` as the separator. Relative URLs in the page resolve against it.
+    fn page_dir(&self) -> String {
+        match self.page_path().parent() {
+            Some(dir) => assets::relative_key(dir),
+            None => String::new(),
+        }
+    }
+
+    fn get_indent(&self) -> String {
+        let mut indent = String::new();
+        for _ in 0..self.indent_level {
+            indent.push_str(self.config.indent_string);
+        }
+        indent
+    }
+
+    fn for_each_values(&mut self, values: &ForEachValues) -> Result<Vec<serde_yaml::Value>> {
+        let reference = match values {
+            ForEachValues::List(values) => return Ok(values.clone()),
+            ForEachValues::Data(reference) => reference,
+        };
+
+        let value = if reference == "$tags" {
+            return self.tag_values();
+        } else if reference == "$page.related" && self.config.site_config.related.is_some() {
+            return self.related_values();
+        } else if let Some(field) = reference.strip_prefix("$page.") {
+            self.current_frontmatter
+                .as_ref()
+                .and_then(|page| page.value(field))
+                .unwrap_or(serde_yaml::Value::Sequence(Vec::new()))
+        } else if let Some(path) = reference.strip_prefix("$data.") {
+            self.data_value(path)?
+        } else {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "ForEach: values must be a list or a $data, $page or $tags reference, got {} in {}",
+                    reference, self.current_file
+                ),
+            )
+            .into());
+        };
+
+        match value {
+            serde_yaml::Value::Sequence(values) => Ok(values),
+            _ => Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "ForEach: {} in {} is not a list",
+                    reference, self.current_file
+                ),
+            )
+            .into()),
+        }
+    }
+
+    /// Language of the page being constructed: the `lang` of its markdown frontmatter, or
+    /// else the one of the directory it's in. `None` if the site has no languages.
+    fn page_language(&self) -> Option<String> {
+        let i18n = &self.config.site_config.i18n;
+
+        self.page_frontmatter
+            .as_ref()
+            .and_then(|page| page.get("lang"))
+            .filter(|language| i18n.is_language(language))
+            .or_else(|| i18n.page_language(&self.current_page).map(str::to_string))
+    }
+
+    /// `hreflang` links to each language version of the page, the default language's also
+    /// being the `x-default`. None if the page has no translations.
+    fn language_alternates(&self) -> Vec<String> {
+        let site = &self.config.site_config;
+        let i18n = &site.i18n;
+
+        let versions = i18n
+            .languages
+            .iter()
+            .map(|language| {
+                (
+                    language,
+                    i18n.translated_page_name(&self.current_page, language),
+                )
+            })
+            .filter(|(_, name)| self.block_items.contains_key(name))
+            .collect::<Vec<_>>();
+
+        if versions.len() < 2 {
+            return Vec::new();
+        }
+
+        let mut links = Vec::new();
+
+        for (language, name) in versions {
+            let path = site.page_path(&name);
+            let url = match site.absolute_url(&path) {
+                Some(url) => site.page_url(url),
+                None => site.page_url(relative_url(&self.page_path(), &path)),
+            };
+
+            let mut hreflangs = vec![language.as_str()];
+            if Some(language.as_str()) == i18n.default_language() {
+                hreflangs.push("x-default");
+            }
+
+            for hreflang in hreflangs {
+                links.push(format!(
+                    "<link rel=\"alternate\" hreflang=\"{}\" href=\"{}\" />",
+                    html::escape(hreflang),
+                    html::escape(&url)
+                ));
+            }
+        }
+
+        links
+    }
+
+    /// Looks up a `$t(key)` string in the language of the page, recording the file it comes
+    /// from as a dependency.
+    fn translation(&mut self, key: &str) -> Result<String> {
+        let i18n = &self.config.site_config.i18n;
+        let language = self.page_language().ok_or_else(|| {
+            Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "$t({}) used in {}, but the site config has no i18n languages",
+                    key, self.current_file
+                ),
+            )
+        })?;
+
+        match self.translations.get(i18n, &language, key) {
+            Some((file, value)) => {
+                self.dependencies.files.insert(file.to_path_buf());
+                Ok(value)
+            }
+            None => Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unknown translation {} for {} used in {}",
+                    key, language, self.current_file
+                ),
+            )
+            .into()),
+        }
+    }
+
+    /// Looks up a `$data` value, recording the file it comes from as a dependency.
+    fn data_value(&mut self, path: &str) -> Result<serde_yaml::Value> {
+        match self.data.get(path) {
+            Some((file, value)) => {
+                self.dependencies.files.insert(file.to_path_buf());
+                Ok(value.clone())
+            }
+            None => Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown data value {} used in {}", path, self.current_file),
+            )
+            .into()),
+        }
+    }
+
+    fn for_each(&mut self, values: &[serde_yaml::Value], items: &[BlockItem]) -> Result<String> {
+        let mut output = String::new();
+
+        for value in values {
+            self.current_loop_value = value.clone();
+
+            for item in items {
+                output.push_str(&self.construct_block(item)?);
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn for_each_file(
+        &mut self,
+        pattern: &str,
+        query: &FileQuery,
+        items: &[BlockItem],
+    ) -> Result<String> {
+        let entries = self.query_files(pattern, query)?;
+
+        self.for_each_entry(entries, items)
+    }
+
+    /// Splits the files a pattern matches into pages of `per_page` and loops over the ones
+    /// on the page being constructed.
+    fn paginate(
+        &mut self,
+        pattern: &str,
+        per_page: usize,
+        query: &FileQuery,
+        items: &[BlockItem],
+    ) -> Result<String> {
+        if per_page == 0 {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "$paginate: per_page must be at least 1 in {}",
+                    self.current_file
+                ),
+            )
+            .into());
+        }
+
+        let entries = self.query_files(pattern, query)?;
+        let page_count = entries.len().div_ceil(per_page).max(1);
+
+        self.pagination.found_page_count = Some(
+            self.pagination
+                .found_page_count
+                .map_or(page_count, |found| found.max(page_count)),
+        );
+
+        let entries = entries
+            .into_iter()
+            .skip((self.pagination.number - 1) * per_page)
+            .take(per_page)
+            .collect();
+
+        self.for_each_entry(entries, items)
+    }
+
+    /// Loops over file names and their frontmatter, making each available as `$loop_value`
+    /// and `$page`.
+    fn for_each_entry(
+        &mut self,
+        entries: Vec<(PathBuf, String, Option<Frontmatter>)>,
+        items: &[BlockItem],
+    ) -> Result<String> {
+        let mut output = String::new();
+        let old_loop_file = self.current_loop_file.take();
+
+        for (path, file_name, page) in entries {
+            self.current_loop_value = serde_yaml::Value::String(file_name);
+            self.current_loop_file = Some(path);
+            let old_frontmatter = std::mem::replace(&mut self.current_frontmatter, page);
+
+            for item in items {
+                output.push_str(&self.construct_block(item)?);
+            }
+
+            self.current_frontmatter = old_frontmatter;
+        }
+
+        self.current_loop_file = old_loop_file;
+
+        Ok(output)
+    }
+
+    /// Returns the names and frontmatter of the files matching a pattern, filtered, sorted
+    /// and limited as the query asks.
+    fn query_files(
+        &mut self,
+        pattern: &str,
+        query: &FileQuery,
+    ) -> Result<Vec<(PathBuf, String, Option<Frontmatter>)>> {
+        self.dependencies.patterns.insert(pattern.to_string());
+
+        let pattern = self.config.input_dir.to_str().unwrap().to_string() + "/" + pattern;
+
+        let files = self.cache.glob(&pattern, for_each_match_options())?;
+
+        let mut entries = Vec::new();
+
+        for entry in files.iter() {
+            // glob leaves out the leading `./` of the pattern
+            let ignored = without_cur_dir(entry)
+                .strip_prefix(without_cur_dir(&self.config.input_dir))
+                .is_ok_and(|relative| self.config.site_config.is_ignored(relative));
+
+            if ignored {
+                continue;
+            }
+
+            let file_name = match entry.file_name() {
+                Some(file_name) => file_name.to_string_lossy().to_string(),
+                None => continue,
+            };
+
+            // looping over markdown files makes their frontmatter available as $page
+            let mut page = if entry.extension().is_some_and(|ext| ext == "md") {
+                let parsed = self.cache.markdown(entry)?;
+
+                if self.is_hidden(&parsed.frontmatter) {
+                    continue;
+                }
+
+                Some(parsed.frontmatter.clone())
+            } else {
+                None
+            };
+
+            if let Some(filter) = &query.filter {
+                self.current_loop_value = serde_yaml::Value::String(file_name.clone());
+                let old_frontmatter = std::mem::replace(&mut self.current_frontmatter, page);
+                let keep = self.condition(filter);
+                page = std::mem::replace(&mut self.current_frontmatter, old_frontmatter);
+
+                if !keep? {
+                    continue;
+                }
+            }
+
+            entries.push((entry.clone(), file_name, page));
+        }
+
+        match query.sort_by.as_deref() {
+            None => {}
+            Some("name") => entries.sort_by(|a, b| a.1.cmp(&b.1)),
+            Some("modified") => {
+                entries.sort_by_cached_key(|(entry, _, _)| {
+                    std::fs::metadata(entry).and_then(|m| m.modified()).ok()
+                });
+            }
+            Some(field) => entries.sort_by_cached_key(|(_, _, page)| {
+                page.as_ref()
+                    .and_then(|page| page.get(field))
+                    .unwrap_or_default()
+            }),
+        }
+
+        if query.order == SortOrder::Descending {
+            entries.reverse();
+        }
+
+        if let Some(limit) = query.limit {
+            entries.truncate(limit);
+        }
+
+        Ok(entries)
+    }
+
+    /// Evaluates a `$if` condition: either `a == b` / `a != b`, comparing the two sides as
+    /// strings, `list contains item`, checking the `, ` separated items of a list like
+    /// `$page.tags`, or a single value, which is true unless it is empty or `false`. Special
+    /// values are substituted on each side separately, and either side may be quoted.
+    fn condition(&mut self, expression: &str) -> Result<bool> {
+        match COMPARISON_REGEX.captures(expression.trim()) {
+            Some(caps) => {
+                let left = self.condition_operand(&caps[1])?;
+                let right = self.condition_operand(&caps[3])?;
+
+                Ok(match caps[2].trim() {
+                    "contains" => left.split(',').any(|item| item.trim() == right),
+                    "==" => left == right,
+                    _ => left != right,
+                })
+            }
+            None => {
+                let value = self.condition_operand(expression)?;
+
+                Ok(!value.is_empty() && value != "false")
+            }
+        }
+    }
+
+    fn condition_operand(&mut self, operand: &str) -> Result<String> {
+        let operand = operand.trim();
+
+        let unquoted = ['"', '\'']
+            .iter()
+            .find_map(|quote| {
+                operand
+                    .strip_prefix(*quote)
+                    .and_then(|rest| rest.strip_suffix(*quote))
+            })
+            .unwrap_or(operand);
+
+        self.process_special_values(unquoted)
+    }
+
+    /// Looks up a `$pagination.name` value. A page without `$paginate` is a single page with
+    /// no previous or next page.
+    fn pagination_value(&self, name: &str) -> Option<String> {
+        let Pagination {
+            number, page_count, ..
+        } = self.pagination;
+        let page_count = page_count.unwrap_or(number);
+        let current = pagination_path(
+            &self.current_path,
+            number,
+            self.config.site_config.pretty_urls,
+        );
+        let url = |number| {
+            self.config.site_config.page_url(relative_url(
+                &current,
+                &pagination_path(
+                    &self.current_path,
+                    number,
+                    self.config.site_config.pretty_urls,
+                ),
+            ))
+        };
+
+        match name {
+            "page" => Some(number.to_string()),
+            "pages" => Some(page_count.to_string()),
+            "prev_url" if number > 1 => Some(url(number - 1)),
+            "next_url" if number < page_count => Some(url(number + 1)),
+            "prev_url" | "next_url" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    fn loop_value(&self) -> Result<String> {
+        Ok(frontmatter::value_to_string(&self.current_loop_value))
+    }
+
+    fn loop_value_filename(&self) -> Result<String> {
+        let output = self.loop_value()?;
+
+        let output = Path::new(&output)
+            .file_stem()
+            .unwrap_or_else(|| OsStr::new(""))
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        Ok(output)
+    }
+
+    fn process_special_values(&mut self, value: &str) -> Result<String> {
+        let mut s = value.to_string();
+        let cached_filename = &self.loop_value_filename().ok();
+        let cached_loop_value = &self.loop_value()?;
+
+        // fields of map values, like $loop_value.title, are substituted before the plain
+        // value so the field name isn't left behind
+        if let serde_yaml::Value::Mapping(_) = self.current_loop_value {
+            if let Some(caps) = LOOP_FIELD_REGEX.captures_iter(&s).find(|caps| {
+                caps.get(1).is_none()
+                    && data_files::lookup(&self.current_loop_value, &caps[2]).is_none()
+            }) {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Unknown field {} of $loop_value used in {}",
+                        &caps[2][1..],
+                        self.current_file
+                    ),
+                )
+                .into());
+            }
+
+            s = LOOP_FIELD_REGEX
+                .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                    Some(_) => format!("\\$loop_value{}", &caps[2]),
+                    None => data_files::lookup(&self.current_loop_value, &caps[2])
+                        .map(frontmatter::value_to_string)
+                        .unwrap_or_default(),
+                })
+                .to_string();
+        }
+
+        if let Some(filename) = cached_filename {
+            s = LOOP_FILENAME_REGEX
+                .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                    Some(_) => "$loop_value_filename".to_string(),
+                    None => filename.clone(),
+                })
+                .to_string();
+        }
+
+        s = LOOP_VALUE_REGEX
+            .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                Some(_) => "$loop_value".to_string(),
+                None => cached_loop_value.clone(),
+            })
+            .to_string();
+
+        s = ENV_REGEX
+            .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                Some(_) => format!("$env_{}", &caps[2]),
+                None => std::env::var(&caps[2]).unwrap_or_else(|_| {
+                    warn!(
+                        "Environment variable {} is not set, substituting an empty string in {}",
+                        caps[2].yellow().bold(),
+                        self.current_file.yellow().bold()
+                    );
+                    String::new()
+                }),
+            })
+            .to_string();
+
+        // matching the escape along with the param keeps adjacent params like
+        // "$param.first $param.last" from sharing a boundary character
+
+        if let Some(caps) = PARAM_REGEX
+            .captures_iter(&s)
+            .find(|caps| caps.get(1).is_none() && !self.current_params.contains_key(&caps[2]))
+        {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown param {} used in {}", &caps[2], self.current_file),
+            )
+            .into());
+        }
+
+        s = PARAM_REGEX
+            .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                Some(_) => format!("$param.{}", &caps[2]),
+                None => self.current_params[&caps[2]].clone(),
+            })
+            .to_string();
+
+        let site_config = &self.config.site_config;
+
+        if let Some(caps) = SITE_REGEX
+            .captures_iter(&s)
+            .find(|caps| caps.get(1).is_none() && site_config.variable(&caps[2]).is_none())
+        {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unknown site variable {} used in {}",
+                    &caps[2], self.current_file
+                ),
+            )
+            .into());
+        }
+
+        s = SITE_REGEX
+            .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                Some(_) => format!("$site.{}", &caps[2]),
+                None => site_config.variable(&caps[2]).unwrap_or_default(),
+            })
+            .to_string();
+
+        s = self.theme_values(&s)?;
+
+        let base_url = site_config.base_url_prefix();
+
+        s = BASE_URL_REGEX
+            .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                Some(_) => "$base_url".to_string(),
+                None => base_url.to_string(),
+            })
+            .to_string();
+
+        let build_date = self.build_date.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let file_modified = if DATE_REGEX
+            .captures_iter(&s)
+            .any(|caps| caps.get(1).is_none() && &caps[2] == "file.modified")
+        {
+            Some(
+                self.file_modified()?
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+            )
+        } else {
+            None
+        };
+
+        s = DATE_REGEX
+            .replace_all(&s, |caps: &Captures| match (caps.get(1), &caps[2]) {
+                (Some(_), name) => format!("${}", name),
+                (None, "build_date") => build_date.clone(),
+                (None, _) => file_modified.clone().unwrap_or_default(),
+            })
+            .to_string();
+
+        let mut data_values = BTreeMap::new();
+        for caps in DATA_REGEX.captures_iter(&s) {
+            if caps.get(1).is_none() {
+                let path = caps[2][1..].to_string();
+                let value = frontmatter::value_to_string(&self.data_value(&path)?);
+                data_values.insert(path, value);
+            }
+        }
+
+        s = DATA_REGEX
+            .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                Some(_) => format!("$data{}", &caps[2]),
+                None => data_values[&caps[2][1..]].clone(),
+            })
+            .to_string();
+
+        if let Some(caps) = TAG_REGEX
+            .captures_iter(&s)
+            .find(|caps| caps.get(1).is_none() && self.tag_value(&caps[2]).is_none())
+        {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "$tag.{} used in {}, which is not constructed as a tag page",
+                    &caps[2], self.current_file
+                ),
+            )
+            .into());
+        }
+
+        s = TAG_REGEX
+            .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                Some(_) => format!("$tag.{}", &caps[2]),
+                None => self.tag_value(&caps[2]).unwrap_or_default(),
+            })
+            .to_string();
+
+        if let Some(caps) = PAGINATION_REGEX
+            .captures_iter(&s)
+            .find(|caps| caps.get(1).is_none() && self.pagination_value(&caps[2]).is_none())
+        {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unknown pagination value {} used in {}",
+                    &caps[2], self.current_file
+                ),
+            )
+            .into());
+        }
+
+        s = PAGINATION_REGEX
+            .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                Some(_) => format!("$pagination.{}", &caps[2]),
+                None => self.pagination_value(&caps[2]).unwrap_or_default(),
+            })
+            .to_string();
+
+        let mut translations = BTreeMap::new();
+        for caps in TRANSLATION_REGEX.captures_iter(&s) {
+            if caps.get(1).is_none() {
+                translations.insert(caps[2].to_string(), self.translation(&caps[2])?);
+            }
+        }
+
+        s = TRANSLATION_REGEX
+            .replace_all(&s, |caps: &Captures| match caps.get(1) {
+                Some(_) => format!("$t({})", &caps[2]),
+                None => translations[&caps[2]].clone(),
+            })
+            .to_string();
+
+        s = frontmatter::substitute(&s, self.current_frontmatter.as_ref()).map_err(|e| {
+            Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} in {}", e, self.current_file),
+            )
+        })?;
+
+        // ${...} expressions go last, so what they produce isn't substituted again
+        s = interpolate::interpolate(&s, |name| self.interpolation_value(name))?;
+
+        Ok(s)
+    }
+
+    /// Value of a variable in a `${...}` expression, like `page.title` or `loop_value.name`.
+    /// `None` for names outside the scopes here, like a `${HOME}` in a shell snippet.
+    fn interpolation_value(&mut self, name: &str) -> Result<Option<String>> {
+        let (scope, field) = name.split_once('.').unwrap_or((name, ""));
+
+        let value = match (scope, field) {
+            ("loop_value", field) => data_files::lookup(&self.current_loop_value, field)
+                .map(frontmatter::value_to_string),
+            ("loop_value_filename", "") => Some(self.loop_value_filename()?),
+            ("base_url", "") => Some(self.config.site_config.base_url_prefix().to_string()),
+            ("param", name) => self.current_params.get(name).cloned(),
+            ("site", name) => self.config.site_config.variable(name),
+            ("theme", name) => self.config.site_config.theme.value(name),
+            ("t", key) if !key.is_empty() => Some(self.translation(key)?),
+            ("lang", "") => self.page_language(),
+            ("page", name) => match &self.current_frontmatter {
+                Some(frontmatter) => Some(frontmatter.get(name).unwrap_or_default()),
+                None => {
+                    return Err(Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "page.{} used outside of a markdown file or a page with meta",
+                            name
+                        ),
+                    )
+                    .into())
+                }
+            },
+            ("data", path) if !path.is_empty() => {
+                Some(frontmatter::value_to_string(&self.data_value(path)?))
+            }
+            ("env", name) => Some(std::env::var(name).unwrap_or_else(|_| {
+                warn!(
+                    "Environment variable {} is not set, substituting an empty string in {}",
+                    name.yellow().bold(),
+                    self.current_file.yellow().bold()
+                );
+                String::new()
+            })),
+            ("tag", name) => self.tag_value(name),
+            ("pagination", name) => self.pagination_value(name),
+            ("build_date", "") => Some(self.build_date.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            ("file", "modified") => Some(
+                self.file_modified()?
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+            ),
+            _ => return Ok(None),
+        };
+
+        match value {
+            Some(value) => Ok(Some(value)),
+            None => Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown value {} used in {}", name, self.current_file),
+            )
+            .into()),
+        }
+    }
+}
+
+fn modified_date(path: &Path) -> Result<DateTime<FixedOffset>> {
+    let modified = std::fs::metadata(path)?.modified()?;
+
+    Ok(DateTime::<chrono::Local>::from(modified).fixed_offset())
+}
+
+/// The `:link`, `:visited`, `:hover` and `:active` rules of an explicit link style.
+fn link_rules(
+    color: &LinkColor,
+    underline: bool,
+    visited_color: Option<&LinkColor>,
+) -> [StyleRule; 4] {
+    let mut normal_style = StyleRule::new();
+    let mut hover_style = StyleRule::new();
+
+    normal_style.insert("color".to_string(), color.normal.to_css());
+    if let Some(hover) = &color.hover {
+        hover_style.insert("color".to_string(), hover.to_css());
+    } else {
+        hover_style.insert("color".to_string(), color.normal.to_css());
+    }
+
+    if underline {
+        normal_style.insert("text-decoration".to_string(), "underline".to_string());
+    }
+
+    hover_style.insert("text-decoration".to_string(), "underline".to_string());
+
+    let mut visited_style = normal_style.clone();
+
+    if let Some(what) = visited_color {
+        visited_style.insert("color".to_string(), what.normal.to_css());
+    }
+
+    [
+        normal_style,
+        visited_style,
+        hover_style.clone(),
+        hover_style,
+    ]
+}
+
+/// The include of the header or footer of a page, from the site-wide block and what the
+/// page's `html` item says.
+fn page_region(site_block: &Option<String>, region: &Option<PageRegion>) -> Option<BlockItem> {
+    let name = match region {
+        None | Some(PageRegion::Enabled(true)) => site_block.clone()?,
+        Some(PageRegion::Enabled(false)) => return None,
+        Some(PageRegion::Block(name)) => name.clone(),
+    };
+
+    Some(BlockItem::Include(IncludeTarget::Plain(name)))
+}
+
+/// Whether a head style links to the generated_style.css all pages share.
+fn is_shared_style_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+
+    path == styles::SHARED_STYLE_FILE || path.ends_with(&format!("/{}", styles::SHARED_STYLE_FILE))
+}
+
+fn without_cur_dir(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn construct(yaml: &str) -> Result<String> {
+        construct_with(yaml, |_| ())
+    }
+
+    /// Like `construct`, with `configure` changing the config first.
+    fn construct_with(
+        yaml: &str,
+        configure: impl FnOnce(&mut BlockBuilderConfig),
+    ) -> Result<String> {
+        let input = tempfile::tempdir()?;
+        let mut config =
+            BlockBuilderConfig::new(input.path().to_path_buf(), input.path().join("out"));
+        configure(&mut config);
+        let mut builder = BlockBuilder::new(config)?;
+
+        builder.add_block("page", serde_yaml::from_str(yaml)?);
+        Ok(builder.construct_by_name("page")?.trim_end().to_string())
+    }
+
+    #[test]
+    fn text_paragraphs() {
+        let html = construct(
+            "text:\n  paragraphs: true\n  text: |\n    First one,\n    still first.\n\n    Second <b>.\n\n\n    Third.\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            html,
+            "<p>First one, still first.</p>\n<p>Second &lt;b&gt;.</p>\n<p>Third.</p>"
+        );
+    }
+
+    #[test]
+    fn text_inline_markdown() {
+        let html = construct(
+            "text:\n  markdown: true\n  text: 'See *the* [docs](https://example.com/docs).'\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            html,
+            "See <em>the</em> <a href=\"https://example.com/docs\">docs</a>."
+        );
+    }
+
+    #[test]
+    fn text_options_loop_value() {
+        let html = construct(
+            "$for_each:\n  values: [one, two]\n  items:\n    - text:\n        paragraphs: true\n        text: \"$loop_value\\n\\nafter $loop_value\"\n    - text:\n        markdown: true\n        text: '[$loop_value](/$loop_value)'\n",
+        )
+        .unwrap();
+
+        assert!(html.contains("<p>one</p>\n<p>after one</p>"), "{}", html);
+        assert!(html.contains("<p>two</p>\n<p>after two</p>"), "{}", html);
+        assert!(html.contains("<a href=\"/one\">one</a>"), "{}", html);
+        assert!(html.contains("<a href=\"/two\">two</a>"), "{}", html);
+    }
+
+    #[test]
+    fn env_values() {
+        std::env::set_var("BLOCKBLOG_TEST_FIRST", "one");
+        std::env::set_var("BLOCKBLOG_TEST_SECOND", "two");
+
+        let html =
+            construct("text: '$env_BLOCKBLOG_TEST_FIRST $env_BLOCKBLOG_TEST_SECOND'\n").unwrap();
+        assert_eq!(html, "one two");
+
+        let html =
+            construct("text: '($env_BLOCKBLOG_TEST_FIRST,$env_BLOCKBLOG_TEST_SECOND)'\n").unwrap();
+        assert_eq!(html, "(one,two)");
+    }
+
+    #[test]
+    fn escaped_and_unset_env_values() {
+        std::env::set_var("BLOCKBLOG_TEST_ESCAPED", "value");
+        std::env::remove_var("BLOCKBLOG_TEST_UNSET");
+
+        let html = construct(
+            "text: '\\$env_BLOCKBLOG_TEST_ESCAPED [$env_BLOCKBLOG_TEST_UNSET] $env_BLOCKBLOG_TEST_ESCAPEDx'\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            html,
+            "$env_BLOCKBLOG_TEST_ESCAPED [] $env_BLOCKBLOG_TEST_ESCAPEDx"
+        );
+    }
+
+    #[test]
+    fn void_block() {
+        let html =
+            construct("block:\n  html_type: hr\n  style: rule\n  attrs:\n    role: presentation\n")
+                .unwrap();
+
+        assert_eq!(html, "<hr class=\"rule\" role=\"presentation\" />");
+    }
+
+    #[test]
+    fn custom_element_block() {
+        let html = construct("block:\n  html_type: my-widget\n  id: w\n  items:\n    - text: Hi\n")
+            .unwrap();
+
+        assert_eq!(html, "<my-widget id=\"w\">\n    Hi\n</my-widget>");
+    }
+
+    #[test]
+    fn invalid_block_elements() {
+        let error =
+            construct("block:\n  html_type: input\n  items:\n    - text: Hi\n").unwrap_err();
+        let error = format!("{:#}", error);
+        assert!(
+            error.contains("input is a void element and cannot have children"),
+            "{}",
+            error
+        );
+
+        for html_type in ["'<div>'", "'my widget'", "'1div'"] {
+            let error = construct(&format!("block:\n  html_type: {}\n", html_type)).unwrap_err();
+            let error = format!("{:#}", error);
+            assert!(error.contains("Invalid html_type"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn conditions() {
+        let html = construct(
+            "$for_each:\n  values: [hi, 'a, b', 'x == y']\n  items:\n    - $if:\n        condition: $loop_value == hi\n        then:\n          - text: '[greeting]'\n        else:\n          - $if:\n              condition: $loop_value contains b\n              then:\n                - text: '[list]'\n              else:\n                - $if:\n                    condition: \"$loop_value != 'x == y'\"\n                    then:\n                      - text: '[other]'\n                    else:\n                      - text: '[tricky]'\n",
+        )
+        .unwrap();
+        let results = html
+            .split_whitespace()
+            .filter(|word| word.starts_with('['))
+            .collect::<Vec<_>>();
+
+        assert_eq!(results, ["[greeting]", "[list]", "[tricky]"]);
+    }
+
+    #[test]
+    fn adjacent_loop_values() {
+        let html = construct(
+            "$for_each:\n  values: [posts/a.md]\n  items:\n    - text: '$loop_value $loop_value|$loop_value_filename,$loop_value_filename|\\$loop_value'\n",
+        )
+        .unwrap();
+
+        assert_eq!(html, "posts/a.md posts/a.md|a,a|$loop_value");
+    }
+
+    #[test]
+    fn comment_dashes() {
+        let debug = |config: &mut BlockBuilderConfig| config.debug = true;
+
+        let html = construct_with("comment: 'arrow ---> here <b>x</b>'\n", debug).unwrap();
+        assert_eq!(html, "<!-- arrow &#45;&#45;&#45;> here <b>x</b> -->");
+
+        let html = construct_with("comment: 'trailing -'\n", debug).unwrap();
+        assert_eq!(html, "<!-- trailing &#45; -->");
+
+        assert_eq!(construct("comment: hidden\n").unwrap(), "");
+    }
+
+    #[test]
+    fn breadcrumbs_separators() {
+        let slash = construct("breadcrumbs: {}\n").unwrap();
+        let arrow = construct("breadcrumbs:\n  separator: '>'\n").unwrap();
+        let class = |html: &str| {
+            let start = html.find("<nav class=\"").unwrap() + 12;
+            html[start..start + html[start..].find('"').unwrap()].to_string()
+        };
+
+        assert!(class(&slash).starts_with("breadcrumbs breadcrumbs-"));
+        assert_ne!(class(&slash), class(&arrow));
+
+        for style in ["'a b'", "'x{}'", "'1st'"] {
+            let error = construct(&format!("breadcrumbs:\n  style: {}\n", style)).unwrap_err();
+            let error = format!("{:#}", error);
+            assert!(error.contains("Invalid style"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn tabs_style() {
+        let html = construct("tabs:\n  tabs:\n    - label: One\n      items: []\n  style: panes\n")
+            .unwrap();
+        assert!(html.starts_with("<div class=\"panes\">"), "{}", html);
+
+        let error = construct("tabs:\n  tabs: []\n  style: 'a > b'\n").unwrap_err();
+        let error = format!("{:#}", error);
+        assert!(error.contains("Invalid style \"a > b\" in "), "{}", error);
+    }
+}
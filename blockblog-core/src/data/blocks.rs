@@ -0,0 +1,959 @@
+use std::collections::BTreeMap;
+
+use serde::{
+    de::{self, IntoDeserializer, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::callout::CalloutKind;
+use super::colors::LinkColor;
+use super::diagrams::DiagramKind;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum LinkStyle {
+    #[serde(rename = "explicit")]
+    Explicit {
+        underline: bool,
+        color: LinkColor,
+        visited_color: Option<LinkColor>,
+        /// Used instead of `color` in dark mode.
+        dark_color: Option<LinkColor>,
+        dark_visited_color: Option<LinkColor>,
+    },
+    #[serde(rename = "style")]
+    Style(String),
+    /// One of the `link_styles` of the site config, by name.
+    #[serde(rename = "preset")]
+    Preset(String),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Head {
+    pub lang: Option<String>,
+    pub title: Option<String>,
+    /// Defaults to the `description` of the page's markdown frontmatter.
+    pub description: Option<String>,
+    /// Defaults to the page's URL under `base_url`, if that is set.
+    pub canonical: Option<String>,
+    /// Image shown when the page is shared. Defaults to the `image` of the page's markdown
+    /// frontmatter.
+    pub image: Option<String>,
+    /// Adds Open Graph `og:` tags built from the title, description, image and URL.
+    pub open_graph: Option<bool>,
+    /// Twitter card type, like `summary` or `summary_large_image`.
+    pub twitter_card: Option<String>,
+    /// Further `<meta>` tags, by name. `og:` names are written as properties.
+    pub meta: Option<BTreeMap<String, String>>,
+    pub icon: Option<String>,
+    pub styles: Option<Vec<String>>,
+    pub scripts: Option<Vec<Script>>,
+}
+
+/// A script in the head, given as its URL or with attributes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum Script {
+    Plain(String),
+    Options {
+        src: String,
+        #[serde(default)]
+        defer: bool,
+        #[serde(rename = "async", default)]
+        is_async: bool,
+        /// Loads the script as an ES module.
+        #[serde(default)]
+        module: bool,
+        integrity: Option<String>,
+        crossorigin: Option<String>,
+    },
+}
+
+/// Text that is HTML-escaped when rendered, unless given as `{ text, raw: true }`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum EscapableText {
+    Plain(String),
+    Options {
+        text: String,
+        #[serde(default)]
+        raw: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum TextContent {
+    Plain(String),
+    Options {
+        text: String,
+        #[serde(default)]
+        markdown: bool,
+        #[serde(default)]
+        paragraphs: bool,
+        #[serde(default)]
+        raw: bool,
+    },
+}
+
+/// CSS given inline, or as the path of a file relative to the input directory.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum StyleSource {
+    Css(String),
+    File { path: String },
+}
+
+/// Values a `ForEach` loops over: a list of strings or maps, or a `$data` reference to one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum ForEachValues {
+    List(Vec<serde_yaml::Value>),
+    Data(String),
+}
+
+/// How a `ForEach` over a file pattern picks and orders the matching files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct FileQuery {
+    /// `name`, `modified`, or a frontmatter field. Files are in path order otherwise.
+    #[serde(default)]
+    pub sort_by: Option<String>,
+    #[serde(default)]
+    pub order: SortOrder,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// A `$if` style condition, evaluated with each file's frontmatter as `$page`.
+    #[serde(default)]
+    pub filter: Option<String>,
+}
+
+impl FileQuery {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    #[default]
+    #[serde(rename = "asc")]
+    Ascending,
+    #[serde(rename = "desc")]
+    Descending,
+}
+
+/// A param a template takes: just its name, or with a type and a default that makes it
+/// optional.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum TemplateParam {
+    Plain(String),
+    Verbose {
+        name: String,
+        #[serde(rename = "type", default)]
+        param_type: ParamType,
+        #[serde(default)]
+        default: Option<String>,
+    },
+}
+
+impl TemplateParam {
+    pub fn name(&self) -> &str {
+        match self {
+            TemplateParam::Plain(name) | TemplateParam::Verbose { name, .. } => name,
+        }
+    }
+
+    pub fn param_type(&self) -> ParamType {
+        match self {
+            TemplateParam::Plain(_) => ParamType::String,
+            TemplateParam::Verbose { param_type, .. } => *param_type,
+        }
+    }
+
+    pub fn default(&self) -> Option<&str> {
+        match self {
+            TemplateParam::Plain(_) => None,
+            TemplateParam::Verbose { default, .. } => default.as_deref(),
+        }
+    }
+
+    /// Describes the param for error messages, like `title (string)`.
+    pub fn describe(&self) -> String {
+        format!("{} ({})", self.name(), self.param_type().name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ParamType {
+    #[default]
+    #[serde(rename = "string")]
+    String,
+    #[serde(rename = "number")]
+    Number,
+    #[serde(rename = "integer")]
+    Integer,
+    #[serde(rename = "bool")]
+    Bool,
+}
+
+impl ParamType {
+    pub fn name(self) -> &'static str {
+        match self {
+            ParamType::String => "string",
+            ParamType::Number => "number",
+            ParamType::Integer => "integer",
+            ParamType::Bool => "bool",
+        }
+    }
+
+    pub fn accepts(self, value: &str) -> bool {
+        let value = value.trim();
+
+        match self {
+            ParamType::String => true,
+            ParamType::Number => value.parse::<f64>().is_ok_and(f64::is_finite),
+            ParamType::Integer => value.parse::<i64>().is_ok(),
+            ParamType::Bool => value == "true" || value == "false",
+        }
+    }
+}
+
+/// What `input`, `textarea` and `select` items have in common.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct FormField {
+    pub name: String,
+    /// Shown in a `<label>` before the field.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// `field-<name>` by default, which the label points at.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub style: Option<String>,
+}
+
+/// Playback options of `video` and `audio` items.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct MediaOptions {
+    /// Shows the browser's playback controls, on by default.
+    #[serde(default = "default_controls")]
+    pub controls: bool,
+    /// Browsers only autoplay muted media.
+    #[serde(default)]
+    pub autoplay: bool,
+    #[serde(rename = "loop", default)]
+    pub looped: bool,
+    #[serde(default)]
+    pub muted: bool,
+    #[serde(default)]
+    pub style: Option<String>,
+}
+
+fn default_controls() -> bool {
+    true
+}
+
+/// An image of a `gallery`, either just its URL or with alt text and a caption.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum GalleryImage {
+    Plain(String),
+    Verbose {
+        path: String,
+        #[serde(default)]
+        alt: Option<String>,
+        #[serde(default)]
+        caption: Option<String>,
+    },
+}
+
+/// The header or footer of a page: another block than the site-wide one, or `false` to
+/// leave it out.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum PageRegion {
+    Enabled(bool),
+    Block(String),
+}
+
+/// A panel of a `tabs` item.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Tab {
+    pub label: String,
+    #[serde(default)]
+    pub items: Vec<BlockItem>,
+}
+
+/// An option of a `select`, either the same text and value or both given.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum SelectOption {
+    Plain(String),
+    Verbose { value: String, text: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum IncludeTarget {
+    Plain(String),
+    Verbose {
+        path: String,
+        #[serde(default)]
+        params: BTreeMap<String, String>,
+        /// Items the included block puts where it has `$slot`s of the same names.
+        #[serde(default)]
+        slots: BTreeMap<String, Vec<BlockItem>>,
+        /// Items the included block puts where it has `$children`, the same as a slot named
+        /// `children`.
+        #[serde(default)]
+        children: Vec<BlockItem>,
+    },
+}
+
+/// A thematic break. `hr` needs no options, so it can be written on its own.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct Separator {
+    pub style: Option<String>,
+    /// Hidden from screen readers unless set to `false`.
+    pub aria_hidden: Option<bool>,
+}
+
+fn separator_or_null<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Separator, D::Error> {
+    Ok(Option::<Separator>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// Deserialized through the impls below, which let an item without options, like `hr`, be
+// written on its own even though its variant isn't a unit variant.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(remote = "Self")]
+pub enum BlockItem {
+    #[serde(rename = "html")]
+    Html {
+        #[serde(rename = "head")]
+        head: Option<Head>,
+        #[serde(rename = "body")]
+        body: Option<Vec<BlockItem>>,
+        /// Output path of the page instead of `<name>.html`, relative to the output
+        /// directory. Supports the `{name}`, `{dir}`, `{stem}`, `{slug}` and `{date}`
+        /// placeholders, and ends in `index.html` when it ends in `/`.
+        #[serde(rename = "permalink", default)]
+        permalink: Option<String>,
+        /// Overrides the `header` of the site config.
+        #[serde(rename = "header", default)]
+        header: Option<PageRegion>,
+        /// Overrides the `footer` of the site config.
+        #[serde(rename = "footer", default)]
+        footer: Option<PageRegion>,
+        /// Fields of the page, available as `$page.name` in every block it is built from,
+        /// like frontmatter. `title`, `description` and `image` fill in the head.
+        #[serde(rename = "meta", default)]
+        meta: BTreeMap<String, serde_yaml::Value>,
+    },
+    #[serde(rename = "include")]
+    Include(IncludeTarget),
+    /// Constructs a layout block, usually a template with an `html` item, putting the given
+    /// items where it has `$slot`s of the same names.
+    #[serde(rename = "layout")]
+    Layout {
+        #[serde(rename = "name")]
+        name: String,
+        #[serde(rename = "params", default)]
+        params: BTreeMap<String, String>,
+        #[serde(rename = "slots", default)]
+        slots: BTreeMap<String, Vec<BlockItem>>,
+        /// Fields of the page, available as `$page.name` in every block it is built from,
+        /// like frontmatter. `title`, `description` and `image` fill in the head.
+        #[serde(rename = "meta", default)]
+        meta: BTreeMap<String, serde_yaml::Value>,
+    },
+    /// Where a layout puts the items a page fills the slot of this name with. Empty if the
+    /// page leaves it out.
+    #[serde(rename = "$slot")]
+    Slot(String),
+    /// Where an included block puts the `children` it was included with.
+    #[serde(rename = "$children")]
+    Children,
+    #[serde(rename = "template")]
+    Template {
+        #[serde(rename = "params", default)]
+        params: Vec<TemplateParam>,
+        #[serde(rename = "items")]
+        items: Vec<BlockItem>,
+    },
+    #[serde(rename = "title")]
+    Title(EscapableText),
+    /// A heading of any level. `title` is a heading of level 1.
+    #[serde(rename = "heading")]
+    Heading {
+        #[serde(rename = "text")]
+        text: String,
+        /// From 1 to 6, 2 by default.
+        #[serde(rename = "level")]
+        level: Option<usize>,
+        /// Made from the text if an anchor is wanted.
+        #[serde(rename = "id")]
+        id: Option<String>,
+        /// Ends the heading with a link to itself, like the headings of markdown.
+        #[serde(rename = "anchor", default)]
+        anchor: bool,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    #[serde(rename = "block")]
+    Block {
+        #[serde(rename = "style")]
+        style: Option<String>,
+        #[serde(rename = "id")]
+        id: Option<String>,
+        #[serde(rename = "html_type")]
+        html_type: Option<String>,
+        #[serde(rename = "data_attrs")]
+        data_attrs: Option<BTreeMap<String, String>>,
+        /// Any other attributes of the element, like `aria-label` or `role`.
+        #[serde(rename = "attrs")]
+        attrs: Option<BTreeMap<String, String>>,
+        #[serde(rename = "items", default)]
+        items: Vec<BlockItem>,
+    },
+    /// A `<ul>`, or an `<ol>` if ordered, with each item in its own `<li>`.
+    #[serde(rename = "list")]
+    List {
+        #[serde(rename = "ordered", default)]
+        ordered: bool,
+        #[serde(rename = "style")]
+        style: Option<String>,
+        #[serde(rename = "items", default)]
+        items: Vec<BlockItem>,
+    },
+    /// A `<table>` of inline rows or of the rows of a CSV file.
+    #[serde(rename = "table")]
+    Table {
+        #[serde(rename = "rows")]
+        rows: Option<Vec<Vec<serde_yaml::Value>>>,
+        #[serde(rename = "csv")]
+        csv: Option<String>,
+        /// Renders the first row as the table header.
+        #[serde(rename = "header", default)]
+        header: bool,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// The site menu from `blockblog.yml`, marking the entry for the current page as active.
+    #[serde(rename = "nav")]
+    Nav {
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// HTML written into the page as is, after special values are substituted.
+    #[serde(rename = "raw_html")]
+    RawHtml(String),
+    /// A `<style>` element with the given CSS or the contents of a CSS file.
+    #[serde(rename = "style_inline")]
+    StyleInline(StyleSource),
+    #[serde(rename = "markdown")]
+    Markdown(String),
+    #[serde(rename = "markdown_file")]
+    MarkdownFile(String),
+    /// A nested list of links to the headings of a markdown file, to show on the page that
+    /// includes it.
+    #[serde(rename = "toc")]
+    Toc {
+        /// Relative to the input directory.
+        #[serde(rename = "path")]
+        path: String,
+        /// Headings above this level are left out, 1 by default.
+        #[serde(rename = "min_level")]
+        min_level: Option<usize>,
+        /// Headings below this level are left out, 6 by default.
+        #[serde(rename = "max_level")]
+        max_level: Option<usize>,
+        #[serde(rename = "ordered", default)]
+        ordered: bool,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    #[serde(rename = "code")]
+    Code(EscapableText),
+    #[serde(rename = "code_file")]
+    CodeFile {
+        #[serde(rename = "path")]
+        path: String,
+        #[serde(rename = "language")]
+        language: Option<String>,
+        #[serde(rename = "line_numbers", default)]
+        line_numbers: bool,
+    },
+    #[serde(rename = "image")]
+    Image {
+        #[serde(rename = "path")]
+        path: String,
+        #[serde(rename = "alt")]
+        alt: Option<String>,
+        /// Resizes the image to fit this width, keeping its aspect ratio.
+        #[serde(rename = "width")]
+        width: Option<u32>,
+        #[serde(rename = "height")]
+        height: Option<u32>,
+        /// Generates the image at several widths and lets the browser pick through `srcset`.
+        #[serde(rename = "responsive", default)]
+        responsive: bool,
+        /// Converts the image to another format, given as its file extension.
+        #[serde(rename = "format")]
+        format: Option<String>,
+    },
+    #[serde(rename = "text")]
+    Text(TextContent),
+    #[serde(rename = "link")]
+    Link {
+        #[serde(rename = "text")]
+        text: String,
+        #[serde(rename = "url")]
+        url: String,
+        #[serde(rename = "link_style")]
+        link_style: LinkStyle,
+        #[serde(rename = "raw", default)]
+        raw: bool,
+    },
+    /// A `<time>` element showing `value`, or else the frontmatter `date` of `file` or when
+    /// it was last modified, or else the `date` of the markdown being rendered or the page's
+    /// markdown.
+    #[serde(rename = "date")]
+    Date {
+        #[serde(rename = "value")]
+        value: Option<String>,
+        /// Relative to the input directory.
+        #[serde(rename = "file")]
+        file: Option<String>,
+        /// A `strftime` format, `%Y-%m-%d` by default.
+        #[serde(rename = "format")]
+        format: Option<String>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// A search box listing the pages in the search index that contain what is typed.
+    #[serde(rename = "search")]
+    Search {
+        /// `Search` by default.
+        #[serde(rename = "placeholder")]
+        placeholder: Option<String>,
+        /// `search` by default.
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// A link that looks like a button if it has a `url`, or else a `<button>` of the
+    /// `action` type, `submit` by default.
+    #[serde(rename = "button")]
+    Button {
+        #[serde(rename = "text")]
+        text: String,
+        #[serde(rename = "url")]
+        url: Option<String>,
+        /// `submit`, `reset` or `button`.
+        #[serde(rename = "action")]
+        action: Option<String>,
+        /// `button` by default.
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// A `<form>` sending its fields to `action`.
+    #[serde(rename = "form")]
+    Form {
+        #[serde(rename = "action")]
+        action: String,
+        /// `get` or `post`, `post` by default.
+        #[serde(rename = "method")]
+        method: Option<String>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+        #[serde(rename = "items", default)]
+        items: Vec<BlockItem>,
+    },
+    #[serde(rename = "input")]
+    Input {
+        #[serde(flatten)]
+        field: FormField,
+        /// `text` by default.
+        #[serde(rename = "type")]
+        input_type: Option<String>,
+        #[serde(rename = "placeholder")]
+        placeholder: Option<String>,
+        #[serde(rename = "value")]
+        value: Option<String>,
+    },
+    #[serde(rename = "textarea")]
+    Textarea {
+        #[serde(flatten)]
+        field: FormField,
+        #[serde(rename = "placeholder")]
+        placeholder: Option<String>,
+        #[serde(rename = "rows")]
+        rows: Option<u32>,
+    },
+    #[serde(rename = "select")]
+    Select {
+        #[serde(flatten)]
+        field: FormField,
+        #[serde(rename = "options")]
+        options: Vec<SelectOption>,
+    },
+    /// A `<figure>` of an image or of any items, with a caption under it.
+    #[serde(rename = "figure")]
+    Figure {
+        /// Shown before the items, resized to `width` if given.
+        #[serde(rename = "image")]
+        image: Option<String>,
+        #[serde(rename = "alt")]
+        alt: Option<String>,
+        #[serde(rename = "width")]
+        width: Option<u32>,
+        /// Links the image to the full size original.
+        #[serde(rename = "link_full_size", default)]
+        link_full_size: bool,
+        #[serde(rename = "items", default)]
+        items: Vec<BlockItem>,
+        #[serde(rename = "caption")]
+        caption: Option<String>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// A grid of thumbnails of the images matching `pattern` or listed in `images`, each
+    /// linking to the full size image.
+    #[serde(rename = "gallery")]
+    Gallery {
+        /// Relative to the input directory.
+        #[serde(rename = "pattern")]
+        pattern: Option<String>,
+        #[serde(rename = "images")]
+        images: Option<Vec<GalleryImage>>,
+        /// 3 by default.
+        #[serde(rename = "columns")]
+        columns: Option<u32>,
+        /// Width of the thumbnails, 300 by default.
+        #[serde(rename = "thumbnail_width")]
+        thumbnail_width: Option<u32>,
+        /// `gallery` by default. Also the `data-gallery` of the links, for lightbox scripts.
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// Links to the home page and to each directory the page is in, ending with the page.
+    #[serde(rename = "breadcrumbs")]
+    Breadcrumbs {
+        /// `Home` by default.
+        #[serde(rename = "home")]
+        home: Option<String>,
+        /// Shown for the page, the title of its markdown or its name by default.
+        #[serde(rename = "title")]
+        title: Option<String>,
+        /// Shown between the links, `/` by default.
+        #[serde(rename = "separator")]
+        separator: Option<String>,
+        /// `breadcrumbs` by default.
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// Items hidden in a `<details>` until its `summary` is clicked.
+    #[serde(rename = "details")]
+    Details {
+        #[serde(rename = "summary")]
+        summary: String,
+        /// Shows the items from the start.
+        #[serde(rename = "open", default)]
+        open: bool,
+        #[serde(rename = "style")]
+        style: Option<String>,
+        #[serde(rename = "items", default)]
+        items: Vec<BlockItem>,
+    },
+    /// An admonition box of items, colored by its `kind`.
+    #[serde(rename = "callout")]
+    Callout {
+        /// `note` by default.
+        #[serde(rename = "kind", default)]
+        kind: CalloutKind,
+        /// The name of the kind by default.
+        #[serde(rename = "title")]
+        title: Option<String>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+        #[serde(rename = "items", default)]
+        items: Vec<BlockItem>,
+    },
+    /// A `<blockquote>` of `text` and then the items, with a footer crediting the `author`.
+    #[serde(rename = "quote")]
+    Quote {
+        #[serde(rename = "text")]
+        text: Option<String>,
+        #[serde(rename = "items", default)]
+        items: Vec<BlockItem>,
+        #[serde(rename = "author")]
+        author: Option<String>,
+        /// URL of the source, linked from the author.
+        #[serde(rename = "cite")]
+        cite: Option<String>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// Panels of items with a row of labels to switch between them, the first one shown.
+    /// Works without scripts.
+    #[serde(rename = "tabs")]
+    Tabs {
+        #[serde(rename = "tabs")]
+        tabs: Vec<Tab>,
+        /// `tabs` by default.
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// A `<video>` of a file copied like any other asset, or an external URL.
+    #[serde(rename = "video")]
+    Video {
+        #[serde(rename = "path")]
+        path: String,
+        /// Image shown before the video plays.
+        #[serde(rename = "poster")]
+        poster: Option<String>,
+        #[serde(flatten)]
+        options: MediaOptions,
+    },
+    #[serde(rename = "audio")]
+    Audio {
+        #[serde(rename = "path")]
+        path: String,
+        #[serde(flatten)]
+        options: MediaOptions,
+    },
+    /// A lazily loaded `<iframe>` of another page, like a map or a video player.
+    #[serde(rename = "embed")]
+    Embed {
+        #[serde(rename = "url")]
+        url: String,
+        /// Describes the frame for screen readers.
+        #[serde(rename = "title")]
+        title: Option<String>,
+        #[serde(rename = "width")]
+        width: Option<u32>,
+        #[serde(rename = "height")]
+        height: Option<u32>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// An embedded YouTube video, from youtube-nocookie.com so that nothing is stored
+    /// before it plays.
+    #[serde(rename = "youtube")]
+    Youtube {
+        #[serde(rename = "id")]
+        id: String,
+        #[serde(rename = "title")]
+        title: Option<String>,
+        /// Seconds into the video to start at.
+        #[serde(rename = "start")]
+        start: Option<u32>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// The summary of a markdown file, from its frontmatter or the start of its markdown.
+    #[serde(rename = "summary")]
+    Summary {
+        /// Relative to the input directory. Without it, the markdown being rendered, like the
+        /// file of a `$for_each` over markdown files, is used.
+        #[serde(rename = "file")]
+        file: Option<String>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// A note about the block definition, left out of the page unless debugging.
+    #[serde(rename = "comment")]
+    Comment(String),
+    #[serde(rename = "br")]
+    Br,
+    #[serde(
+        rename = "hr",
+        alias = "separator",
+        deserialize_with = "separator_or_null"
+    )]
+    Separator(Separator),
+    /// Empty space of a given height, like `2em` or `$theme.large`.
+    #[serde(rename = "spacer")]
+    Spacer {
+        /// `1em` by default.
+        #[serde(rename = "height")]
+        height: Option<String>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// TeX math, rendered with KaTeX like the math in markdown.
+    #[serde(rename = "math")]
+    Math {
+        #[serde(rename = "tex")]
+        tex: String,
+        /// Whether it is a block of its own rather than part of a line. `true` by default.
+        #[serde(rename = "display")]
+        display: Option<bool>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// A Mermaid or Graphviz diagram, from its source or a file relative to the input
+    /// directory.
+    #[serde(rename = "diagram")]
+    Diagram {
+        #[serde(rename = "source")]
+        source: Option<String>,
+        #[serde(rename = "file")]
+        file: Option<String>,
+        #[serde(rename = "kind", default)]
+        kind: DiagramKind,
+        /// Describes the diagram for screen readers.
+        #[serde(rename = "title")]
+        title: Option<String>,
+        #[serde(rename = "style")]
+        style: Option<String>,
+    },
+    /// An item of a type a plugin renders, with its other fields passed on to the plugin.
+    #[serde(rename = "plugin")]
+    Plugin {
+        #[serde(rename = "type")]
+        item_type: String,
+        #[serde(flatten)]
+        fields: BTreeMap<String, serde_yaml::Value>,
+    },
+    #[serde(rename = "footnote_ref")]
+    FootnoteRef {
+        #[serde(rename = "id")]
+        id: String,
+        #[serde(rename = "text")]
+        text: String,
+    },
+    #[serde(rename = "footnote")]
+    Footnote {
+        #[serde(rename = "id")]
+        id: String,
+        #[serde(rename = "content")]
+        content: Vec<BlockItem>,
+    },
+    #[serde(rename = "$for_each")]
+    ForEach {
+        #[serde(rename = "pattern")]
+        pattern: Option<String>,
+        #[serde(rename = "values")]
+        values: Option<ForEachValues>,
+        #[serde(flatten)]
+        query: FileQuery,
+        #[serde(rename = "items")]
+        items: Vec<BlockItem>,
+    },
+    #[serde(rename = "$paginate")]
+    Paginate {
+        #[serde(rename = "pattern")]
+        pattern: String,
+        #[serde(rename = "per_page")]
+        per_page: usize,
+        #[serde(flatten)]
+        query: FileQuery,
+        #[serde(rename = "items")]
+        items: Vec<BlockItem>,
+    },
+    #[serde(rename = "$if")]
+    If {
+        #[serde(rename = "condition")]
+        condition: String,
+        #[serde(rename = "then", default)]
+        then: Vec<BlockItem>,
+        #[serde(rename = "else", default)]
+        otherwise: Vec<BlockItem>,
+    },
+    #[serde(rename = "$loop_value")]
+    LoopValue,
+    #[serde(rename = "$loop_value_filename")]
+    LoopValueFileName,
+}
+
+impl Serialize for BlockItem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BlockItem::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockItem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BlockItemVisitor)
+    }
+}
+
+struct BlockItemVisitor;
+
+impl<'de> Visitor<'de> for BlockItemVisitor {
+    type Value = BlockItem;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a block item")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<BlockItem, E> {
+        match value {
+            "hr" | "separator" => Ok(BlockItem::Separator(Separator::default())),
+            value => BlockItem::deserialize(value.into_deserializer()),
+        }
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<BlockItem, A::Error> {
+        BlockItem::deserialize(de::value::MapAccessDeserializer::new(map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(yaml: &str) -> Vec<BlockItem> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn separator_forms() {
+        let bare = BlockItem::Separator(Separator::default());
+
+        assert_eq!(items("- hr\n- hr:\n- hr: {}\n- separator\n"), vec![bare; 4]);
+        assert_eq!(
+            items("- hr:\n    style: thin\n    aria_hidden: false\n"),
+            [BlockItem::Separator(Separator {
+                style: Some("thin".to_string()),
+                aria_hidden: Some(false),
+            })]
+        );
+    }
+
+    #[test]
+    fn unit_and_struct_items() {
+        assert_eq!(
+            items("- br\n- $loop_value\n- text: hi\n"),
+            [
+                BlockItem::Br,
+                BlockItem::LoopValue,
+                BlockItem::Text(TextContent::Plain("hi".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_items() {
+        let error = serde_yaml::from_str::<Vec<BlockItem>>("- txt: hi\n").unwrap_err();
+        assert!(
+            error.to_string().contains("unknown variant `txt`"),
+            "{}",
+            error
+        );
+
+        let error = serde_yaml::from_str::<Vec<BlockItem>>("- text: a\n  br:\n").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("expected map containing 1 entry"),
+            "{}",
+            error
+        );
+
+        assert!(serde_yaml::from_str::<Vec<BlockItem>>("- hr: 3\n").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+use color_eyre::{eyre::eyre, Result};
+
+use super::frontmatter::{self, Frontmatter};
+use super::html::{self, Heading};
+use super::shortcodes::{self, Shortcode};
+use super::typography::Typography;
+
+pub struct ParsedMarkdown {
+    pub frontmatter: Frontmatter,
+    /// Whether the file starts with a frontmatter header.
+    pub has_frontmatter: bool,
+    /// Has a placeholder for each shortcode, which the block builder expands.
+    pub html: String,
+    pub headings: Vec<Heading>,
+    pub shortcodes: Vec<Shortcode>,
+}
+
+struct CachedMarkdown {
+    modified: Option<SystemTime>,
+    len: u64,
+    parsed: Rc<ParsedMarkdown>,
+}
+
+/// Reads the files the cache parses, which lets tests count how often each one is read.
+pub(crate) trait FileReader {
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+}
+
+struct FsReader;
+
+impl FileReader for FsReader {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        Ok(content)
+    }
+}
+
+/// Per-run cache for markdown files and glob results that are needed by several pages.
+pub struct Cache {
+    markdown: HashMap<PathBuf, CachedMarkdown>,
+    globs: HashMap<(String, bool, bool, bool), Rc<Vec<PathBuf>>>,
+    /// Used when converting markdown.
+    typography: Typography,
+    reader: Box<dyn FileReader>,
+}
+
+impl Cache {
+    pub fn new(typography: Typography) -> Self {
+        Self::with_reader(typography, Box::new(FsReader))
+    }
+
+    pub(crate) fn with_reader(typography: Typography, reader: Box<dyn FileReader>) -> Self {
+        Self {
+            markdown: HashMap::new(),
+            globs: HashMap::new(),
+            typography,
+            reader,
+        }
+    }
+
+    /// Reads, splits and converts a markdown file, reusing the previous result unless the
+    /// file changed since.
+    pub fn markdown(&mut self, path: &Path) -> Result<Rc<ParsedMarkdown>> {
+        let path = path
+            .canonicalize()
+            .map_err(|e| eyre!("{}: {}", path.to_string_lossy(), e))?;
+        let metadata = std::fs::metadata(&path)?;
+        let modified = metadata.modified().ok();
+
+        if let Some(cached) = self.markdown.get(&path) {
+            if cached.modified == modified && cached.len == metadata.len() {
+                return Ok(cached.parsed.clone());
+            }
+        }
+
+        let content = self.reader.read_to_string(&path)?;
+
+        let (frontmatter, markdown) =
+            frontmatter::split(&content).map_err(|e| eyre!("{}: {}", path.to_string_lossy(), e))?;
+
+        let has_frontmatter = markdown.len() != content.len();
+        let markdown = frontmatter::substitute(markdown, Some(&frontmatter))?;
+
+        let (markdown, shortcodes) = shortcodes::extract(&markdown)?;
+        let (html, headings) = html::markdown_with_headings(&markdown, self.typography);
+
+        let parsed = Rc::new(ParsedMarkdown {
+            frontmatter,
+            has_frontmatter,
+            html,
+            headings,
+            shortcodes,
+        });
+
+        self.markdown.insert(
+            path,
+            CachedMarkdown {
+                modified,
+                len: metadata.len(),
+                parsed: parsed.clone(),
+            },
+        );
+
+        Ok(parsed)
+    }
+
+    pub fn glob(&mut self, pattern: &str, options: glob::MatchOptions) -> Result<Rc<Vec<PathBuf>>> {
+        let key = (
+            pattern.to_string(),
+            options.case_sensitive,
+            options.require_literal_separator,
+            options.require_literal_leading_dot,
+        );
+
+        if let Some(paths) = self.globs.get(&key) {
+            return Ok(paths.clone());
+        }
+
+        let paths = glob::glob_with(pattern, options)?.collect::<Result<Vec<_>, _>>()?;
+        let paths = Rc::new(paths);
+
+        self.globs.insert(key, paths.clone());
+
+        Ok(paths)
+    }
+}
@@ -0,0 +1,132 @@
+use pulldown_cmark::BlockQuoteKind;
+use serde::{Deserialize, Serialize};
+
+use super::html;
+use super::styles::StyleRule;
+
+pub fn title_paragraph(title: &str) -> String {
+    format!("<p class=\"callout-title\">{}</p>", html::escape(title))
+}
+
+pub const CALLOUT_KINDS: [CalloutKind; 4] = [
+    CalloutKind::Note,
+    CalloutKind::Tip,
+    CalloutKind::Warning,
+    CalloutKind::Danger,
+];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum CalloutKind {
+    #[default]
+    #[serde(rename = "note")]
+    Note,
+    #[serde(rename = "tip")]
+    Tip,
+    #[serde(rename = "warning")]
+    Warning,
+    #[serde(rename = "danger")]
+    Danger,
+}
+
+impl CalloutKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            CalloutKind::Note => "note",
+            CalloutKind::Tip => "tip",
+            CalloutKind::Warning => "warning",
+            CalloutKind::Danger => "danger",
+        }
+    }
+
+    /// Shown when a callout isn't given a title.
+    pub fn title(self) -> &'static str {
+        match self {
+            CalloutKind::Note => "Note",
+            CalloutKind::Tip => "Tip",
+            CalloutKind::Warning => "Warning",
+            CalloutKind::Danger => "Danger",
+        }
+    }
+
+    /// Used when the theme has no color named after the kind.
+    fn default_color(self) -> (u8, u8, u8) {
+        match self {
+            CalloutKind::Note => (0x09, 0x69, 0xda),
+            CalloutKind::Tip => (0x1a, 0x7f, 0x37),
+            CalloutKind::Warning => (0x9a, 0x67, 0x00),
+            CalloutKind::Danger => (0xd1, 0x24, 0x2f),
+        }
+    }
+
+    /// The kind and title of a `> [!NOTE]` alert in markdown. `IMPORTANT` is a note and
+    /// `CAUTION` a danger, keeping their own titles.
+    pub fn from_alert(kind: BlockQuoteKind) -> (Self, &'static str) {
+        match kind {
+            BlockQuoteKind::Note => (CalloutKind::Note, "Note"),
+            BlockQuoteKind::Important => (CalloutKind::Note, "Important"),
+            BlockQuoteKind::Tip => (CalloutKind::Tip, "Tip"),
+            BlockQuoteKind::Warning => (CalloutKind::Warning, "Warning"),
+            BlockQuoteKind::Caution => (CalloutKind::Danger, "Caution"),
+        }
+    }
+
+    /// Class of a callout of the kind, which `rules` style.
+    pub fn class(self) -> String {
+        format!("callout-{}", self.name())
+    }
+
+    pub fn opening_tag(self, style: Option<&str>) -> String {
+        let mut class = format!("callout {}", self.class());
+
+        if let Some(style) = style {
+            class.push(' ');
+            class.push_str(style);
+        }
+
+        format!("<aside class=\"{}\" role=\"note\">", html::escape(&class))
+    }
+
+    /// The opening tag and title of a callout, up to its contents.
+    pub fn open(self, title: &str) -> String {
+        format!("{}\n{}\n", self.opening_tag(None), title_paragraph(title))
+    }
+
+    /// The generated rules for callouts of the kind. Its color is the theme color named after
+    /// it, like `--color-warning`, if there is one.
+    pub fn rules(self) -> Vec<(String, StyleRule)> {
+        let (r, g, b) = self.default_color();
+        let color = format!("var(--color-{}, #{:02x}{:02x}{:02x})", self.name(), r, g, b);
+
+        vec![
+            (
+                ".callout".to_string(),
+                StyleRule::from([
+                    ("border-left".to_string(), "4px solid".to_string()),
+                    ("margin".to_string(), "1em 0".to_string()),
+                    ("padding".to_string(), "0.5em 1em".to_string()),
+                ]),
+            ),
+            (
+                ".callout-title".to_string(),
+                StyleRule::from([
+                    ("font-weight".to_string(), "bold".to_string()),
+                    ("margin".to_string(), "0".to_string()),
+                ]),
+            ),
+            (
+                format!(".{}", self.class()),
+                StyleRule::from([
+                    (
+                        "background-color".to_string(),
+                        format!("rgba({}, {}, {}, 0.1)", r, g, b),
+                    ),
+                    ("border-color".to_string(), color.clone()),
+                ]),
+            ),
+            (
+                format!(".{} .callout-title", self.class()),
+                StyleRule::from([("color".to_string(), color)]),
+            ),
+        ]
+    }
+}
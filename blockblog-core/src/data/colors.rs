@@ -0,0 +1,664 @@
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+use serde::{de::Visitor, Deserialize, Deserializer, Serialize};
+use serde_yaml::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError {
+    pub input: String,
+    pub reason: String,
+}
+
+impl ParseColorError {
+    fn new(input: &str, reason: impl Into<String>) -> Self {
+        ParseColorError {
+            input: input.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for ParseColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "failed to parse color {:?}: {}; expected a hex color like #ff00ff, #f0f or #ff00ff80, rgb()/rgba(), hsl()/hsla() or a CSS color name",
+            self.input, self.reason
+        )
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+impl Serialize for Color {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // code from https://github.com/alacritty/alacritty/blob/master/alacritty_terminal/src/term/color.rs
+        struct ColorVisitor;
+
+        // Used for deserializing reftests.
+        #[derive(Deserialize)]
+        struct ColorDerivedDeser {
+            r: u8,
+            g: u8,
+            b: u8,
+            a: Option<u8>,
+        }
+
+        impl<'a> Visitor<'a> for ColorVisitor {
+            type Value = Color;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                f.write_str(
+                    "hex color like #ff00ff, rgb()/rgba(), hsl()/hsla() or a CSS color name",
+                )
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Color, E>
+            where
+                E: serde::de::Error,
+            {
+                Color::from_str(value).map_err(E::custom)
+            }
+        }
+
+        // Return an error if the syntax is incorrect.
+        let value = Value::deserialize(deserializer)?;
+
+        // Attempt to deserialize from struct form.
+        if let Ok(ColorDerivedDeser { r, g, b, a }) = ColorDerivedDeser::deserialize(value.clone())
+        {
+            return Ok(Color {
+                r,
+                g,
+                b,
+                a: a.unwrap_or(255),
+            });
+        }
+
+        // Deserialize from a string (hex notation, rgb()/rgba(), hsl()/hsla() or a color name).
+        match value.as_str() {
+            Some(_) => value.clone().deserialize_str(ColorVisitor).map_err(serde::de::Error::custom),
+            None => Err(serde::de::Error::custom(format!(
+                "failed to parse color {:?}; expected a string like #ff00ff or a map with r, g, b and optionally a",
+                value
+            ))),
+        }
+    }
+
+    fn deserialize_in_place<D>(deserializer: D, place: &mut Self) -> Result<(), D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // Default implementation just delegates to `deserialize` impl.
+        *place = Self::deserialize(deserializer)?;
+        Ok(())
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Color, ParseColorError> {
+        let trimmed = s.trim();
+
+        if let Some(hex) = trimmed.strip_prefix('#') {
+            return Color::from_hex(s, hex);
+        }
+
+        if let Some(hex) = trimmed.strip_prefix("0x") {
+            return match hex.len() {
+                6 | 8 => Color::from_hex(s, hex),
+                _ => Err(ParseColorError::new(
+                    s,
+                    "0x colors must have 6 or 8 hex digits",
+                )),
+            };
+        }
+
+        let lowercase = trimmed.to_lowercase();
+
+        if let Some(args) = lowercase
+            .strip_prefix("rgba(")
+            .or_else(|| lowercase.strip_prefix("rgb("))
+        {
+            return Color::from_rgb_function(s, args);
+        }
+
+        if let Some(args) = lowercase
+            .strip_prefix("hsla(")
+            .or_else(|| lowercase.strip_prefix("hsl("))
+        {
+            return Color::from_hsl_function(s, args);
+        }
+
+        match named_color(&lowercase) {
+            Some(color) => Ok(color),
+            None => Err(ParseColorError::new(s, "unknown color format or name")),
+        }
+    }
+}
+
+impl Color {
+    fn from_hex(input: &str, hex: &str) -> Result<Color, ParseColorError> {
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseColorError::new(input, "contains a non-hex digit"));
+        }
+
+        let digits = hex.as_bytes();
+        let nibble = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).unwrap();
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+
+        match digits.len() {
+            3 | 4 => Ok(Color {
+                r: nibble(0) * 17,
+                g: nibble(1) * 17,
+                b: nibble(2) * 17,
+                a: if digits.len() == 4 {
+                    nibble(3) * 17
+                } else {
+                    255
+                },
+            }),
+            6 | 8 => Ok(Color {
+                r: byte(0),
+                g: byte(2),
+                b: byte(4),
+                a: if digits.len() == 8 { byte(6) } else { 255 },
+            }),
+            n => Err(ParseColorError::new(
+                input,
+                format!("hex colors must have 3, 4, 6 or 8 digits, found {}", n),
+            )),
+        }
+    }
+
+    /// The comma separated arguments of a color function, without the closing parenthesis.
+    fn function_args<'a>(input: &str, args: &'a str) -> Result<Vec<&'a str>, ParseColorError> {
+        let args = args
+            .strip_suffix(')')
+            .ok_or_else(|| ParseColorError::new(input, "missing closing parenthesis"))?;
+
+        let args: Vec<&str> = args.split(',').map(str::trim).collect();
+
+        if args.len() != 3 && args.len() != 4 {
+            return Err(ParseColorError::new(
+                input,
+                format!(
+                    "expected 3 or 4 comma separated values, found {}",
+                    args.len()
+                ),
+            ));
+        }
+
+        Ok(args)
+    }
+
+    /// An alpha from 0 to 1 or a percentage, or opaque if there is none.
+    fn function_alpha(input: &str, value: Option<&&str>) -> Result<u8, ParseColorError> {
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(255),
+        };
+
+        let (number, scale) = match value.strip_suffix('%') {
+            Some(percentage) => (percentage, 100.0),
+            None => (*value, 1.0),
+        };
+
+        let alpha = number.parse::<f64>().ok().map(|alpha| alpha / scale);
+
+        match alpha {
+            Some(alpha) if (0.0..=1.0).contains(&alpha) => Ok((alpha * 255.0).round() as u8),
+            _ => Err(ParseColorError::new(
+                input,
+                format!(
+                    "alpha value {:?} is not a number from 0 to 1 or a percentage",
+                    value
+                ),
+            )),
+        }
+    }
+
+    fn from_rgb_function(input: &str, args: &str) -> Result<Color, ParseColorError> {
+        let args = Color::function_args(input, args)?;
+
+        let channel = |value: &str| {
+            value.parse::<u8>().map_err(|_| {
+                ParseColorError::new(
+                    input,
+                    format!("channel value {:?} is not an integer from 0 to 255", value),
+                )
+            })
+        };
+
+        Ok(Color {
+            r: channel(args[0])?,
+            g: channel(args[1])?,
+            b: channel(args[2])?,
+            a: Color::function_alpha(input, args.get(3))?,
+        })
+    }
+
+    fn from_hsl_function(input: &str, args: &str) -> Result<Color, ParseColorError> {
+        let args = Color::function_args(input, args)?;
+
+        let hue = args[0]
+            .strip_suffix("deg")
+            .unwrap_or(args[0])
+            .parse::<f64>()
+            .map_err(|_| {
+                ParseColorError::new(
+                    input,
+                    format!("hue {:?} is not a number of degrees", args[0]),
+                )
+            })?;
+
+        let percentage = |value: &str| match value
+            .strip_suffix('%')
+            .and_then(|number| number.parse::<f64>().ok())
+        {
+            Some(number) if (0.0..=100.0).contains(&number) => Ok(number / 100.0),
+            _ => Err(ParseColorError::new(
+                input,
+                format!("{:?} is not a percentage from 0% to 100%", value),
+            )),
+        };
+
+        let saturation = percentage(args[1])?;
+        let lightness = percentage(args[2])?;
+
+        // https://www.w3.org/TR/css-color-3/#hsl-color
+        let hue = hue.rem_euclid(360.0) / 30.0;
+        let amount = saturation * lightness.min(1.0 - lightness);
+        let channel = |n: f64| {
+            let k = (n + hue) % 12.0;
+            let value = lightness - amount * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0);
+
+            (value * 255.0).round() as u8
+        };
+
+        Ok(Color {
+            r: channel(0.0),
+            g: channel(8.0),
+            b: channel(4.0),
+            a: Color::function_alpha(input, args.get(3))?,
+        })
+    }
+
+    /// Relative luminance as defined by WCAG 2.
+    pub fn relative_luminance(&self) -> f64 {
+        let channel = |value: u8| {
+            let value = value as f64 / 255.0;
+            if value <= 0.03928 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        0.2126 * channel(self.r) + 0.7152 * channel(self.g) + 0.0722 * channel(self.b)
+    }
+
+    /// WCAG 2 contrast ratio between two colors, from 1.0 to 21.0.
+    pub fn contrast_ratio(&self, other: &Color) -> f64 {
+        let a = self.relative_luminance();
+        let b = other.relative_luminance();
+
+        if a > b {
+            (a + 0.05) / (b + 0.05)
+        } else {
+            (b + 0.05) / (a + 0.05)
+        }
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.a == 255
+    }
+
+    /// The color as a CSS value, using `rgba()` when it is not fully opaque.
+    pub fn to_css(&self) -> String {
+        if self.is_opaque() {
+            self.to_string()
+        } else {
+            let alpha = format!("{:.3}", self.a as f64 / 255.0);
+            let alpha = alpha.trim_end_matches('0').trim_end_matches('.');
+
+            format!("rgba({}, {}, {}, {})", self.r, self.g, self.b, alpha)
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // use the short #rgb(a) form whenever it is lossless
+        let channels = [self.r, self.g, self.b, self.a];
+        let channels = if self.is_opaque() {
+            &channels[..3]
+        } else {
+            &channels[..]
+        };
+
+        write!(f, "#")?;
+
+        if channels.iter().all(|c| c >> 4 == c & 0xf) {
+            for c in channels {
+                write!(f, "{:x}", c & 0xf)?;
+            }
+        } else {
+            for c in channels {
+                write!(f, "{:02x}", c)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 255,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct LinkColor {
+    pub normal: Color,
+    pub hover: Option<Color>,
+}
+
+impl Display for LinkColor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(ref hover) = self.hover {
+            write!(f, "{{ normal: {}, hover: {} }}", self.normal, hover)
+        } else {
+            write!(f, "{}", self.normal)
+        }
+    }
+}
+
+fn named_color(name: &str) -> Option<Color> {
+    let hex: u32 = match name {
+        "aliceblue" => 0xf0f8ff,
+        "antiquewhite" => 0xfaebd7,
+        "aqua" => 0x00ffff,
+        "aquamarine" => 0x7fffd4,
+        "azure" => 0xf0ffff,
+        "beige" => 0xf5f5dc,
+        "bisque" => 0xffe4c4,
+        "black" => 0x000000,
+        "blanchedalmond" => 0xffebcd,
+        "blue" => 0x0000ff,
+        "blueviolet" => 0x8a2be2,
+        "brown" => 0xa52a2a,
+        "burlywood" => 0xdeb887,
+        "cadetblue" => 0x5f9ea0,
+        "chartreuse" => 0x7fff00,
+        "chocolate" => 0xd2691e,
+        "coral" => 0xff7f50,
+        "cornflowerblue" => 0x6495ed,
+        "cornsilk" => 0xfff8dc,
+        "crimson" => 0xdc143c,
+        "cyan" => 0x00ffff,
+        "darkblue" => 0x00008b,
+        "darkcyan" => 0x008b8b,
+        "darkgoldenrod" => 0xb8860b,
+        "darkgray" => 0xa9a9a9,
+        "darkgreen" => 0x006400,
+        "darkgrey" => 0xa9a9a9,
+        "darkkhaki" => 0xbdb76b,
+        "darkmagenta" => 0x8b008b,
+        "darkolivegreen" => 0x556b2f,
+        "darkorange" => 0xff8c00,
+        "darkorchid" => 0x9932cc,
+        "darkred" => 0x8b0000,
+        "darksalmon" => 0xe9967a,
+        "darkseagreen" => 0x8fbc8f,
+        "darkslateblue" => 0x483d8b,
+        "darkslategray" => 0x2f4f4f,
+        "darkslategrey" => 0x2f4f4f,
+        "darkturquoise" => 0x00ced1,
+        "darkviolet" => 0x9400d3,
+        "deeppink" => 0xff1493,
+        "deepskyblue" => 0x00bfff,
+        "dimgray" => 0x696969,
+        "dimgrey" => 0x696969,
+        "dodgerblue" => 0x1e90ff,
+        "firebrick" => 0xb22222,
+        "floralwhite" => 0xfffaf0,
+        "forestgreen" => 0x228b22,
+        "fuchsia" => 0xff00ff,
+        "gainsboro" => 0xdcdcdc,
+        "ghostwhite" => 0xf8f8ff,
+        "gold" => 0xffd700,
+        "goldenrod" => 0xdaa520,
+        "gray" => 0x808080,
+        "green" => 0x008000,
+        "greenyellow" => 0xadff2f,
+        "grey" => 0x808080,
+        "honeydew" => 0xf0fff0,
+        "hotpink" => 0xff69b4,
+        "indianred" => 0xcd5c5c,
+        "indigo" => 0x4b0082,
+        "ivory" => 0xfffff0,
+        "khaki" => 0xf0e68c,
+        "lavender" => 0xe6e6fa,
+        "lavenderblush" => 0xfff0f5,
+        "lawngreen" => 0x7cfc00,
+        "lemonchiffon" => 0xfffacd,
+        "lightblue" => 0xadd8e6,
+        "lightcoral" => 0xf08080,
+        "lightcyan" => 0xe0ffff,
+        "lightgoldenrodyellow" => 0xfafad2,
+        "lightgray" => 0xd3d3d3,
+        "lightgreen" => 0x90ee90,
+        "lightgrey" => 0xd3d3d3,
+        "lightpink" => 0xffb6c1,
+        "lightsalmon" => 0xffa07a,
+        "lightseagreen" => 0x20b2aa,
+        "lightskyblue" => 0x87cefa,
+        "lightslategray" => 0x778899,
+        "lightslategrey" => 0x778899,
+        "lightsteelblue" => 0xb0c4de,
+        "lightyellow" => 0xffffe0,
+        "lime" => 0x00ff00,
+        "limegreen" => 0x32cd32,
+        "linen" => 0xfaf0e6,
+        "magenta" => 0xff00ff,
+        "maroon" => 0x800000,
+        "mediumaquamarine" => 0x66cdaa,
+        "mediumblue" => 0x0000cd,
+        "mediumorchid" => 0xba55d3,
+        "mediumpurple" => 0x9370db,
+        "mediumseagreen" => 0x3cb371,
+        "mediumslateblue" => 0x7b68ee,
+        "mediumspringgreen" => 0x00fa9a,
+        "mediumturquoise" => 0x48d1cc,
+        "mediumvioletred" => 0xc71585,
+        "midnightblue" => 0x191970,
+        "mintcream" => 0xf5fffa,
+        "mistyrose" => 0xffe4e1,
+        "moccasin" => 0xffe4b5,
+        "navajowhite" => 0xffdead,
+        "navy" => 0x000080,
+        "oldlace" => 0xfdf5e6,
+        "olive" => 0x808000,
+        "olivedrab" => 0x6b8e23,
+        "orange" => 0xffa500,
+        "orangered" => 0xff4500,
+        "orchid" => 0xda70d6,
+        "palegoldenrod" => 0xeee8aa,
+        "palegreen" => 0x98fb98,
+        "paleturquoise" => 0xafeeee,
+        "palevioletred" => 0xdb7093,
+        "papayawhip" => 0xffefd5,
+        "peachpuff" => 0xffdab9,
+        "peru" => 0xcd853f,
+        "pink" => 0xffc0cb,
+        "plum" => 0xdda0dd,
+        "powderblue" => 0xb0e0e6,
+        "purple" => 0x800080,
+        "rebeccapurple" => 0x663399,
+        "red" => 0xff0000,
+        "rosybrown" => 0xbc8f8f,
+        "royalblue" => 0x4169e1,
+        "saddlebrown" => 0x8b4513,
+        "salmon" => 0xfa8072,
+        "sandybrown" => 0xf4a460,
+        "seagreen" => 0x2e8b57,
+        "seashell" => 0xfff5ee,
+        "sienna" => 0xa0522d,
+        "silver" => 0xc0c0c0,
+        "skyblue" => 0x87ceeb,
+        "slateblue" => 0x6a5acd,
+        "slategray" => 0x708090,
+        "slategrey" => 0x708090,
+        "snow" => 0xfffafa,
+        "springgreen" => 0x00ff7f,
+        "steelblue" => 0x4682b4,
+        "tan" => 0xd2b48c,
+        "teal" => 0x008080,
+        "thistle" => 0xd8bfd8,
+        "tomato" => 0xff6347,
+        "turquoise" => 0x40e0d0,
+        "violet" => 0xee82ee,
+        "wheat" => 0xf5deb3,
+        "white" => 0xffffff,
+        "whitesmoke" => 0xf5f5f5,
+        "yellow" => 0xffff00,
+        "yellowgreen" => 0x9acd32,
+        "transparent" => {
+            return Some(Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 0,
+            })
+        }
+        _ => return None,
+    };
+
+    Some(Color {
+        r: (hex >> 16) as u8,
+        g: (hex >> 8) as u8,
+        b: hex as u8,
+        a: 255,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    fn parse(s: &str) -> Color {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse("#ff00ff"), rgba(255, 0, 255, 255));
+        assert_eq!(parse("#f0f"), rgba(255, 0, 255, 255));
+        assert_eq!(parse("#ff00ff80"), rgba(255, 0, 255, 128));
+        assert_eq!(parse("#f0f8"), rgba(255, 0, 255, 136));
+        assert_eq!(parse("0x102030"), rgba(16, 32, 48, 255));
+        assert_eq!(parse("  #ABCDEF "), rgba(171, 205, 239, 255));
+    }
+
+    #[test]
+    fn function_colors() {
+        assert_eq!(parse("rgb(1, 2, 3)"), rgba(1, 2, 3, 255));
+        assert_eq!(parse("RGBA(1, 2, 3, 0.5)"), rgba(1, 2, 3, 128));
+        assert_eq!(parse("hsl(0, 100%, 50%)"), rgba(255, 0, 0, 255));
+        assert_eq!(parse("hsla(120, 100%, 25%, 1)"), rgba(0, 128, 0, 255));
+    }
+
+    #[test]
+    fn named_colors() {
+        assert_eq!(parse("rebeccapurple"), rgba(102, 51, 153, 255));
+        assert_eq!(parse("White"), rgba(255, 255, 255, 255));
+    }
+
+    #[test]
+    fn invalid_colors() {
+        for input in [
+            "",
+            "#ff",
+            "#ggg",
+            "#12345",
+            "0xfff",
+            "rgb(1, 2)",
+            "rgba(1, 2, 3, 2)",
+            "notacolor",
+        ] {
+            let error = input.parse::<Color>().unwrap_err();
+            assert_eq!(error.input, input);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for (input, displayed) in [
+            ("#ff00ff", "#f0f"),
+            ("#123456", "#123456"),
+            ("#ff00ff80", "#ff00ff80"),
+            ("#ffff", "#fff"),
+            ("rgba(17, 34, 51, 0.6)", "#1239"),
+        ] {
+            let color = parse(input);
+
+            assert_eq!(color.to_string(), displayed);
+            assert_eq!(parse(displayed), color);
+        }
+    }
+
+    #[test]
+    fn css_values() {
+        assert_eq!(parse("#123456").to_css(), "#123456");
+        assert_eq!(parse("#12345680").to_css(), "rgba(18, 52, 86, 0.502)");
+    }
+
+    #[test]
+    fn deserialize_forms() {
+        let color: Color = serde_yaml::from_str("'#102030'").unwrap();
+        assert_eq!(color, rgba(16, 32, 48, 255));
+
+        let color: Color = serde_yaml::from_str("{ r: 1, g: 2, b: 3 }").unwrap();
+        assert_eq!(color, rgba(1, 2, 3, 255));
+
+        let color: Color = serde_yaml::from_str(&serde_yaml::to_string(&color).unwrap()).unwrap();
+        assert_eq!(color, rgba(1, 2, 3, 255));
+
+        assert!(serde_yaml::from_str::<Color>("'#nope'").is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Error},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+
+use super::config::SiteConfig;
+
+/// Directory, relative to the input directory, holding a directory of blocks for each
+/// component set, named after its namespace.
+pub const COMPONENTS_DIR: &str = "components";
+
+/// Directories of the component sets of a site by namespace, relative to the input
+/// directory: each directory in `components`, and each one in the site config. The blocks
+/// of a set are included by their name under the namespace, like `cards/card`.
+pub fn component_dirs(input: &Path, site_config: &SiteConfig) -> Result<BTreeMap<String, PathBuf>> {
+    let mut dirs = BTreeMap::new();
+    let components = input.join(COMPONENTS_DIR);
+
+    if components.is_dir() {
+        for entry in std::fs::read_dir(&components)? {
+            let path = entry?.path();
+            let relative = path.strip_prefix(input)?;
+
+            if path.is_dir() && !site_config.is_ignored(relative) {
+                let namespace = path.file_name().unwrap().to_string_lossy().to_string();
+                dirs.insert(namespace, relative.to_path_buf());
+            }
+        }
+    }
+
+    for (namespace, dir) in site_config.components.iter() {
+        if dirs.contains_key(namespace) {
+            return Err(Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "Component set {} is both in {} and in the site config",
+                    namespace, COMPONENTS_DIR
+                ),
+            )
+            .into());
+        }
+
+        if !input.join(dir).is_dir() {
+            return Err(Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Directory {} of component set {} does not exist",
+                    dir.to_string_lossy(),
+                    namespace
+                ),
+            )
+            .into());
+        }
+
+        dirs.insert(namespace.clone(), dir.clone());
+    }
+
+    Ok(dirs)
+}
+
+/// Where `add-component` gets a component set from: `gh:user/repo` is a GitHub repository,
+/// anything that looks like a URL is another git repository, and anything else is a
+/// directory. A source starting with `-` is refused, so it can't be taken for an option of
+/// `git`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComponentSource {
+    Git(String),
+    Dir(PathBuf),
+}
+
+impl ComponentSource {
+    pub fn parse(source: &str) -> Result<Self> {
+        if source.starts_with('-') {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid component source {}", source),
+            )
+            .into());
+        }
+
+        Ok(if let Some(repository) = source.strip_prefix("gh:") {
+            ComponentSource::Git(format!("https://github.com/{}.git", repository))
+        } else if source.contains("://") || source.starts_with("git@") {
+            ComponentSource::Git(source.to_string())
+        } else {
+            ComponentSource::Dir(PathBuf::from(source))
+        })
+    }
+
+    /// Namespace of the set when it isn't given one: the last part of the source, without
+    /// `.git` or a `blockblog-` prefix, so `gh:user/blockblog-cards` is `cards`.
+    pub fn default_namespace(&self) -> Option<String> {
+        let source = match self {
+            ComponentSource::Git(url) => url.as_str(),
+            ComponentSource::Dir(dir) => dir.to_str()?,
+        };
+
+        let name = source.trim_end_matches('/').rsplit(['/', ':']).next()?;
+        let name = name.strip_suffix(".git").unwrap_or(name);
+        let name = name.strip_prefix("blockblog-").unwrap_or(name);
+
+        (!name.is_empty() && name != "." && name != "..").then(|| name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sources() {
+        assert_eq!(
+            ComponentSource::parse("gh:user/blockblog-cards").unwrap(),
+            ComponentSource::Git("https://github.com/user/blockblog-cards.git".to_string())
+        );
+        assert_eq!(
+            ComponentSource::parse("../cards").unwrap(),
+            ComponentSource::Dir(PathBuf::from("../cards"))
+        );
+        assert!(ComponentSource::parse("--upload-pack=touch x").is_err());
+    }
+}
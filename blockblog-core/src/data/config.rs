@@ -0,0 +1,417 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Error, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use super::blocks::{Head, LinkStyle};
+use super::diagrams::DiagramConfig;
+use super::error;
+use super::feed::FeedConfig;
+use super::frontmatter;
+use super::i18n::I18nConfig;
+use super::links::ExternalLinks;
+use super::math::MathConfig;
+use super::nav::MenuEntry;
+use super::plugins::Plugin;
+use super::styles::DarkMode;
+use super::taxonomy::{RelatedConfig, TagsConfig};
+use super::theme::Theme;
+use super::transforms::Transform;
+use super::typography::Typography;
+
+pub const SITE_CONFIG_FILE: &str = "blockblog.yml";
+
+/// Names of files and directories ignored wherever they are, besides dotfiles like `.git`.
+const IGNORED_NAMES: &[&str] = &["node_modules"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SiteConfig {
+    /// Output path template for standalone markdown files, relative to the output directory.
+    /// Supports the `{dir}`, `{stem}` and `{date}` placeholders, and `{lang}` for the
+    /// frontmatter `lang` of files not in the default language.
+    pub md_output: Option<String>,
+    /// Emit copied assets under content-hashed names and rewrite references to them.
+    pub fingerprint_assets: bool,
+    /// External commands asset files are piped through instead of being copied verbatim.
+    pub transforms: Vec<Transform>,
+    /// Public URL the site is deployed at, available as `$site.base_url`.
+    pub base_url: Option<String>,
+    /// Site title, available as `$site.title` and used for pages without a title.
+    pub title: Option<String>,
+    /// Block included at the start of the body of every page, unless its `html` item says
+    /// otherwise.
+    pub header: Option<String>,
+    /// Block included at the end of the body of every page, after its footnotes.
+    pub footer: Option<String>,
+    /// Head items every page starts from. Page values override these, while styles and
+    /// scripts are added after the site-wide ones.
+    pub head: Option<Head>,
+    /// String used for one level of indentation in the generated HTML.
+    pub indent: Option<String>,
+    /// Output directory, relative to the input directory. The command line flag takes
+    /// precedence.
+    pub output: Option<PathBuf>,
+    /// Glob patterns, relative to the input directory, of files that are not read at all.
+    /// Dotfiles and `node_modules` are always ignored.
+    pub ignore: Vec<String>,
+    /// Glob patterns of files and directories to read even though they are ignored by
+    /// default, like `.well-known`.
+    pub keep: Vec<String>,
+    /// Values available as `$site.name` in every block.
+    pub variables: BTreeMap<String, serde_yaml::Value>,
+    /// RSS or Atom feeds generated from standalone markdown files.
+    pub feeds: Vec<FeedConfig>,
+    /// Write a sitemap.xml listing every generated page. Defaults to doing so when
+    /// `base_url` is set, which it needs.
+    pub sitemap: Option<bool>,
+    /// Write a robots.txt that allows everything and points to the sitemap.
+    pub robots: bool,
+    /// Write a search_index.json with the URL, title and text of every page, which `search`
+    /// items search in.
+    pub search_index: bool,
+    /// Pages listing the markdown files with each frontmatter tag.
+    pub tags: Option<TagsConfig>,
+    /// Posts related by shared tags, listed by looping over `$page.related`.
+    pub related: Option<RelatedConfig>,
+    /// Entries of the navigation bar rendered by `nav` items.
+    pub menu: Vec<MenuEntry>,
+    /// Titles `breadcrumbs` items show for directories of the output, like `posts: Blog`,
+    /// instead of the directory names.
+    pub breadcrumbs: BTreeMap<String, String>,
+    /// Directory, relative to the input directory, `blockblog new post` writes posts to.
+    /// Defaults to `posts`.
+    pub posts_dir: Option<PathBuf>,
+    /// Write the generated styles of each page to a file next to it, linked from its head,
+    /// instead of merging them all into generated_style.css.
+    pub page_styles: bool,
+    /// Colors, fonts and spacing usable as `$theme.name` in blocks and exported as CSS custom
+    /// properties.
+    pub theme: Theme,
+    /// Whether the dark variants of the theme and link styles follow the system preference
+    /// (`media`, the default) or a `dark` class on the root element (`class`).
+    pub dark_mode: DarkMode,
+    /// Link styles used by `preset` name from `link` items.
+    pub link_styles: BTreeMap<String, LinkStyle>,
+    /// Write each page as `<name>/index.html` instead of `<name>.html`, and leave the
+    /// `index.html` out of the links the builder generates.
+    pub pretty_urls: bool,
+    /// Overlays selected by name with `--profile`, like `dev` and `prod`.
+    pub profiles: BTreeMap<String, Profile>,
+    /// Languages of the site, with their strings in `i18n/<language>.yml`.
+    pub i18n: I18nConfig,
+    /// HTML added to every page as it is, like an analytics script or a verification meta
+    /// tag.
+    pub snippets: Vec<Snippet>,
+    /// Component sets outside the site by namespace, as directories relative to the input
+    /// directory. Sets in `components/<namespace>` don't need to be listed.
+    pub components: BTreeMap<String, PathBuf>,
+    /// External commands rendering `plugin` items and hooked into builds.
+    pub plugins: Vec<Plugin>,
+    pub math: MathConfig,
+    pub diagrams: DiagramConfig,
+    pub typography: Typography,
+    /// Adds `rel="noopener noreferrer"` to links to other sites, and optionally a target and
+    /// a class.
+    pub external_links: Option<ExternalLinks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Snippet {
+    pub html: String,
+    #[serde(default)]
+    pub position: SnippetPosition,
+    /// Profiles to add the snippet in. It is added in every build if none are given.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SnippetPosition {
+    /// At the end of the head.
+    #[default]
+    #[serde(rename = "head")]
+    Head,
+    /// At the end of the body.
+    #[serde(rename = "body")]
+    Body,
+}
+
+/// Build options and site config values used when building with a profile.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Profile {
+    /// Like `--drafts`.
+    pub drafts: bool,
+    /// Like `--future`.
+    pub future: bool,
+    /// Like `--debug`.
+    pub debug: bool,
+    /// Like `--minify`.
+    pub minify: bool,
+    /// Site config values replacing the ones outside the profile, like a different
+    /// `base_url`. Mappings, like `head` or `variables`, are merged key by key.
+    pub config: serde_yaml::Mapping,
+}
+
+impl SiteConfig {
+    pub fn load(input: &Path) -> Result<Self> {
+        Self::load_profile(input, None)
+    }
+
+    /// Loads the site config with the `config` of a profile merged into it. Fails if the
+    /// profile doesn't exist.
+    pub fn load_profile(input: &Path, profile: Option<&str>) -> Result<Self> {
+        let path = input.join(SITE_CONFIG_FILE);
+
+        if !path.is_file() {
+            return match profile {
+                Some(profile) => Err(unknown_profile(profile, &Self::default())),
+                None => Ok(Self::default()),
+            };
+        }
+
+        let mut file = File::open(&path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        // the theme is read first so the rest of the config, like the link styles, can use it
+        let theme = config_theme(&contents);
+        let config = error::parse_yaml_resolved::<Option<SiteConfig>>(&path, &contents, |value| {
+            let merged = profile.is_some_and(|profile| merge_profile(value, profile));
+            theme.resolve_references(value) | merged
+        })?
+        .unwrap_or_default();
+
+        if let Some(profile) = profile {
+            if !config.profiles.contains_key(profile) {
+                return Err(unknown_profile(profile, &config));
+            }
+        }
+
+        for pattern in config.ignore.iter().chain(config.keep.iter()) {
+            if let Err(why) = glob::Pattern::new(pattern) {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}: invalid pattern {}: {}",
+                        path.to_string_lossy(),
+                        pattern,
+                        why
+                    ),
+                )
+                .into());
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Whether a path relative to the input directory matches one of the ignore patterns, or
+    /// is or is inside a dotfile or `node_modules` that no `keep` pattern matches.
+    pub fn is_ignored(&self, relative: &Path) -> bool {
+        let ignored_by_default = relative.components().any(|component| match component {
+            Component::Normal(name) => {
+                let name = name.to_string_lossy();
+                name.starts_with('.') || IGNORED_NAMES.contains(&name.as_ref())
+            }
+            _ => false,
+        });
+
+        let kept = || {
+            relative
+                .ancestors()
+                .any(|path| matches_any(&self.keep, path))
+        };
+
+        (ignored_by_default && !kept()) || matches_any(&self.ignore, relative)
+    }
+
+    /// Ignores the output directory, if it is inside the input directory, so files generated
+    /// by a previous build aren't read back as input.
+    pub fn ignore_output(&mut self, input: &Path, output: &Path) {
+        let relative = match (resolve(input), resolve(output)) {
+            (Some(input), Some(output)) => match output.strip_prefix(&input) {
+                Ok(relative) if relative != Path::new("") => relative.to_path_buf(),
+                _ => return,
+            },
+            _ => return,
+        };
+
+        let pattern = relative
+            .components()
+            .map(|component| glob::Pattern::escape(&component.as_os_str().to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join("/");
+
+        self.ignore.push(format!("{}/**", pattern));
+        self.ignore.push(pattern);
+    }
+
+    /// Output path of a page without a permalink, relative to the output directory.
+    pub fn page_path(&self, block_name: &str) -> PathBuf {
+        let is_index = block_name == "index" || block_name.ends_with("/index");
+
+        if self.pretty_urls && !is_index {
+            Path::new(block_name).join("index.html")
+        } else {
+            PathBuf::from(format!("{}.html", block_name))
+        }
+    }
+
+    /// Leaves the `index.html` out of a URL to a page when using pretty URLs.
+    pub fn page_url(&self, url: String) -> String {
+        if !self.pretty_urls {
+            return url;
+        }
+
+        match url.strip_suffix("index.html") {
+            Some("") => "./".to_string(),
+            Some(dir) if dir.ends_with('/') => dir.to_string(),
+            _ => url,
+        }
+    }
+
+    /// `base_url` without a trailing `/`, to put in front of a path starting with one. Empty
+    /// if it isn't set, leaving the path root-relative.
+    pub fn base_url_prefix(&self) -> &str {
+        self.base_url
+            .as_deref()
+            .unwrap_or_default()
+            .trim_end_matches('/')
+    }
+
+    /// Path part of `base_url` without a trailing `/`, like `/project` for a site deployed at
+    /// `https://example.github.io/project/`. Empty when the site is at the domain root.
+    pub fn base_path(&self) -> String {
+        let base_url = match &self.base_url {
+            Some(base_url) => base_url.as_str(),
+            None => return String::new(),
+        };
+
+        let path = match base_url.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or("", |start| &rest[start..]),
+            None => base_url,
+        };
+
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+
+        match path.trim_end_matches('/') {
+            "" => String::new(),
+            path if path.starts_with('/') => path.to_string(),
+            path => format!("/{}", path),
+        }
+    }
+
+    /// Joins a path relative to the output directory onto `base_url`, if it is set.
+    pub fn absolute_url(&self, relative: &Path) -> Option<String> {
+        let base_url = self.base_url.as_ref()?;
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Some(format!("{}/{}", base_url.trim_end_matches('/'), relative))
+    }
+
+    /// Looks up a `$site.name` value.
+    pub fn variable(&self, name: &str) -> Option<String> {
+        match name {
+            "base_url" => self.base_url.clone(),
+            "title" => self.title.clone(),
+            _ => self.variables.get(name).map(frontmatter::value_to_string),
+        }
+    }
+}
+
+fn matches_any(patterns: &[String], path: &Path) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| glob::Pattern::new(pattern).is_ok_and(|pattern| pattern.matches_path(path)))
+}
+
+/// Absolute form of a path that may not exist yet, with symlinks in its existing part
+/// resolved.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    if let Ok(path) = path.canonicalize() {
+        return Some(path);
+    }
+
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if parent != Path::new("") => Some(resolve(parent)?.join(name)),
+        (_, Some(name)) => Some(Path::new(".").canonicalize().ok()?.join(name)),
+        _ => None,
+    }
+}
+
+/// Merges the `config` of a profile into the whole site config. Returns whether there was
+/// one to merge.
+fn merge_profile(config: &mut serde_yaml::Value, profile: &str) -> bool {
+    let overlay = config
+        .get("profiles")
+        .and_then(|profiles| profiles.get(profile))
+        .and_then(|profile| profile.get("config"))
+        .cloned();
+
+    match overlay {
+        Some(overlay) => {
+            merge_yaml(config, overlay);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Merges mappings key by key, replacing anything else with `overlay`.
+fn merge_yaml(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn unknown_profile(profile: &str, config: &SiteConfig) -> color_eyre::Report {
+    let known = config.profiles.keys().cloned().collect::<Vec<_>>();
+
+    Error::new(
+        io::ErrorKind::InvalidInput,
+        if known.is_empty() {
+            format!(
+                "Unknown profile {}: the site config has no profiles",
+                profile
+            )
+        } else {
+            format!(
+                "Unknown profile {}: the site config has {}",
+                profile,
+                known.join(", ")
+            )
+        },
+    )
+    .into()
+}
+
+/// The `theme` of a site config, or an empty one if it can't be read, which parsing the whole
+/// config then reports.
+fn config_theme(contents: &str) -> Theme {
+    serde_yaml::from_str::<serde_yaml::Value>(contents)
+        .ok()
+        .and_then(|config| config.get("theme").cloned())
+        .and_then(|theme| serde_yaml::from_value(theme).ok())
+        .unwrap_or_default()
+}
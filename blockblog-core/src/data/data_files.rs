@@ -0,0 +1,120 @@
+use std::{
+    collections::BTreeMap,
+    fs::read_dir,
+    io::{self, Error},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+
+use super::components::COMPONENTS_DIR;
+use super::config::SiteConfig;
+use super::i18n::I18N_DIR;
+
+/// Directory, relative to the input directory, holding the files available as `$data`.
+pub const DATA_DIR: &str = "data";
+
+/// Contents of the YAML, JSON and TOML files in the data directory. A file is addressed by
+/// its path without the extension, with `.` as the separator, so the `name` field of
+/// `data/team/members.yml` is `$data.team.members.name`.
+#[derive(Debug, Default)]
+pub struct DataFiles {
+    files: BTreeMap<String, (PathBuf, serde_yaml::Value)>,
+}
+
+/// Whether a path relative to the input directory is inside the data directory, or another
+/// one that is read on its own instead of as pages and assets, like the translations and
+/// components directories.
+pub fn is_data_file(relative: &Path) -> bool {
+    relative.starts_with(DATA_DIR)
+        || relative.starts_with(I18N_DIR)
+        || relative.starts_with(COMPONENTS_DIR)
+}
+
+impl DataFiles {
+    pub fn load(input: &Path, site_config: &SiteConfig) -> Result<Self> {
+        let mut data = Self::default();
+        let dir = input.join(DATA_DIR);
+
+        if dir.is_dir() {
+            data.load_dir(&dir, input, site_config)?;
+        }
+
+        Ok(data)
+    }
+
+    fn load_dir(&mut self, dir: &Path, input: &Path, site_config: &SiteConfig) -> Result<()> {
+        for entry in read_dir(dir)? {
+            let path = entry?.path();
+            let relative = path.strip_prefix(input)?;
+
+            if site_config.is_ignored(relative) {
+                continue;
+            }
+
+            if path.is_dir() {
+                self.load_dir(&path, input, site_config)?;
+                continue;
+            }
+
+            let contents = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("yml" | "yaml" | "json" | "toml") => std::fs::read_to_string(&path)?,
+                _ => continue,
+            };
+
+            let value = match path.extension().and_then(|ext| ext.to_str()) {
+                Some("json") => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+                Some("toml") => toml::from_str(&contents).map_err(|e| e.to_string()),
+                _ => serde_yaml::from_str(&contents).map_err(|e| e.to_string()),
+            }
+            .map_err(|why| {
+                Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.to_string_lossy(), why),
+                )
+            })?;
+
+            let key = relative
+                .strip_prefix(DATA_DIR)?
+                .with_extension("")
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(".");
+
+            self.files.insert(key, (relative.to_path_buf(), value));
+        }
+
+        Ok(())
+    }
+
+    /// Looks up a `$data` path like `authors.duckonaut.name`, returning the value and the
+    /// file it comes from. Sequence items are addressed by their index.
+    pub fn get(&self, path: &str) -> Option<(&Path, &serde_yaml::Value)> {
+        let (key, (file, value)) = self.files.iter().find(|(key, _)| {
+            path == key.as_str()
+                || path
+                    .strip_prefix(key.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })?;
+
+        Some((file, lookup(value, &path[key.len()..])?))
+    }
+}
+
+/// Follows a `.` separated path of map keys and sequence indices into `value`.
+pub fn lookup<'v>(value: &'v serde_yaml::Value, path: &str) -> Option<&'v serde_yaml::Value> {
+    let mut value = value;
+
+    for field in path.split('.').filter(|field| !field.is_empty()) {
+        value = match value {
+            serde_yaml::Value::Mapping(map) => {
+                map.get(&serde_yaml::Value::String(field.to_string()))?
+            }
+            serde_yaml::Value::Sequence(values) => values.get(field.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+
+    Some(value)
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use super::hash;
+use super::transforms::Transform;
+
+/// Directory, relative to the output directory, pre-rendered diagrams are written to.
+pub const DIAGRAMS_DIR: &str = "diagrams";
+
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum DiagramKind {
+    #[default]
+    #[serde(rename = "mermaid")]
+    Mermaid,
+    /// Graphviz.
+    #[serde(rename = "dot")]
+    Dot,
+}
+
+impl DiagramKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            DiagramKind::Mermaid => "mermaid",
+            DiagramKind::Dot => "dot",
+        }
+    }
+
+    fn default_command(self) -> (&'static str, &'static str) {
+        match self {
+            DiagramKind::Mermaid => ("mmdc", "mmdc -i {input} -o {output}"),
+            DiagramKind::Dot => ("dot", "dot -Tsvg -o {output} {input}"),
+        }
+    }
+}
+
+/// How `diagram` items are rendered. A diagram is pre-rendered to SVG with the command for
+/// its kind, if there is one. Otherwise Mermaid diagrams are rendered in the browser.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct DiagramConfig {
+    /// URL of the Mermaid module loaded on pages with diagrams that aren't pre-rendered.
+    pub mermaid: String,
+    /// Shell commands rendering a diagram of a kind to SVG, with `{input}` and `{output}`
+    /// placeholders. Without one, `mmdc` or `dot` is used if it's installed.
+    pub commands: BTreeMap<DiagramKind, String>,
+}
+
+impl Default for DiagramConfig {
+    fn default() -> Self {
+        Self {
+            mermaid: "https://cdn.jsdelivr.net/npm/mermaid@11/dist/mermaid.esm.min.mjs".to_string(),
+            commands: BTreeMap::new(),
+        }
+    }
+}
+
+impl DiagramConfig {
+    /// The command pre-rendering diagrams of `kind`, if there is one.
+    pub fn command(&self, kind: DiagramKind) -> Option<String> {
+        if let Some(command) = self.commands.get(&kind) {
+            return Some(command.clone());
+        }
+
+        let (program, command) = kind.default_command();
+
+        is_installed(program).then(|| command.to_string())
+    }
+}
+
+/// Output path of the SVG of a diagram, named after its contents.
+pub fn svg_path(kind: DiagramKind, source: &str) -> PathBuf {
+    let hash = hash::short_hash(format!("{}\n{}", kind.name(), source).as_bytes());
+
+    Path::new(DIAGRAMS_DIR).join(format!("{}.svg", hash))
+}
+
+/// Renders a diagram to SVG with `command`, run from the input root.
+pub fn render_svg(
+    command: &str,
+    input_root: &Path,
+    kind: DiagramKind,
+    source: &str,
+) -> Result<String> {
+    let dir = std::env::temp_dir().join(format!(
+        "blockblog-diagram-{}-{}",
+        std::process::id(),
+        hash::short_hash(source.as_bytes())
+    ));
+    std::fs::create_dir_all(&dir)?;
+
+    let input = dir.join(format!("diagram.{}", kind.name()));
+    let output = dir.join("diagram.svg");
+    std::fs::write(&input, source)?;
+
+    let transform = Transform {
+        pattern: String::new(),
+        command: command.to_string(),
+        output_ext: None,
+        timeout: None,
+    };
+
+    let svg = transform
+        .run(input_root, &input, &output)
+        .and_then(|_| Ok(std::fs::read_to_string(&output)?));
+
+    std::fs::remove_dir_all(&dir).ok();
+
+    svg
+}
+
+/// The script that renders Mermaid diagrams in the browser.
+pub fn mermaid_script(url: &str) -> String {
+    format!(
+        "<script type=\"module\">import mermaid from {}; mermaid.initialize({{ startOnLoad: true }});</script>",
+        serde_json::to_string(url).unwrap_or_default()
+    )
+}
+
+fn is_installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
@@ -0,0 +1,355 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use serde::de::DeserializeOwned;
+use serde_path_to_error::Segment;
+use yaml_rust::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+/// A YAML file that could not be deserialized, with where in it the problem is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub file: PathBuf,
+    /// Key path to the value that failed, like `html.body[2].link`. Empty at the top level.
+    pub yaml_path: String,
+    /// 1-based line and column.
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+    /// The lines around the location, numbered, with a caret under the column.
+    pub snippet: Option<String>,
+}
+
+/// Lines shown before and after the one with the error.
+const SNIPPET_CONTEXT: usize = 1;
+
+impl ParseError {
+    /// `contents` are what was deserialized, to find the value that failed in. Without them
+    /// the error has no location.
+    pub fn new(
+        file: &Path,
+        contents: Option<&str>,
+        error: &serde_path_to_error::Error<serde_yaml::Error>,
+    ) -> Self {
+        let path = error.path();
+        let yaml_path = match path.to_string() {
+            path if path == "." => String::new(),
+            path => path,
+        };
+        let error = error.inner();
+
+        let reported = error
+            .location()
+            .map(|location| (location.line(), location.column()));
+
+        // serde_yaml points at where it was reading, which is the start of a buffered value,
+        // like a flattened or untagged one, rather than what failed in it
+        let location = match contents {
+            Some(_) if yaml_path.is_empty() => reported,
+            Some(contents) => {
+                // the name of an unknown variant is its key
+                let at_key = error.to_string().contains("unknown variant");
+                locate(contents, path, at_key)
+            }
+            None => None,
+        };
+
+        let mut message = error.to_string();
+
+        // the location is shown separately
+        if let Some((line, column)) = reported {
+            let suffix = format!(" at line {} column {}", line, column);
+
+            if let Some(stripped) = message.strip_suffix(&suffix) {
+                message = stripped.to_string();
+            }
+        }
+
+        // serde_yaml leads with the path too when it knows part of it
+        if let Some((prefix, rest)) = message.split_once(": ") {
+            if !prefix.is_empty() && yaml_path.starts_with(prefix) {
+                message = rest.to_string();
+            }
+        }
+
+        Self {
+            file: file.to_path_buf(),
+            yaml_path,
+            location,
+            message,
+            snippet: location
+                .zip(contents)
+                .map(|((line, column), contents)| snippet(contents, line, column)),
+        }
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.location.map(|(line, _)| line)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+
+        if let Some((line, column)) = self.location {
+            write!(f, ":{}:{}", line, column)?;
+        }
+
+        write!(f, ": {}", self.message)?;
+
+        if !self.yaml_path.is_empty() {
+            write!(f, "\n  at {}", self.yaml_path)?;
+        }
+
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{}", snippet)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Deserializes YAML, pointing at the file, key path and lines of a value that doesn't fit.
+pub fn parse_yaml<T: DeserializeOwned>(file: &Path, contents: &str) -> Result<T, ParseError> {
+    let deserializer = serde_yaml::Deserializer::from_str(contents);
+
+    serde_path_to_error::deserialize(deserializer)
+        .map_err(|error| ParseError::new(file, Some(contents), &error))
+}
+
+/// A YAML value with the 1-based line and column it starts at.
+struct Node {
+    value: NodeValue,
+    start: (usize, usize),
+}
+
+enum NodeValue {
+    Scalar(String),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(Node, Node)>),
+}
+
+#[derive(Default)]
+struct Events(Vec<(Event, Marker)>);
+
+impl MarkedEventReceiver for Events {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        self.0.push((event, marker));
+    }
+}
+
+/// Where the value at `path` starts in `contents`, or its key if `at_key`. `None` if the path
+/// leads somewhere the contents don't have.
+fn locate(
+    contents: &str,
+    path: &serde_path_to_error::Path,
+    at_key: bool,
+) -> Option<(usize, usize)> {
+    let mut events = Events::default();
+    Parser::new(contents.chars())
+        .load(&mut events, false)
+        .ok()?;
+
+    let mut events = events
+        .0
+        .into_iter()
+        .skip_while(|(event, _)| *event != Event::DocumentStart)
+        .skip(1)
+        .collect::<Vec<_>>()
+        .into_iter();
+    let mut node = &parse_node(&mut events)?;
+    let mut location = node.start;
+
+    let segments = path.iter().count();
+
+    for (number, segment) in path.iter().enumerate() {
+        let is_last = number + 1 == segments;
+
+        match (segment, &node.value) {
+            (Segment::Seq { index }, NodeValue::Sequence(items)) => {
+                node = items.get(*index)?;
+                location = node.start;
+            }
+            (
+                Segment::Map { key: name } | Segment::Enum { variant: name },
+                NodeValue::Mapping(entries),
+            ) => {
+                let (key, value) = entries
+                    .iter()
+                    .find(|(key, _)| matches!(&key.value, NodeValue::Scalar(key) if key == name))?;
+
+                // a value spanning the lines under its key is best pointed at by the key
+                location = match value.start.0 == key.start.0 && !(at_key && is_last) {
+                    true => value.start,
+                    false => key.start,
+                };
+                node = value;
+            }
+            // a unit variant written as a plain string
+            (Segment::Enum { variant }, NodeValue::Scalar(value)) if value == variant => (),
+            _ => return None,
+        }
+    }
+
+    Some(location)
+}
+
+fn parse_node(events: &mut std::vec::IntoIter<(Event, Marker)>) -> Option<Node> {
+    let (event, marker) = events.next()?;
+    let start = (marker.line(), marker.col() + 1);
+
+    let is_end = |events: &std::vec::IntoIter<(Event, Marker)>| {
+        matches!(
+            events.as_slice().first(),
+            Some((Event::SequenceEnd | Event::MappingEnd, _))
+        )
+    };
+
+    let value = match event {
+        Event::Scalar(value, ..) => NodeValue::Scalar(value),
+        // an alias is located where it is used
+        Event::Alias(_) => NodeValue::Scalar(String::new()),
+        Event::SequenceStart(_) => {
+            let mut items = Vec::new();
+            while !is_end(events) {
+                items.push(parse_node(events)?);
+            }
+            events.next();
+
+            NodeValue::Sequence(items)
+        }
+        Event::MappingStart(_) => {
+            let mut entries = Vec::new();
+            while !is_end(events) {
+                entries.push((parse_node(events)?, parse_node(events)?));
+            }
+            events.next();
+
+            NodeValue::Mapping(entries)
+        }
+        _ => return None,
+    };
+
+    Some(Node { value, start })
+}
+
+fn snippet(contents: &str, line: usize, column: usize) -> String {
+    let lines = contents.lines().collect::<Vec<_>>();
+    let first = line.saturating_sub(SNIPPET_CONTEXT).max(1);
+    let last = (line + SNIPPET_CONTEXT).min(lines.len()).max(line);
+    let width = last.to_string().len();
+
+    let mut snippet = format!("{:width$} |", "", width = width);
+
+    for number in first..=last {
+        let text = lines.get(number - 1).copied().unwrap_or_default();
+        snippet += &format!("\n{:>width$} | {}", number, text, width = width);
+
+        if number == line {
+            snippet += &format!(
+                "\n{:width$} | {:column$}^",
+                "",
+                "",
+                width = width,
+                column = column.saturating_sub(1)
+            );
+        }
+    }
+
+    snippet
+}
+
+/// Like [`parse_yaml`], but lets `resolve` rewrite the parsed values before they are
+/// deserialized. Returns whether it changed anything. Errors in rewritten files can't point at
+/// a line, as the values may no longer be where the file has them.
+pub fn parse_yaml_resolved<T: DeserializeOwned>(
+    file: &Path,
+    contents: &str,
+    resolve: impl Fn(&mut serde_yaml::Value) -> bool,
+) -> Result<T, ParseError> {
+    let mut value = match serde_yaml::from_str::<serde_yaml::Value>(contents) {
+        Ok(value) => value,
+        Err(_) => return parse_yaml(file, contents),
+    };
+
+    if !resolve(&mut value) {
+        return parse_yaml(file, contents);
+    }
+
+    serde_path_to_error::deserialize(value).map_err(|error| ParseError::new(file, None, &error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::blocks::BlockItem;
+
+    fn error(contents: &str) -> ParseError {
+        parse_yaml::<BlockItem>(Path::new("c.yml"), contents).unwrap_err()
+    }
+
+    #[test]
+    fn sequence_item_location() {
+        let error = error(
+            "html:\n  head:\n    title: T\n  body:\n    - text: a\n    - text: b\n    - tytle: c\n",
+        );
+
+        assert_eq!(error.yaml_path, "html.body[2].tytle");
+        assert_eq!(error.location, Some((7, 7)));
+        assert!(
+            error
+                .to_string()
+                .contains("7 |     - tytle: c\n  |       ^"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn value_location() {
+        let error = error(
+            "html:\n  body:\n    - text: a\n    - link:\n        text: x\n        url: [1, 2]\n",
+        );
+
+        assert_eq!(error.yaml_path, "html.body[1].link.url");
+        assert_eq!(error.location, Some((6, 14)));
+    }
+
+    #[test]
+    fn flattened_field_location() {
+        let error = error(
+            "html:\n  body:\n    - text: a\n    - $for_each:\n        pattern: '*.md'\n        items:\n          - text: a\n        limit: many\n",
+        );
+
+        assert_eq!(error.yaml_path, "html.body[1].$for_each");
+        assert_eq!(error.location, Some((4, 7)));
+    }
+
+    #[test]
+    fn syntax_error_location() {
+        let error = error("html:\n  body: [\n");
+
+        assert!(error.yaml_path.is_empty());
+        assert_eq!(error.line(), Some(3));
+    }
+
+    #[test]
+    fn resolved_errors_have_no_location() {
+        let error = parse_yaml_resolved::<BlockItem>(
+            Path::new("c.yml"),
+            "html:\n  body:\n    - tytle: c\n",
+            |_| true,
+        )
+        .unwrap_err();
+
+        assert_eq!(error.yaml_path, "html.body[0].tytle");
+        assert_eq!(error.location, None);
+        assert_eq!(error.snippet, None);
+    }
+}
@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::html::escape;
+
+/// A feed of the standalone markdown files matching `pattern`, written to `output` in the
+/// output directory.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FeedConfig {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    #[serde(default = "default_output")]
+    pub output: PathBuf,
+    #[serde(default)]
+    pub format: FeedFormat,
+    /// Defaults to the site title.
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Maximum number of entries, newest first.
+    pub limit: Option<usize>,
+}
+
+fn default_output() -> PathBuf {
+    PathBuf::from("feed.xml")
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FeedFormat {
+    #[default]
+    #[serde(rename = "rss")]
+    Rss,
+    #[serde(rename = "atom")]
+    Atom,
+}
+
+impl FeedConfig {
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        glob::Pattern::new(&self.pattern).is_ok_and(|pattern| pattern.matches_path(relative_path))
+    }
+}
+
+pub struct FeedEntry {
+    pub title: String,
+    pub url: String,
+    pub date: DateTime<FixedOffset>,
+    pub summary: Option<String>,
+}
+
+/// Everything a feed needs besides its entries.
+pub struct FeedInfo<'a> {
+    pub title: &'a str,
+    pub description: &'a str,
+    pub site_url: &'a str,
+    pub feed_url: &'a str,
+}
+
+/// Reads an RFC 3339 date, a `YYYY-MM-DD HH:MM:SS` date and time, or a plain `YYYY-MM-DD`
+/// date. Dates without a time zone are taken as UTC.
+pub fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = date.trim();
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date);
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })?;
+
+    Some(naive.and_utc().fixed_offset())
+}
+
+pub fn render(format: FeedFormat, info: &FeedInfo, entries: &[FeedEntry]) -> String {
+    match format {
+        FeedFormat::Rss => render_rss(info, entries),
+        FeedFormat::Atom => render_atom(info, entries),
+    }
+}
+
+fn render_rss(info: &FeedInfo, entries: &[FeedEntry]) -> String {
+    let mut output = String::new();
+
+    output.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    output.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n");
+    output.push_str("  <channel>\n");
+    output.push_str(&format!("    <title>{}</title>\n", escape(info.title)));
+    output.push_str(&format!("    <link>{}</link>\n", escape(info.site_url)));
+    output.push_str(&format!(
+        "    <description>{}</description>\n",
+        escape(info.description)
+    ));
+    output.push_str(&format!(
+        "    <atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        escape(info.feed_url)
+    ));
+
+    for entry in entries {
+        output.push_str("    <item>\n");
+        output.push_str(&format!("      <title>{}</title>\n", escape(&entry.title)));
+        output.push_str(&format!("      <link>{}</link>\n", escape(&entry.url)));
+        output.push_str(&format!("      <guid>{}</guid>\n", escape(&entry.url)));
+        output.push_str(&format!(
+            "      <pubDate>{}</pubDate>\n",
+            entry.date.to_rfc2822()
+        ));
+        if let Some(summary) = &entry.summary {
+            output.push_str(&format!(
+                "      <description>{}</description>\n",
+                escape(summary)
+            ));
+        }
+        output.push_str("    </item>\n");
+    }
+
+    output.push_str("  </channel>\n");
+    output.push_str("</rss>\n");
+
+    output
+}
+
+fn render_atom(info: &FeedInfo, entries: &[FeedEntry]) -> String {
+    let mut output = String::new();
+
+    let updated = entries
+        .iter()
+        .map(|entry| entry.date)
+        .max()
+        .unwrap_or_else(|| Utc::now().fixed_offset());
+
+    output.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    output.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    output.push_str(&format!("  <title>{}</title>\n", escape(info.title)));
+    if !info.description.is_empty() {
+        output.push_str(&format!(
+            "  <subtitle>{}</subtitle>\n",
+            escape(info.description)
+        ));
+    }
+    output.push_str(&format!("  <link href=\"{}\"/>\n", escape(info.site_url)));
+    output.push_str(&format!(
+        "  <link href=\"{}\" rel=\"self\"/>\n",
+        escape(info.feed_url)
+    ));
+    output.push_str(&format!("  <id>{}</id>\n", escape(info.site_url)));
+    output.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        updated.to_rfc3339_opts(SecondsFormat::Secs, true)
+    ));
+
+    for entry in entries {
+        output.push_str("  <entry>\n");
+        output.push_str(&format!("    <title>{}</title>\n", escape(&entry.title)));
+        output.push_str(&format!("    <link href=\"{}\"/>\n", escape(&entry.url)));
+        output.push_str(&format!("    <id>{}</id>\n", escape(&entry.url)));
+        output.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            entry.date.to_rfc3339_opts(SecondsFormat::Secs, true)
+        ));
+        if let Some(summary) = &entry.summary {
+            output.push_str(&format!("    <summary>{}</summary>\n", escape(summary)));
+        }
+        output.push_str("  </entry>\n");
+    }
+
+    output.push_str("</feed>\n");
+
+    output
+}
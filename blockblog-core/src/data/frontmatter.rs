@@ -0,0 +1,195 @@
+use std::{
+    collections::HashMap,
+    io::{self, Error},
+    sync::LazyLock,
+};
+
+use color_eyre::Result;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use super::feed;
+use super::html;
+
+/// Reading speed `reading_time` is estimated with.
+const WORDS_PER_MINUTE: usize = 200;
+
+static PAGE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\\)?\$page\.([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Frontmatter {
+    pub output: Option<String>,
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub tags: Vec<String>,
+    pub draft: bool,
+    /// Shown in listings instead of the start of the markdown.
+    pub summary: Option<String>,
+    /// The start of the markdown, up to a `<!--more-->` line or else its first paragraph.
+    #[serde(skip)]
+    pub excerpt: String,
+    /// Words in the markdown after the frontmatter, counted when it is split off.
+    #[serde(skip)]
+    pub word_count: usize,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_yaml::Value>,
+}
+
+impl Frontmatter {
+    /// Returns a field as text, joining lists with `, `.
+    pub fn get(&self, field: &str) -> Option<String> {
+        match field {
+            "output" => self.output.clone(),
+            "title" => self.title.clone(),
+            "date" => self.date.clone(),
+            "tags" => Some(self.tags.join(", ")),
+            "draft" => Some(self.draft.to_string()),
+            "word_count" => Some(self.word_count.to_string()),
+            "reading_time" => Some(self.reading_time().to_string()),
+            "summary" => Some(self.summary_text()),
+            _ => self.extra.get(field).map(value_to_string),
+        }
+    }
+
+    /// The summary without its markup.
+    pub fn summary_text(&self) -> String {
+        html::plain_text(self.summary_markdown())
+    }
+
+    /// The `summary`, or else the excerpt, as markdown.
+    pub fn summary_markdown(&self) -> &str {
+        self.summary.as_deref().unwrap_or(&self.excerpt)
+    }
+
+    /// Estimated minutes it takes to read the markdown, rounded up.
+    pub fn reading_time(&self) -> usize {
+        self.word_count.div_ceil(WORDS_PER_MINUTE)
+    }
+
+    /// Whether the file is left out of the build: a draft, unless `drafts` is set, or dated
+    /// in the future, unless `future` is set.
+    pub fn is_hidden(&self, drafts: bool, future: bool) -> bool {
+        (self.draft && !drafts) || (self.is_future() && !future)
+    }
+
+    /// Whether the `date` is later than now.
+    pub fn is_future(&self) -> bool {
+        self.date
+            .as_deref()
+            .and_then(feed::parse_date)
+            .is_some_and(|date| date > chrono::Utc::now())
+    }
+
+    /// Returns a field as a YAML value, keeping lists as sequences.
+    pub fn value(&self, field: &str) -> Option<serde_yaml::Value> {
+        match field {
+            "tags" => Some(serde_yaml::Value::Sequence(
+                self.tags
+                    .iter()
+                    .cloned()
+                    .map(serde_yaml::Value::String)
+                    .collect(),
+            )),
+            "draft" => Some(serde_yaml::Value::Bool(self.draft)),
+            "word_count" => Some(serde_yaml::Value::from(self.word_count as u64)),
+            "reading_time" => Some(serde_yaml::Value::from(self.reading_time() as u64)),
+            "output" | "title" | "date" | "summary" => {
+                self.get(field).map(serde_yaml::Value::String)
+            }
+            _ => self.extra.get(field).cloned(),
+        }
+    }
+}
+
+/// Renders a YAML value as text for substitution into a page, joining lists with `, `.
+pub fn value_to_string(value: &serde_yaml::Value) -> String {
+    match value {
+        serde_yaml::Value::String(s) => s.clone(),
+        serde_yaml::Value::Bool(b) => b.to_string(),
+        serde_yaml::Value::Number(n) => n.to_string(),
+        serde_yaml::Value::Sequence(values) => values
+            .iter()
+            .map(value_to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        serde_yaml::Value::Null | serde_yaml::Value::Mapping(_) => String::new(),
+    }
+}
+
+/// Replaces `$page.field` placeholders with fields of `frontmatter`. Missing fields become
+/// empty strings, `\$page.` escapes a placeholder, and using a placeholder without any
+/// frontmatter is an error.
+pub fn substitute(text: &str, frontmatter: Option<&Frontmatter>) -> Result<String> {
+    if frontmatter.is_none() {
+        if let Some(caps) = PAGE_REGEX
+            .captures_iter(text)
+            .find(|caps| caps.get(1).is_none())
+        {
+            return Err(Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "$page.{} used outside of a markdown file or a page with meta",
+                    &caps[2]
+                ),
+            )
+            .into());
+        }
+    }
+
+    Ok(PAGE_REGEX
+        .replace_all(text, |caps: &Captures| match (caps.get(1), frontmatter) {
+            (None, Some(frontmatter)) => frontmatter.get(&caps[2]).unwrap_or_default(),
+            _ => format!("$page.{}", &caps[2]),
+        })
+        .to_string())
+}
+
+/// Splits a `---` delimited YAML frontmatter header off a markdown document, returning the
+/// parsed frontmatter and the remaining markdown. The frontmatter has the excerpt and word
+/// count of the markdown even if there is no header.
+pub fn split(content: &str) -> Result<(Frontmatter, &str)> {
+    let rest = match content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    {
+        Some(rest) => rest,
+        None => {
+            let frontmatter = Frontmatter {
+                excerpt: html::excerpt(content).to_string(),
+                word_count: html::word_count(content),
+                ..Frontmatter::default()
+            };
+
+            return Ok((frontmatter, content));
+        }
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let header = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+
+            let mut frontmatter = match serde_yaml::from_str::<Option<Frontmatter>>(header) {
+                Ok(frontmatter) => frontmatter.unwrap_or_default(),
+                Err(why) => {
+                    return Err(Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid frontmatter: {}", why),
+                    )
+                    .into())
+                }
+            };
+
+            frontmatter.excerpt = html::excerpt(body).to_string();
+            frontmatter.word_count = html::word_count(body);
+
+            return Ok((frontmatter, body));
+        }
+        offset += line.len();
+    }
+
+    Err(Error::new(io::ErrorKind::InvalidData, "Unterminated frontmatter").into())
+}
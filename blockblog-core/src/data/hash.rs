@@ -0,0 +1,11 @@
+/// FNV-1a hash of `bytes`, used to derive stable class names and asset file names.
+pub fn short_hash(bytes: &[u8]) -> String {
+    let mut hash: u32 = 0x811c9dc5;
+
+    for byte in bytes {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+
+    format!("{:08x}", hash)
+}
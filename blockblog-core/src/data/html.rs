@@ -0,0 +1,471 @@
+use std::collections::{HashMap, HashSet};
+
+use color_eyre::Result;
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd, TextMergeStream};
+use regex::{Captures, Regex};
+
+use super::callout::CalloutKind;
+use super::typography::{self, Typography};
+
+pub fn escape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// Decodes the entities `escape` produces, `&nbsp;` and numeric character references.
+/// Other entities are left as they are.
+pub fn unescape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+
+            Some((c, end + 1))
+        });
+
+        match decoded {
+            Some((c, len)) => {
+                output.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+pub fn is_void_element(html_type: &str) -> bool {
+    VOID_ELEMENTS.contains(&html_type.to_lowercase().as_str())
+}
+
+/// A heading of converted markdown, to link to from a table of contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    pub level: usize,
+    pub id: String,
+    pub text: String,
+}
+
+/// Converts CommonMark with the GitHub extensions: tables, footnotes, strikethrough, task
+/// lists and `> [!NOTE]` alerts, which become callouts, and `$...$` and `$$...$$` math.
+/// Headings get an id from their text unless one is given with `{#id}`, and a link to
+/// themselves. Footnotes are listed at the end, each linking back to where it is referenced.
+pub fn markdown_to_html(text: &str, typography: Typography) -> String {
+    markdown_with_headings(text, typography).0
+}
+
+/// Converts markdown like `markdown_to_html`, also returning its headings in order.
+pub fn markdown_with_headings(text: &str, typography: Typography) -> (String, Vec<Heading>) {
+    let mut options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_GFM
+        | Options::ENABLE_MATH;
+
+    if typography.smart_punctuation {
+        options |= Options::ENABLE_SMART_PUNCTUATION;
+    }
+
+    let mut events = TextMergeStream::new(Parser::new_ext(text, options)).collect::<Vec<_>>();
+
+    if typography.emoji {
+        events = replace_emoji(events);
+    }
+
+    let mut used_ids = HashSet::new();
+    let mut headings = Vec::new();
+
+    for i in 0..events.len() {
+        let (level, given_id) = match &events[i] {
+            Event::Start(Tag::Heading { level, id, .. }) => (*level as usize, id.clone()),
+            _ => continue,
+        };
+
+        let mut heading_text = String::new();
+        for event in events[i + 1..].iter() {
+            match event {
+                Event::End(TagEnd::Heading(_)) => break,
+                Event::Text(text) | Event::Code(text) => heading_text.push_str(text),
+                _ => {}
+            }
+        }
+
+        if let Some(id) = given_id {
+            headings.push(Heading {
+                level,
+                id: id.to_string(),
+                text: heading_text,
+            });
+            continue;
+        }
+
+        let slug = slugify(&heading_text);
+        let mut unique = slug.clone();
+        let mut n = 1;
+        while !used_ids.insert(unique.clone()) {
+            n += 1;
+            unique = format!("{}_{}", slug, n);
+        }
+
+        if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+            *id = Some(CowStr::from(unique.clone()));
+        }
+
+        headings.push(Heading {
+            level,
+            id: unique,
+            text: heading_text,
+        });
+    }
+
+    let events = add_heading_anchors(events, &headings);
+    let events = list_footnotes(events);
+    let events = alert_callouts(events);
+
+    let mut output = String::new();
+    pulldown_cmark::html::push_html(&mut output, events.into_iter());
+    (output, headings)
+}
+
+/// Replaces emoji shortcodes in text, other than in code blocks.
+fn replace_emoji(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut in_code_block = false;
+
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                event
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                event
+            }
+            Event::Text(text) if !in_code_block && text.contains(':') => {
+                Event::Text(CowStr::from(typography::replace_emoji(&text)))
+            }
+            event => event,
+        })
+        .collect()
+}
+
+/// Ends each heading with a link to itself, for copying a link to a section.
+fn add_heading_anchors<'a>(events: Vec<Event<'a>>, headings: &[Heading]) -> Vec<Event<'a>> {
+    let mut output = Vec::with_capacity(events.len() + headings.len());
+    let mut headings = headings.iter();
+
+    for event in events {
+        let heading = match event {
+            Event::End(TagEnd::Heading(_)) => headings.next(),
+            _ => None,
+        };
+
+        if let Some(heading) = heading {
+            output.push(Event::InlineHtml(CowStr::from(heading_anchor(&heading.id))));
+        }
+
+        output.push(event);
+    }
+
+    output
+}
+
+/// Replaces the block quotes of alerts with callouts like the `callout` item's.
+fn alert_callouts(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::BlockQuote(Some(kind))) => {
+                let (kind, title) = CalloutKind::from_alert(kind);
+                Event::Html(CowStr::from(kind.open(title)))
+            }
+            Event::End(TagEnd::BlockQuote(Some(_))) => Event::Html(CowStr::from("</aside>\n")),
+            event => event,
+        })
+        .collect()
+}
+
+/// A link to the heading with `id`, hidden from screen readers.
+pub fn heading_anchor(id: &str) -> String {
+    format!(
+        " <a class=\"anchor\" href=\"#{}\" aria-hidden=\"true\">#</a>",
+        escape(id)
+    )
+}
+
+/// Moves footnote definitions to a numbered list at the end, in the order they are first
+/// referenced, each with links back to its references. Definitions that are never
+/// referenced are left out, and references to undefined footnotes are left as text.
+fn list_footnotes(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut definitions = HashMap::new();
+    let mut body = Vec::with_capacity(events.len());
+    let mut definition = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::FootnoteDefinition(label)) => definition = Some((label, Vec::new())),
+            Event::End(TagEnd::FootnoteDefinition) => {
+                if let Some((label, content)) = definition.take() {
+                    definitions.insert(label, content);
+                }
+            }
+            event => match &mut definition {
+                Some((_, content)) => content.push(event),
+                None => body.push(event),
+            },
+        }
+    }
+
+    let mut footnotes = Footnotes {
+        defined: definitions.keys().cloned().collect(),
+        order: Vec::new(),
+        references: Vec::new(),
+    };
+    let mut output = footnotes.replace_references(body);
+
+    if footnotes.order.is_empty() {
+        return output;
+    }
+
+    output.push(Event::Html(CowStr::from(
+        "<section class=\"footnotes\">\n<ol>\n",
+    )));
+
+    // footnotes referenced only from other footnotes are added to the order while listing
+    let mut index = 0;
+    while index < footnotes.order.len() {
+        let number = index + 1;
+        let content = definitions
+            .remove(&footnotes.order[index])
+            .unwrap_or_default();
+        let mut content = footnotes.replace_references(content);
+
+        let backrefs = (1..=footnotes.references[index])
+            .map(|reference| {
+                format!(
+                    " <a class=\"footnote-backref\" href=\"#{}\" aria-label=\"Back to reference {}\">↩</a>",
+                    footnote_ref_id(number, reference),
+                    number
+                )
+            })
+            .collect::<String>();
+
+        let end = match content.last() {
+            Some(Event::End(TagEnd::Paragraph)) => content.len() - 1,
+            _ => content.len(),
+        };
+        content.insert(end, Event::InlineHtml(CowStr::from(backrefs)));
+
+        output.push(Event::Html(CowStr::from(format!(
+            "<li id=\"footnote-{}\">\n",
+            number
+        ))));
+        output.extend(content);
+        output.push(Event::Html(CowStr::from("</li>\n")));
+
+        index += 1;
+    }
+
+    output.push(Event::Html(CowStr::from("</ol>\n</section>\n")));
+    output
+}
+
+struct Footnotes<'a> {
+    defined: HashSet<CowStr<'a>>,
+    /// Labels of the referenced footnotes, in the order they are numbered.
+    order: Vec<CowStr<'a>>,
+    /// How many times each footnote in `order` is referenced.
+    references: Vec<usize>,
+}
+
+impl<'a> Footnotes<'a> {
+    /// Replaces references to defined footnotes with links numbered in the order they are
+    /// first referenced.
+    fn replace_references(&mut self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                Event::FootnoteReference(label) if self.defined.contains(&label) => {
+                    let index = match self.order.iter().position(|other| *other == label) {
+                        Some(index) => index,
+                        None => {
+                            self.order.push(label);
+                            self.references.push(0);
+                            self.order.len() - 1
+                        }
+                    };
+                    self.references[index] += 1;
+
+                    let number = index + 1;
+                    Event::InlineHtml(CowStr::from(format!(
+                        "<sup class=\"footnote-ref\"><a id=\"{}\" href=\"#footnote-{}\">{}</a></sup>",
+                        footnote_ref_id(number, self.references[index]),
+                        number,
+                        number
+                    )))
+                }
+                Event::FootnoteReference(label) => {
+                    Event::Text(CowStr::from(format!("[^{}]", label)))
+                }
+                event => event,
+            })
+            .collect()
+    }
+}
+
+/// Id of the `reference`th reference to footnote `number`, counting from 1.
+fn footnote_ref_id(number: usize, reference: usize) -> String {
+    if reference == 1 {
+        format!("footnote-ref-{}", number)
+    } else {
+        format!("footnote-ref-{}-{}", number, reference)
+    }
+}
+
+/// Line that ends the summary of a markdown document.
+const MORE_MARKER: &str = "<!--more-->";
+
+/// The markdown before a `<!--more-->` line, or else the first paragraph.
+pub fn excerpt(markdown: &str) -> &str {
+    let mut offset = 0;
+    for line in markdown.split_inclusive('\n') {
+        if line.trim() == MORE_MARKER {
+            return markdown[..offset].trim();
+        }
+        offset += line.len();
+    }
+
+    Parser::new(markdown)
+        .into_offset_iter()
+        .find(|(event, _)| matches!(event, Event::Start(Tag::Paragraph)))
+        .map_or("", |(_, range)| markdown[range].trim())
+}
+
+/// The text of markdown without its markup, with line breaks as spaces.
+pub fn plain_text(markdown: &str) -> String {
+    let mut output = String::new();
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(text) | Event::Code(text) => output.push_str(&text),
+            Event::SoftBreak | Event::HardBreak | Event::End(_) if !output.ends_with(' ') => {
+                output.push(' ')
+            }
+            _ => {}
+        }
+    }
+
+    output.trim().to_string()
+}
+
+/// Number of words in the text of markdown, leaving out its markup, URLs and HTML.
+pub fn word_count(markdown: &str) -> usize {
+    Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES)
+        .map(|event| match event {
+            Event::Text(text) | Event::Code(text) => text
+                .split_whitespace()
+                .filter(|word| word.chars().any(char::is_alphanumeric))
+                .count(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Lowercases text and joins its words with `_`, dropping punctuation.
+pub fn slugify(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Prefixes root-relative URLs in `href`, `src`, `action` and `poster` attributes with
+/// `base_path`, so a site deployed under a subpath links within itself. URLs that already
+/// start with it are left alone.
+pub fn prefix_root_urls(html: &str, base_path: &str) -> Result<String> {
+    if base_path.is_empty() {
+        return Ok(html.to_string());
+    }
+
+    let url_regex = Regex::new(r#"(\s(?:href|src|action|poster)=["'])(/[^"']*)"#)?;
+
+    Ok(url_regex
+        .replace_all(html, |caps: &Captures| {
+            let url = &caps[2];
+            let prefixed = url.starts_with("//")
+                || url == base_path
+                || url
+                    .strip_prefix(base_path)
+                    .is_some_and(|rest| rest.starts_with(['/', '?', '#']));
+
+            if prefixed {
+                caps[0].to_string()
+            } else {
+                format!("{}{}{}", &caps[1], base_path, url)
+            }
+        })
+        .to_string())
+}
+
+pub fn inline_markdown(text: &str, typography: Typography) -> String {
+    let output = markdown_to_html(text, typography);
+    let output = output.trim();
+
+    match output
+        .strip_prefix("<p>")
+        .and_then(|inner| inner.strip_suffix("</p>"))
+    {
+        Some(inner) if !inner.contains("<p>") => inner.to_string(),
+        _ => output.to_string(),
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Error},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use super::data_files;
+use super::frontmatter;
+
+/// Directory, relative to the input directory, holding a `<language>.yml` of translated
+/// strings for each language.
+pub const I18N_DIR: &str = "i18n";
+
+/// Languages of a multilingual site. Pages in the first language are at the root, and pages
+/// in each other one are in a directory named after it, like `pl/about.yml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct I18nConfig {
+    pub languages: Vec<String>,
+}
+
+impl I18nConfig {
+    pub fn default_language(&self) -> Option<&str> {
+        self.languages.first().map(String::as_str)
+    }
+
+    pub fn is_language(&self, language: &str) -> bool {
+        self.languages.iter().any(|known| known == language)
+    }
+
+    /// Language of the page `page_name` from the directory it's in, or the default language.
+    pub fn page_language<'a>(&'a self, page_name: &'a str) -> Option<&'a str> {
+        match page_name.split_once('/') {
+            Some((dir, _)) if self.is_language(dir) => Some(dir),
+            _ => self.default_language(),
+        }
+    }
+
+    /// Name of the page `page_name` in another language, which may not exist.
+    pub fn translated_page_name(&self, page_name: &str, language: &str) -> String {
+        let neutral = match page_name.split_once('/') {
+            Some((dir, rest)) if self.is_language(dir) => rest,
+            _ => page_name,
+        };
+
+        if Some(language) == self.default_language() {
+            neutral.to_string()
+        } else {
+            format!("{}/{}", language, neutral)
+        }
+    }
+
+    /// What `{lang}` in an output path stands for: empty for the default language, so only
+    /// the other ones are prefixed.
+    pub fn path_prefix<'a>(&self, language: &'a str) -> &'a str {
+        if Some(language) == self.default_language() {
+            ""
+        } else {
+            language
+        }
+    }
+}
+
+/// The translated strings of each language, used with `$t(key)`.
+#[derive(Debug, Default)]
+pub struct Translations {
+    languages: BTreeMap<String, (PathBuf, serde_yaml::Value)>,
+}
+
+impl Translations {
+    /// Reads `i18n/<language>.yml` for each language that has one.
+    pub fn load(input: &Path, config: &I18nConfig) -> Result<Self> {
+        let mut translations = Self::default();
+
+        for language in config.languages.iter() {
+            let relative = Path::new(I18N_DIR).join(format!("{}.yml", language));
+            let path = input.join(&relative);
+
+            if !path.is_file() {
+                continue;
+            }
+
+            let value = serde_yaml::from_str(&std::fs::read_to_string(&path)?).map_err(|why| {
+                Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.to_string_lossy(), why),
+                )
+            })?;
+
+            translations
+                .languages
+                .insert(language.clone(), (relative, value));
+        }
+
+        Ok(translations)
+    }
+
+    /// The string `key`, which may be a `.` separated path into nested maps, in `language`
+    /// or else in the default language. Also returns the file it comes from.
+    pub fn get(&self, config: &I18nConfig, language: &str, key: &str) -> Option<(&Path, String)> {
+        [Some(language), config.default_language()]
+            .into_iter()
+            .flatten()
+            .find_map(|language| {
+                let (file, strings) = self.languages.get(language)?;
+                let value = data_files::lookup(strings, key)?;
+
+                Some((file.as_path(), frontmatter::value_to_string(value)))
+            })
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    io::{self, Error},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+use image::{imageops::FilterType, DynamicImage};
+
+/// Widths the variants of a responsive image are generated at. Widths larger than the image
+/// itself, or than its requested width, are left out.
+pub const RESPONSIVE_WIDTHS: &[u32] = &[480, 960, 1440, 1920];
+
+/// A resized or converted copy of an input image, generated into the output directory.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ImageVariant {
+    /// Relative to the input directory.
+    pub source: PathBuf,
+    /// Relative to the output directory.
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub fn dimensions(path: &Path) -> Result<(u32, u32)> {
+    image::image_dimensions(path).map_err(|why| {
+        Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.to_string_lossy(), why),
+        )
+        .into()
+    })
+}
+
+/// Scales `original` down to fit inside the requested width and height, keeping its aspect
+/// ratio. Images are never scaled up.
+pub fn fit(original: (u32, u32), width: Option<u32>, height: Option<u32>) -> (u32, u32) {
+    let (original_width, original_height) = original;
+
+    let scale = [
+        width.map(|width| width as f64 / original_width as f64),
+        height.map(|height| height as f64 / original_height as f64),
+    ]
+    .into_iter()
+    .flatten()
+    .fold(1.0, f64::min);
+
+    (
+        ((original_width as f64 * scale).round() as u32).max(1),
+        ((original_height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+/// File name of a variant, like `photo.960x640.webp` for `photo.jpg`.
+pub fn variant_file_name(file_name: &str, size: (u32, u32), format: Option<&str>) -> String {
+    let (stem, ext) = file_name.rsplit_once('.').unwrap_or((file_name, ""));
+    let ext = format.unwrap_or(ext);
+
+    format!("{}.{}x{}.{}", stem, size.0, size.1, ext)
+}
+
+impl ImageVariant {
+    /// Resizes and encodes the source image into the output directory, unless the variant
+    /// there is already newer than the source. Returns whether it was written.
+    pub fn write(&self, input: &Path, output: &Path) -> Result<bool> {
+        let source = input.join(&self.source);
+        let output_file = output.join(&self.output);
+
+        let is_fresh = match (source.metadata(), output_file.metadata()) {
+            (Ok(source), Ok(output)) => match (source.modified(), output.modified()) {
+                (Ok(source), Ok(output)) => output >= source,
+                _ => false,
+            },
+            _ => false,
+        };
+
+        if is_fresh {
+            return Ok(false);
+        }
+
+        let image = image::open(&source).map_err(|why| {
+            Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", source.to_string_lossy(), why),
+            )
+        })?;
+
+        if let Some(parent) = output_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let resized = image.resize_exact(self.width, self.height, FilterType::Lanczos3);
+
+        // JPEG has no alpha channel to encode
+        let resized = match output_file.extension().and_then(|ext| ext.to_str()) {
+            Some("jpg" | "jpeg") => DynamicImage::ImageRgb8(resized.to_rgb8()),
+            _ => resized,
+        };
+
+        resized.save(&output_file).map_err(|why| {
+            Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", output_file.to_string_lossy(), why),
+            )
+        })?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    io::{self, Error},
+    ops::RangeInclusive,
+};
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, FixedOffset,
+};
+use color_eyre::Result;
+
+use super::feed;
+use super::taxonomy;
+
+/// Appended to text `truncate` cuts short, unless it is given another suffix.
+const TRUNCATE_SUFFIX: &str = "…";
+
+/// Replaces the `${...}` expressions in `text`. An expression is a dotted variable name,
+/// like `page.title`, or a quoted string, followed by any number of `| filter` or
+/// `| filter(args)`. `lookup` gives the value of a variable, or `None` if it isn't one.
+/// Anything that isn't an expression like that, like a template literal in a JavaScript
+/// snippet, is left as it is, and `\${` is left as a literal `${`.
+pub fn interpolate(
+    text: &str,
+    mut lookup: impl FnMut(&str) -> Result<Option<String>>,
+) -> Result<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('\\') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        output.push_str(&rest[..start]);
+
+        let inner = &rest[start + 2..];
+        let end = match find_outside_quotes(inner, '}') {
+            Some(end) => end,
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        };
+
+        match evaluate(&inner[..end], &mut lookup)? {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&rest[start..start + end + 3]),
+        }
+
+        rest = &inner[end + 1..];
+    }
+
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+/// Value of the expression between `${` and `}`, or `None` if it isn't one.
+fn evaluate(
+    expression: &str,
+    lookup: &mut impl FnMut(&str) -> Result<Option<String>>,
+) -> Result<Option<String>> {
+    let parts = match split_outside_quotes(expression, '|') {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+    let head = parts[0].trim();
+
+    let mut value = if head.len() > 1 && head.starts_with('"') && head.ends_with('"') {
+        unquote(head)
+    } else if is_variable_name(head) {
+        match lookup(head)? {
+            Some(value) => value,
+            None => return Ok(None),
+        }
+    } else {
+        return Ok(None);
+    };
+
+    for filter in parts[1..].iter() {
+        let (name, args) = parse_filter(filter.trim()).ok_or_else(|| {
+            invalid(format!(
+                "Invalid filter {} in ${{{}}}",
+                filter.trim(),
+                expression.trim()
+            ))
+        })?;
+
+        value = apply_filter(value, name, &args)?;
+    }
+
+    Ok(Some(value))
+}
+
+/// Splits a filter like `truncate(20, "…")` into its name and arguments.
+fn parse_filter(filter: &str) -> Option<(&str, Vec<String>)> {
+    let (name, args) = match filter.split_once('(') {
+        Some((name, args)) => (name.trim(), Some(args.strip_suffix(')')?)),
+        None => (filter, None),
+    };
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    let args = match args {
+        Some(args) if !args.trim().is_empty() => split_outside_quotes(args, ',')?
+            .iter()
+            .map(|arg| unquote(arg.trim()))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Some((name, args))
+}
+
+fn apply_filter(value: String, name: &str, args: &[String]) -> Result<String> {
+    let expect_args = |counts: RangeInclusive<usize>| {
+        if counts.contains(&args.len()) {
+            return Ok(());
+        }
+
+        let expected = if counts.start() == counts.end() {
+            counts.start().to_string()
+        } else {
+            format!("{} to {}", counts.start(), counts.end())
+        };
+
+        Err(invalid(format!(
+            "Filter {} takes {} arguments, got {}",
+            name,
+            expected,
+            args.len()
+        )))
+    };
+
+    match name {
+        "upper" => {
+            expect_args(0..=0)?;
+            Ok(value.to_uppercase())
+        }
+        "lower" => {
+            expect_args(0..=0)?;
+            Ok(value.to_lowercase())
+        }
+        "default" => {
+            expect_args(1..=1)?;
+            Ok(if value.is_empty() {
+                args[0].clone()
+            } else {
+                value
+            })
+        }
+        "slugify" => {
+            expect_args(0..=0)?;
+            Ok(taxonomy::slug(&value))
+        }
+        "date_format" => {
+            expect_args(1..=1)?;
+            date_format(&value, &args[0])
+        }
+        "truncate" => {
+            expect_args(1..=2)?;
+            let length = args[0].parse::<usize>().map_err(|_| {
+                invalid(format!(
+                    "truncate takes a number of characters, got {}",
+                    args[0]
+                ))
+            })?;
+            let suffix = args.get(1).map_or(TRUNCATE_SUFFIX, String::as_str);
+
+            Ok(truncate(value, length, suffix))
+        }
+        "strip_ext" => {
+            expect_args(0..=0)?;
+            Ok(strip_ext(&value).to_string())
+        }
+        "url_encode" => {
+            expect_args(0..=0)?;
+            Ok(url_encode(&value))
+        }
+        _ => Err(invalid(format!("Unknown filter {}", name))),
+    }
+}
+
+/// Formats a date like the ones in frontmatter with a `strftime` format. Empty stays empty,
+/// so pages without a date don't fail.
+fn date_format(value: &str, format: &str) -> Result<String> {
+    if value.trim().is_empty() {
+        return Ok(String::new());
+    }
+
+    let date = feed::parse_date(value)
+        .ok_or_else(|| invalid(format!("date_format needs a date, got {}", value)))?;
+
+    format_date(&date, format)
+}
+
+/// Formats a date with a `strftime` format, failing on an invalid format where chrono would
+/// panic.
+pub fn format_date(date: &DateTime<FixedOffset>, format: &str) -> Result<String> {
+    let items = StrftimeItems::new(format).collect::<Vec<_>>();
+
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(invalid(format!("Invalid date format {}", format)));
+    }
+
+    Ok(date.format_with_items(items.into_iter()).to_string())
+}
+
+/// Cuts text down to `length` characters, with `suffix` at the end if anything was cut.
+fn truncate(value: String, length: usize, suffix: &str) -> String {
+    match value.char_indices().nth(length) {
+        Some((end, _)) => format!("{}{}", value[..end].trim_end(), suffix),
+        None => value,
+    }
+}
+
+/// Leaves out the extension of the last segment of a path, like `posts/a.md` to `posts/a`.
+fn strip_ext(value: &str) -> &str {
+    let name_start = value.rfind('/').map_or(0, |slash| slash + 1);
+
+    match value[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => &value[..name_start + dot],
+        _ => value,
+    }
+}
+
+/// Percent-encodes everything but unreserved characters and `/`, so a path stays a path.
+pub fn url_encode(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                output.push(byte as char)
+            }
+            _ => output.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    output
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_'))
+}
+
+/// Byte offsets of each `separator` in `text` that isn't inside a `"` quoted string, and
+/// whether a string is left open at the end.
+fn separators_outside_quotes(text: &str, separator: char) -> (Vec<usize>, bool) {
+    let mut offsets = Vec::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            c if c == separator && !in_quotes => offsets.push(i),
+            _ => {}
+        }
+    }
+
+    (offsets, in_quotes)
+}
+
+/// Byte offset of the first `separator` in `text` that isn't inside a `"` quoted string.
+fn find_outside_quotes(text: &str, separator: char) -> Option<usize> {
+    separators_outside_quotes(text, separator)
+        .0
+        .first()
+        .copied()
+}
+
+/// Splits `text` at each `separator` that isn't inside a `"` quoted string. `None` if a
+/// string is left open.
+fn split_outside_quotes(text: &str, separator: char) -> Option<Vec<&str>> {
+    let (offsets, unterminated) = separators_outside_quotes(text, separator);
+
+    if unterminated {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    let mut start = 0;
+
+    for offset in offsets {
+        parts.push(&text[start..offset]);
+        start = offset + separator.len_utf8();
+    }
+
+    parts.push(&text[start..]);
+
+    Some(parts)
+}
+
+/// The contents of a `"` quoted string with `\"` and `\\` unescaped, or the text as is if
+/// it isn't quoted.
+fn unquote(text: &str) -> String {
+    let inner = match text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+    {
+        Some(inner) => inner,
+        None => return text.to_string(),
+    };
+
+    let mut output = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => output.extend(chars.next()),
+            c => output.push(c),
+        }
+    }
+
+    output
+}
+
+fn invalid(message: String) -> color_eyre::Report {
+    Error::new(io::ErrorKind::InvalidInput, message).into()
+}
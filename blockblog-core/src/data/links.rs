@@ -0,0 +1,205 @@
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use super::html;
+
+const REL: &str = "noopener noreferrer";
+
+/// An `a` start tag, capturing its attributes and the `/` of a self-closing one.
+static TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"<a((?:\s+[^\s"'>/=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'>]+))?)*)\s*(/?)>"#)
+        .unwrap()
+});
+/// An attribute of a tag, capturing its name and its double-quoted, single-quoted or
+/// unquoted value.
+static ATTRIBUTE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\s+([^\s"'>/=]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+)))?"#).unwrap()
+});
+
+/// How links to other sites are marked up in the generated pages. Every `a` tag with an
+/// `http`, `https` or protocol-relative `href` whose host isn't the one of `base_url` gets
+/// `rel="noopener noreferrer"`, added to any `rel` it already has.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ExternalLinks {
+    /// Add `target="_blank"` to links without a `target`, opening them in a new tab.
+    pub new_tab: bool,
+    /// Class added to external links, like one that shows an icon after them.
+    pub class: Option<String>,
+}
+
+impl ExternalLinks {
+    /// Marks up the external links in `html`, leaving the rest of it as it is.
+    pub fn apply(&self, html: &str, base_url: Option<&str>) -> String {
+        let site_host = base_url.and_then(host);
+
+        TAG_REGEX
+            .replace_all(html, |caps: &Captures| {
+                let attributes = ATTRIBUTE_REGEX
+                    .captures_iter(&caps[1])
+                    .map(|attribute| {
+                        let value = attribute
+                            .get(2)
+                            .or(attribute.get(3))
+                            .or(attribute.get(4))
+                            .map(|value| value.as_str());
+
+                        (
+                            attribute[1].to_ascii_lowercase(),
+                            attribute[0].to_string(),
+                            value,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let external = attributes.iter().any(|(name, _, value)| {
+                    name == "href"
+                        && value
+                            .and_then(host)
+                            .is_some_and(|host| site_host.as_ref() != Some(&host))
+                });
+
+                match external {
+                    true => self.tag(&attributes, &caps[2]),
+                    false => caps[0].to_string(),
+                }
+            })
+            .to_string()
+    }
+
+    fn tag(&self, attributes: &[(String, String, Option<&str>)], self_closing: &str) -> String {
+        let mut output = String::from("<a");
+        let mut has_rel = false;
+        let mut has_target = false;
+        let mut has_class = false;
+
+        for (name, source, value) in attributes {
+            match (name.as_str(), &self.class) {
+                ("rel", _) => {
+                    has_rel = true;
+                    output.push_str(&attribute(
+                        "rel",
+                        &add_tokens(value.unwrap_or_default(), REL),
+                    ));
+                }
+                ("class", Some(class)) => {
+                    has_class = true;
+                    output.push_str(&attribute(
+                        "class",
+                        &add_tokens(value.unwrap_or_default(), &html::escape(class)),
+                    ));
+                }
+                (name, _) => {
+                    has_target |= name == "target";
+                    output.push_str(source);
+                }
+            }
+        }
+
+        if !has_rel {
+            output.push_str(&attribute("rel", REL));
+        }
+
+        if self.new_tab && !has_target {
+            output.push_str(&attribute("target", "_blank"));
+        }
+
+        if let (Some(class), false) = (&self.class, has_class) {
+            output.push_str(&attribute("class", &html::escape(class)));
+        }
+
+        output.push_str(self_closing);
+        output.push('>');
+        output
+    }
+}
+
+/// Lowercased host of an absolute `http`, `https` or protocol-relative URL.
+fn host(url: &str) -> Option<String> {
+    let url = url.trim();
+    let lowercase = url.to_ascii_lowercase();
+
+    let rest = ["http://", "https://", "//"]
+        .iter()
+        .find_map(|prefix| lowercase.strip_prefix(prefix))?;
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => host,
+    };
+
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+/// Space-separated `tokens` with the missing ones of `added` appended.
+fn add_tokens<'a>(tokens: &'a str, added: &'a str) -> String {
+    let mut tokens = tokens.split_whitespace().collect::<Vec<_>>();
+
+    for token in added.split_whitespace() {
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+
+    tokens.join(" ")
+}
+
+fn attribute(name: &str, value: &str) -> String {
+    format!(" {}=\"{}\"", name, value.replace('"', "&quot;"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_URL: Option<&str> = Some("https://example.com/blog/");
+
+    #[test]
+    fn only_other_hosts_are_external() {
+        let links = ExternalLinks::default();
+        let html = links.apply(
+            "<a href=\"https://Example.com:443/about\">a</a> <a href=\"/about\">b</a> <a href=\"https://other.org/\">c</a>",
+            BASE_URL,
+        );
+
+        assert_eq!(
+            html,
+            "<a href=\"https://Example.com:443/about\">a</a> <a href=\"/about\">b</a> <a href=\"https://other.org/\" rel=\"noopener noreferrer\">c</a>"
+        );
+    }
+
+    #[test]
+    fn protocol_relative_links() {
+        let links = ExternalLinks::default();
+
+        assert_eq!(
+            links.apply("<a href='//other.org/x'>x</a>", BASE_URL),
+            "<a href='//other.org/x' rel=\"noopener noreferrer\">x</a>"
+        );
+        assert_eq!(
+            links.apply("<a href=//example.com/x>x</a>", BASE_URL),
+            "<a href=//example.com/x>x</a>"
+        );
+    }
+
+    #[test]
+    fn existing_rel_and_class() {
+        let links = ExternalLinks {
+            new_tab: true,
+            class: Some("external".to_string()),
+        };
+        let html = links.apply(
+            "<a class=\"button\" rel=\"me noopener\" href=\"https://other.org/\">x</a>",
+            BASE_URL,
+        );
+
+        assert_eq!(
+            html,
+            "<a class=\"button external\" rel=\"me noopener noreferrer\" href=\"https://other.org/\" target=\"_blank\">x</a>"
+        );
+    }
+}
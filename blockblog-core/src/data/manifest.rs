@@ -0,0 +1,267 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{read_dir, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use color_eyre::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::block_builder::Dependencies;
+use super::config::SiteConfig;
+use super::hash;
+use super::styles::StyleRules;
+
+pub const BUILD_MANIFEST_FILE: &str = ".blockblog-manifest.json";
+
+/// What was built for a page, needed to decide whether a change affects it and to write
+/// the merged styles without constructing it again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuiltPage {
+    pub generated_styles: StyleRules,
+    pub dependencies: Dependencies,
+    /// Output path of the page, relative to the output directory.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Files written for the page, relative to the output directory: its HTML, the further
+    /// pages of a paginated page and generated images.
+    #[serde(default)]
+    pub outputs: BTreeSet<PathBuf>,
+}
+
+impl BuiltPage {
+    /// Output path of the page, `<name>.html` unless it has a permalink.
+    pub fn path(&self, block_name: &str) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}.html", block_name)))
+    }
+}
+
+pub type BuiltPages = BTreeMap<String, BuiltPage>;
+
+/// The options that change the generated output. A manifest written with different ones
+/// cannot be built on incrementally.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestOptions {
+    pub version: String,
+    pub debug: bool,
+    pub inline_generated_css: bool,
+    pub css_vars: bool,
+    pub drafts: bool,
+    pub future: bool,
+    pub no_assets: bool,
+    pub minify: bool,
+    pub pretty_urls: bool,
+    pub base_url: Option<String>,
+    pub profile: Option<String>,
+}
+
+/// Written to the output directory after each build, recording the hash of every input file
+/// and what each page was built from, so that the next build only redoes what changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildManifest {
+    pub options: ManifestOptions,
+    #[serde(flatten)]
+    pub inputs: InputHashes,
+    pub pages: BuiltPages,
+}
+
+/// Hashes of the input files, keyed by their path relative to the input directory with `/`
+/// as the separator.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputHashes {
+    #[serde(rename = "inputs")]
+    pub hashes: BTreeMap<String, String>,
+    /// Size and modification time of each file when it was hashed, which tell whether the
+    /// hash is still current without reading the file again.
+    #[serde(default)]
+    pub stamps: BTreeMap<String, FileStamp>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub modified: SystemTime,
+    pub len: u64,
+}
+
+impl BuildManifest {
+    /// Reads the manifest from `output`, if there is a readable one.
+    pub fn load(output: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(output.join(BUILD_MANIFEST_FILE)).ok()?;
+
+        match serde_json::from_str(&contents) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                warn!("Ignoring unreadable build manifest: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn write(&self, output: &Path) -> Result<()> {
+        let mut manifest = File::create(output.join(BUILD_MANIFEST_FILE))?;
+
+        manifest.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Returns the input files that were added, removed or modified since this manifest was
+    /// written, relative to the input directory.
+    pub fn changed_inputs(&self, inputs: &InputHashes) -> Vec<PathBuf> {
+        let modified = inputs
+            .hashes
+            .iter()
+            .filter(|(path, hash)| self.inputs.hashes.get(*path) != Some(*hash))
+            .map(|(path, _)| path);
+        let removed = self
+            .inputs
+            .hashes
+            .keys()
+            .filter(|path| !inputs.hashes.contains_key(*path));
+
+        modified.chain(removed).map(PathBuf::from).collect()
+    }
+}
+
+/// Hashes every file under `input` except those in `output` and ignored ones. The hashes of
+/// `previous` are reused for files that have the same size and modification time as when
+/// they were hashed.
+pub fn hash_inputs(
+    input: &Path,
+    output: &Path,
+    site_config: &SiteConfig,
+    previous: Option<&InputHashes>,
+) -> Result<InputHashes> {
+    let mut inputs = InputHashes::default();
+    let output = output.canonicalize().ok();
+
+    hash_dir(
+        input,
+        input,
+        output.as_deref(),
+        site_config,
+        previous,
+        &mut inputs,
+    )?;
+
+    Ok(inputs)
+}
+
+/// The hashes of the previous build of the output directory, if it has a manifest.
+pub fn previous_hashes(output: &Path) -> Option<InputHashes> {
+    BuildManifest::load(output).map(|manifest| manifest.inputs)
+}
+
+fn hash_dir(
+    dir: &Path,
+    input: &Path,
+    output: Option<&Path>,
+    site_config: &SiteConfig,
+    previous: Option<&InputHashes>,
+    inputs: &mut InputHashes,
+) -> Result<()> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+
+        if site_config.is_ignored(path.strip_prefix(input)?) {
+            continue;
+        }
+
+        if path.is_dir() {
+            if output.is_some_and(|output| path.canonicalize().is_ok_and(|path| path == output)) {
+                continue;
+            }
+
+            hash_dir(&path, input, output, site_config, previous, inputs)?;
+        } else if path.is_file() {
+            let key = path
+                .strip_prefix(input)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let metadata = std::fs::metadata(&path)?;
+            let stamp = metadata.modified().ok().map(|modified| FileStamp {
+                modified,
+                len: metadata.len(),
+            });
+
+            let unchanged = previous
+                .filter(|previous| stamp.is_some() && previous.stamps.get(&key) == stamp.as_ref());
+            let hash = match unchanged.and_then(|previous| previous.hashes.get(&key)) {
+                Some(hash) => hash.clone(),
+                None => hash::short_hash(&std::fs::read(&path)?),
+            };
+
+            inputs.hashes.insert(key.clone(), hash);
+            if let Some(stamp) = stamp {
+                inputs.stamps.insert(key, stamp);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashed_site() -> (tempfile::TempDir, InputHashes) {
+        let input = tempfile::tempdir().unwrap();
+        std::fs::write(input.path().join("a.css"), "a").unwrap();
+
+        let inputs = hash_inputs(
+            input.path(),
+            &input.path().join("out"),
+            &SiteConfig::default(),
+            None,
+        )
+        .unwrap();
+
+        (input, inputs)
+    }
+
+    #[test]
+    fn unchanged_files_keep_their_hash() {
+        let (input, mut previous) = hashed_site();
+        previous
+            .hashes
+            .insert("a.css".to_string(), "cached".to_string());
+
+        let inputs = hash_inputs(
+            input.path(),
+            &input.path().join("out"),
+            &SiteConfig::default(),
+            Some(&previous),
+        )
+        .unwrap();
+
+        assert_eq!(inputs.hashes["a.css"], "cached");
+    }
+
+    #[test]
+    fn changed_files_are_hashed_again() {
+        let (input, mut previous) = hashed_site();
+        previous
+            .hashes
+            .insert("a.css".to_string(), "cached".to_string());
+        std::fs::write(input.path().join("a.css"), "changed").unwrap();
+
+        let inputs = hash_inputs(
+            input.path(),
+            &input.path().join("out"),
+            &SiteConfig::default(),
+            Some(&previous),
+        )
+        .unwrap();
+
+        assert_eq!(inputs.hashes["a.css"], hash::short_hash(b"changed"));
+    }
+}
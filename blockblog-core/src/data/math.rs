@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+use super::html;
+
+/// Renders every element with the `math` class with KaTeX once the page is loaded.
+const RENDER_SCRIPT: &str = "document.addEventListener(\"DOMContentLoaded\", function () { \
+    document.querySelectorAll(\".math\").forEach(function (element) { \
+    katex.render(element.textContent, element, { \
+    displayMode: element.classList.contains(\"math-display\"), throwOnError: false }); }); });";
+
+/// How math in `$...$` and `$$...$$` in markdown and in `math` items is rendered. It is
+/// written as escaped TeX, which KaTeX renders in the browser on the pages that have any.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct MathConfig {
+    /// URL of the directory with `katex.min.js` and `katex.min.css`, like a CDN or a copy
+    /// among the site's assets.
+    pub katex: String,
+}
+
+impl Default for MathConfig {
+    fn default() -> Self {
+        Self {
+            katex: "https://cdn.jsdelivr.net/npm/katex@0.16.11/dist".to_string(),
+        }
+    }
+}
+
+impl MathConfig {
+    /// URLs of the KaTeX stylesheet and script.
+    pub fn katex_urls(&self) -> (String, String) {
+        let dir = self.katex.trim_end_matches('/');
+
+        (
+            format!("{}/katex.min.css", dir),
+            format!("{}/katex.min.js", dir),
+        )
+    }
+}
+
+/// The script that renders the math of a page, after KaTeX is loaded.
+pub fn render_script() -> String {
+    format!("<script>{}</script>", RENDER_SCRIPT)
+}
+
+/// Whether converted markdown has any math in it.
+pub fn has_math(html: &str) -> bool {
+    html.contains("<span class=\"math math-")
+}
+
+/// Math written the way markdown math is, for KaTeX to render.
+pub fn markup(tex: &str, display: bool, style: Option<&str>) -> String {
+    let (tag, class) = match display {
+        true => ("div", "math math-display"),
+        false => ("span", "math math-inline"),
+    };
+
+    let class = match style {
+        Some(style) => format!("{} {}", class, style),
+        None => class.to_string(),
+    };
+
+    format!(
+        "<{tag} class=\"{}\">{}</{tag}>",
+        html::escape(&class),
+        html::escape(tex),
+        tag = tag
+    )
+}
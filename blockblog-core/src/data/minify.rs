@@ -0,0 +1,238 @@
+/// Collapses whitespace between and around tags, drops comments and minifies inline styles
+/// and scripts. The contents of `pre` and `textarea` elements are left alone.
+pub fn html(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input.trim();
+
+    while !rest.is_empty() {
+        let lower = rest.get(..10).unwrap_or(rest).to_ascii_lowercase();
+
+        if rest.starts_with("<!--") && !rest.starts_with("<!--[if") {
+            rest = match rest.find("-->") {
+                Some(end) => &rest[end + 3..],
+                None => "",
+            };
+        } else if let Some(element) = ["pre", "textarea", "style", "script"]
+            .into_iter()
+            .find(|element| is_start_tag(&lower, element))
+        {
+            let closing = format!("</{}", element);
+            let (open_end, close_start) = match (
+                rest.find('>'),
+                rest.to_ascii_lowercase().find(closing.as_str()),
+            ) {
+                (Some(open_end), Some(close_start)) if open_end < close_start => {
+                    (open_end + 1, close_start)
+                }
+                _ => {
+                    output.push_str(rest);
+                    break;
+                }
+            };
+
+            output.push_str(&collapse_whitespace(&rest[..open_end]));
+
+            let contents = &rest[open_end..close_start];
+            match element {
+                "style" => output.push_str(&css(contents)),
+                "script" => output.push_str(&js(contents)),
+                _ => output.push_str(contents),
+            }
+
+            rest = &rest[close_start..];
+            let close_end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            output.push_str(&rest[..close_end]);
+            rest = &rest[close_end..];
+        } else {
+            let next = rest[1..].find('<').map_or(rest.len(), |next| next + 1);
+            output.push_str(&collapse_whitespace(&rest[..next]));
+            rest = &rest[next..];
+        }
+    }
+
+    output
+}
+
+fn is_start_tag(lower: &str, element: &str) -> bool {
+    lower
+        .strip_prefix('<')
+        .and_then(|tag| tag.strip_prefix(element))
+        .is_some_and(|after| after.starts_with(['>', ' ', '\t', '\n', '\r', '/']))
+}
+
+/// Replaces each run of whitespace with a single newline if it contained one, or a single
+/// space otherwise.
+fn collapse_whitespace(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if !c.is_whitespace() {
+            output.push(c);
+            continue;
+        }
+
+        let mut newline = c == '\n';
+        while let Some(next) = chars.peek().filter(|next| next.is_whitespace()) {
+            newline |= *next == '\n';
+            chars.next();
+        }
+
+        output.push(if newline { '\n' } else { ' ' });
+    }
+
+    output
+}
+
+/// Drops comments and whitespace that CSS does not need. Strings are kept as they are.
+pub fn css(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut pending_space = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            // `a :hover` and `a:hover` select different things, so the space is kept if
+            // the colon is part of a selector rather than a declaration
+            ':' if pending_space
+                && !output.ends_with(['{', '}', ';', ',', '>'])
+                && chars.clone().find(|c| matches!(c, '{' | '}' | ';')) == Some('{') =>
+            {
+                output.push_str(" :");
+                pending_space = false;
+            }
+            '"' | '\'' => {
+                push_pending_space(&mut output, &mut pending_space);
+                copy_string(c, &mut chars, &mut output);
+            }
+            c if c.is_whitespace() => pending_space = !output.is_empty(),
+            '{' | '}' | ';' | ':' | ',' | '>' | '~' => {
+                if c == '}' && output.ends_with(';') {
+                    output.pop();
+                }
+                output.push(c);
+                pending_space = false;
+            }
+            c => {
+                push_pending_space(&mut output, &mut pending_space);
+                output.push(c);
+            }
+        }
+    }
+
+    output
+}
+
+fn push_pending_space(output: &mut String, pending_space: &mut bool) {
+    if *pending_space && !output.ends_with(['{', '}', ';', ':', ',', '>', '~']) {
+        output.push(' ');
+    }
+    *pending_space = false;
+}
+
+/// Drops comments, indentation and blank lines from JavaScript. Line breaks are kept so
+/// automatic semicolon insertion works as before, and strings, template literals and
+/// regular expressions are copied unchanged.
+pub fn js(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut pending = None;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        pending = Some('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                pending = pending.or(Some(' '));
+            }
+            c if c.is_whitespace() => {
+                if c == '\n' || c == '\r' {
+                    pending = Some('\n');
+                } else {
+                    pending = pending.or(Some(' '));
+                }
+            }
+            c => {
+                if let Some(separator) = pending.take() {
+                    let last = output.chars().last();
+                    let needed = separator == '\n'
+                        || last.is_some_and(|last| {
+                            (is_word_char(last) && is_word_char(c))
+                                || (last == c && (c == '+' || c == '-'))
+                        });
+
+                    if needed && last.is_some() {
+                        output.push(separator);
+                    }
+                }
+
+                match c {
+                    '"' | '\'' | '`' => copy_string(c, &mut chars, &mut output),
+                    '/' if starts_regex(&output) => copy_string(c, &mut chars, &mut output),
+                    c => output.push(c),
+                }
+            }
+        }
+    }
+
+    output
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '\\' || !c.is_ascii()
+}
+
+/// Whether a `/` after `output` starts a regular expression rather than a division.
+fn starts_regex(output: &str) -> bool {
+    match output.trim_end().chars().last() {
+        None => true,
+        Some(last) => "(,=:[!&|?{};+-*%<>~^\n".contains(last),
+    }
+}
+
+/// Copies a string, template literal or regular expression starting with `quote`, which has
+/// already been read, up to and including its closing `quote`.
+fn copy_string(quote: char, chars: &mut std::iter::Peekable<std::str::Chars>, output: &mut String) {
+    output.push(quote);
+
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        output.push(c);
+
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    output.push(escaped);
+                }
+            }
+            '[' if quote == '/' => in_class = true,
+            ']' if quote == '/' => in_class = false,
+            c if c == quote && !in_class => break,
+            _ => {}
+        }
+    }
+}
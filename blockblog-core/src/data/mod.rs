@@ -0,0 +1,33 @@
+pub mod a11y;
+pub mod assets;
+pub mod block_builder;
+pub mod blocks;
+pub mod cache;
+pub mod callout;
+pub mod colors;
+pub mod components;
+pub mod config;
+pub mod data_files;
+pub mod diagrams;
+pub mod error;
+pub mod feed;
+pub mod frontmatter;
+pub mod hash;
+pub mod html;
+pub mod i18n;
+pub mod images;
+pub mod interpolate;
+pub mod links;
+pub mod manifest;
+pub mod math;
+pub mod minify;
+pub mod nav;
+pub mod plugins;
+pub mod search;
+pub mod shortcodes;
+pub mod sitemap;
+pub mod styles;
+pub mod taxonomy;
+pub mod theme;
+pub mod transforms;
+pub mod typography;
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use super::assets;
+
+/// An entry of the site menu rendered by `nav` items.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MenuEntry {
+    pub label: String,
+    /// Relative to the output root, unless it is external or starts with `/`.
+    pub url: String,
+    /// Entries shown in a dropdown under this one.
+    #[serde(default)]
+    pub children: Vec<MenuEntry>,
+}
+
+impl MenuEntry {
+    /// Whether the entry links to the page at `page_path`, relative to the output directory.
+    /// A URL ending in `/` links to the `index.html` in that directory, and one without an
+    /// extension to the `.html` file of that name or, with pretty URLs, to the `index.html`
+    /// in the directory of that name.
+    pub fn links_to(&self, page_path: &str) -> bool {
+        let url = match self.url.find(['?', '#']) {
+            Some(end) => &self.url[..end],
+            None => &self.url,
+        };
+
+        let target = match assets::resolve_url(url, "") {
+            Some(target) => target,
+            None => return false,
+        };
+
+        let target = if !url.is_empty() && !url.ends_with('/') {
+            target
+        } else if target.is_empty() {
+            "index.html".to_string()
+        } else {
+            format!("{}/index.html", target)
+        };
+
+        target == page_path
+            || format!("{}.html", target) == page_path
+            || format!("{}/index.html", target) == page_path
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    io::{Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// An external command that renders `plugin` items of its types and runs at the hooks it
+/// asks for. It is run once for each request, given as a JSON object on its stdin with a
+/// `hook` field saying what it is for:
+///
+/// - `item`, with the item's `type`, its other fields as `item` and the `page` it is on.
+///   Its stdout is the HTML of the item.
+/// - `page`, with the `page` name, its `path` relative to the output directory and its
+///   `html`. Its stdout replaces the HTML of the page.
+/// - `post_build`, with the `input` and `output` directories, once everything is written.
+///   Its stdout is ignored.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Plugin {
+    pub name: String,
+    /// Shell command, run from the input root.
+    pub command: String,
+    /// Types of the `plugin` items it renders.
+    #[serde(default)]
+    pub items: Vec<String>,
+    #[serde(default)]
+    pub hooks: Vec<PluginHook>,
+    /// Timeout in seconds.
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PluginHook {
+    /// Transforms the HTML of every page built from blocks.
+    #[serde(rename = "page")]
+    Page,
+    /// Runs after a build.
+    #[serde(rename = "post_build")]
+    PostBuild,
+}
+
+/// The plugin that renders items of `item_type`, if any.
+pub fn find_item_plugin<'a>(plugins: &'a [Plugin], item_type: &str) -> Option<&'a Plugin> {
+    plugins
+        .iter()
+        .find(|plugin| plugin.items.iter().any(|item| item == item_type))
+}
+
+/// Runs the HTML of a page through every plugin with a page hook, in order.
+pub fn transform_page(
+    plugins: &[Plugin],
+    input_root: &Path,
+    page: &str,
+    path: &Path,
+    html: String,
+) -> Result<String> {
+    let mut html = html;
+
+    for plugin in plugins
+        .iter()
+        .filter(|plugin| plugin.has_hook(PluginHook::Page))
+    {
+        html = plugin.call(
+            input_root,
+            &serde_json::json!({
+                "hook": "page",
+                "page": page,
+                "path": path,
+                "html": html,
+            }),
+        )?;
+    }
+
+    Ok(html)
+}
+
+/// Runs every plugin with a post build hook, in order.
+pub fn post_build(plugins: &[Plugin], input_root: &Path, output: &Path) -> Result<()> {
+    for plugin in plugins
+        .iter()
+        .filter(|plugin| plugin.has_hook(PluginHook::PostBuild))
+    {
+        plugin.call(
+            input_root,
+            &serde_json::json!({
+                "hook": "post_build",
+                "input": input_root,
+                "output": output,
+            }),
+        )?;
+    }
+
+    Ok(())
+}
+
+impl Plugin {
+    pub fn has_hook(&self, hook: PluginHook) -> bool {
+        self.hooks.contains(&hook)
+    }
+
+    /// Runs the plugin with `request` on its stdin, returning what it wrote to stdout.
+    pub fn call(&self, input_root: &Path, request: &serde_json::Value) -> Result<String> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .current_dir(input_root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|why| eyre!("Plugin {} could not be started: {}", self.name, why))?;
+
+        // written from its own thread so that a plugin writing before it has read everything
+        // can't block on a full pipe
+        let mut stdin = child.stdin.take().unwrap();
+        let request = serde_json::to_vec(request)?;
+        let stdin_writer = thread::spawn(move || stdin.write_all(&request));
+
+        let mut stdout = child.stdout.take().unwrap();
+        let stdout_reader = thread::spawn(move || {
+            let mut output = String::new();
+            stdout.read_to_string(&mut output).map(|_| output)
+        });
+
+        let mut stderr = child.stderr.take().unwrap();
+        let stderr_reader = thread::spawn(move || {
+            let mut output = String::new();
+            stderr.read_to_string(&mut output).ok();
+            output
+        });
+
+        let timeout = Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS));
+        let started = Instant::now();
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+
+            if started.elapsed() > timeout {
+                child.kill()?;
+                child.wait()?;
+                return Err(eyre!(
+                    "Plugin {} timed out after {}s",
+                    self.name,
+                    timeout.as_secs()
+                ));
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        // a plugin that doesn't read its request makes writing it fail, which is its business
+        stdin_writer.join().ok();
+        let stdout = stdout_reader
+            .join()
+            .map_err(|_| eyre!("Reading the output of plugin {} failed", self.name))??;
+        let stderr = stderr_reader.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(eyre!(
+                "Plugin {} failed with {}:\n{}",
+                self.name,
+                status,
+                stderr.trim_end()
+            ));
+        }
+
+        Ok(stdout)
+    }
+}
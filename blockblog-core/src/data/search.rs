@@ -0,0 +1,97 @@
+use color_eyre::Result;
+use regex::Regex;
+use serde::Serialize;
+
+use super::html;
+
+pub const SEARCH_INDEX_FILE: &str = "search_index.json";
+
+/// A page in the search index.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SearchEntry {
+    pub url: String,
+    pub title: String,
+    pub text: String,
+}
+
+pub fn render(entries: &[SearchEntry]) -> Result<String> {
+    Ok(serde_json::to_string(entries)?)
+}
+
+/// The title and the text of the body of a generated page, leaving out scripts, styles,
+/// navigation and elements hidden from screen readers, like heading anchors.
+pub fn page_text(page: &str) -> Result<(String, String)> {
+    let title_regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>")?;
+    let hidden_regex = Regex::new(
+        r#"(?is)<script\b.*?</script>|<style\b.*?</style>|<nav\b.*?</nav>|<a\b[^>]*aria-hidden="true"[^>]*>.*?</a>"#,
+    )?;
+    let tag_regex = Regex::new(r"(?s)<[^>]*>")?;
+
+    let title = title_regex
+        .captures(page)
+        .map(|caps| html::unescape(caps[1].trim()))
+        .unwrap_or_default();
+
+    let body = match (page.find("<body"), page.rfind("</body>")) {
+        (Some(start), Some(end)) if start < end => &page[start..end],
+        _ => page,
+    };
+
+    let body = hidden_regex.replace_all(body, " ");
+    let body = tag_regex.replace_all(&body, " ");
+    let text = html::unescape(&body)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok((title, text))
+}
+
+/// The search box and results list of a `search` item, with the script that searches the
+/// index at `index_url` as the visitor types.
+pub fn search_box(index_url: &str, placeholder: &str, class: &str) -> String {
+    format!(
+        r#"<div class="{}" data-index="{}">
+<input type="search" placeholder="{}" aria-label="{}" />
+<ul class="search-results" aria-live="polite"></ul>
+<script>
+(function () {{
+    var root = document.currentScript.parentElement;
+    var input = root.querySelector("input");
+    var results = root.querySelector(".search-results");
+    var index = null;
+
+    function show(query) {{
+        var terms = query.toLowerCase().split(/\s+/).filter(Boolean);
+        results.innerHTML = "";
+        if (!terms.length) return;
+
+        index.filter(function (page) {{
+            var haystack = (page.title + " " + page.text).toLowerCase();
+            return terms.every(function (term) {{ return haystack.indexOf(term) !== -1; }});
+        }}).slice(0, 10).forEach(function (page) {{
+            var item = document.createElement("li");
+            var link = document.createElement("a");
+            link.href = page.url;
+            link.textContent = page.title || page.url;
+            item.appendChild(link);
+            results.appendChild(item);
+        }});
+    }}
+
+    input.addEventListener("input", function () {{
+        if (index) return show(input.value);
+
+        fetch(root.dataset.index)
+            .then(function (response) {{ return response.json(); }})
+            .then(function (pages) {{ index = pages; show(input.value); }});
+    }});
+}})();
+</script>
+</div>"#,
+        html::escape(class),
+        html::escape(index_url),
+        html::escape(placeholder),
+        html::escape(placeholder)
+    )
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use color_eyre::Result;
+use regex::Regex;
+
+use super::html;
+
+/// Directory of the blocks shortcodes are looked up in before the rest of the site.
+pub const SHORTCODES_DIR: &str = "shortcodes";
+
+/// A `{{< name key="value" >}}` in markdown, which includes the block `shortcodes/name`, or
+/// else the block `name`, with its arguments as params.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcode {
+    pub name: String,
+    pub params: BTreeMap<String, String>,
+    /// The shortcode as it was written.
+    pub source: String,
+}
+
+impl Shortcode {
+    /// The blocks the shortcode may include, in the order they are looked up in.
+    pub fn block_names(&self) -> [String; 2] {
+        [
+            format!("{}/{}", SHORTCODES_DIR, self.name),
+            self.name.clone(),
+        ]
+    }
+}
+
+fn placeholder(index: usize) -> String {
+    format!("<!-- shortcode:{} -->", index)
+}
+
+/// Replaces the shortcodes in markdown with placeholders that make it through conversion to
+/// HTML as they are, for `expand` to replace. `\{{<` is left as a literal `{{<`, and so
+/// is anything else that isn't a valid shortcode.
+pub fn extract(markdown: &str) -> Result<(String, Vec<Shortcode>)> {
+    let shortcode_regex = Regex::new(
+        r#"(\\)?\{\{<\s*([A-Za-z0-9_\-/]+)((?:\s+[A-Za-z_][A-Za-z0-9_]*=(?:"(?:[^"\\]|\\.)*"|[^\s"]+?))*)\s*>\}\}"#,
+    )?;
+    let param_regex = Regex::new(r#"([A-Za-z_][A-Za-z0-9_]*)=(?:"((?:[^"\\]|\\.)*)"|([^\s"]+))"#)?;
+
+    let mut shortcodes = Vec::new();
+    let mut output = String::with_capacity(markdown.len());
+    let mut end = 0;
+
+    for caps in shortcode_regex.captures_iter(markdown) {
+        let whole = caps.get(0).unwrap();
+
+        output.push_str(&markdown[end..whole.start()]);
+        end = whole.end();
+
+        if caps.get(1).is_some() {
+            output.push_str(&whole.as_str()[1..]);
+            continue;
+        }
+
+        let params = param_regex
+            .captures_iter(&caps[3])
+            .map(|param| {
+                let value = match param.get(2) {
+                    Some(quoted) => unescape(quoted.as_str()),
+                    None => param[3].to_string(),
+                };
+
+                (param[1].to_string(), value)
+            })
+            .collect();
+
+        shortcodes.push(Shortcode {
+            name: caps[2].to_string(),
+            params,
+            source: whole.as_str().to_string(),
+        });
+
+        output.push_str(&placeholder(shortcodes.len() - 1));
+    }
+
+    output.push_str(&markdown[end..]);
+
+    Ok((output, shortcodes))
+}
+
+/// Replaces the placeholders `extract` left with what `render` makes of each shortcode. One
+/// that ended up in a code block or span is put back as it was written.
+pub fn expand(
+    html: &str,
+    shortcodes: &[Shortcode],
+    mut render: impl FnMut(&Shortcode) -> Result<String>,
+) -> Result<String> {
+    let mut html = html.to_string();
+
+    for (index, shortcode) in shortcodes.iter().enumerate() {
+        let placeholder = placeholder(index);
+
+        if html.contains(&placeholder) {
+            html = html.replace(&placeholder, render(shortcode)?.trim());
+        }
+
+        html = html.replace(
+            &html::escape(&placeholder),
+            &html::escape(&shortcode.source),
+        );
+    }
+
+    Ok(html)
+}
+
+fn unescape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => output.extend(chars.next()),
+            c => output.push(c),
+        }
+    }
+
+    output
+}
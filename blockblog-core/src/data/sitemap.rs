@@ -0,0 +1,42 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::html::escape;
+
+pub struct SitemapEntry {
+    pub url: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+pub fn render(entries: &[SitemapEntry]) -> String {
+    let mut output = String::new();
+
+    output.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    output.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+
+    for entry in entries {
+        output.push_str("  <url>\n");
+        output.push_str(&format!("    <loc>{}</loc>\n", escape(&entry.url)));
+        if let Some(last_modified) = entry.last_modified {
+            output.push_str(&format!(
+                "    <lastmod>{}</lastmod>\n",
+                last_modified.to_rfc3339_opts(SecondsFormat::Secs, true)
+            ));
+        }
+        output.push_str("  </url>\n");
+    }
+
+    output.push_str("</urlset>\n");
+
+    output
+}
+
+/// A robots.txt allowing everything, pointing crawlers at the sitemap if there is one.
+pub fn robots_txt(sitemap_url: Option<&str>) -> String {
+    let mut output = String::from("User-agent: *\nAllow: /\n");
+
+    if let Some(sitemap_url) = sitemap_url {
+        output.push_str(&format!("\nSitemap: {}\n", sitemap_url));
+    }
+
+    output
+}
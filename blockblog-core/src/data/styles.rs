@@ -0,0 +1,160 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Generated styles of every page, unless they are embedded or written per page.
+pub const SHARED_STYLE_FILE: &str = "generated_style.css";
+
+/// Media query of the dark variants of generated styles.
+pub const DARK_MEDIA: &str = "(prefers-color-scheme: dark)";
+
+/// Class on the root element that switches to the dark variants when they follow a toggle.
+pub const DARK_CLASS: &str = "dark";
+
+pub type StyleRule = BTreeMap<String, String>;
+
+/// What switches generated styles to their dark variants.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DarkMode {
+    /// The visitor's system preference.
+    #[default]
+    #[serde(rename = "media")]
+    Media,
+    /// The `dark` class on the root element, for sites with their own toggle.
+    #[serde(rename = "class")]
+    Class,
+}
+
+/// Generated style rules, kept in the order they were first inserted so that
+/// cascade-sensitive selectors (like the `:link`/`:visited`/`:hover`/`:active`
+/// sequence) stay in the order they were generated in.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StyleRules {
+    rules: Vec<(String, StyleRule)>,
+    /// Rules that only apply under a media query, rendered after the others.
+    #[serde(default)]
+    media: Vec<(String, StyleRules)>,
+}
+
+impl StyleRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a rule, adding its declarations to any rule already present for the
+    /// same selector.
+    pub fn insert(&mut self, selector: String, rule: StyleRule) {
+        match self
+            .rules
+            .iter_mut()
+            .find(|(existing, _)| *existing == selector)
+        {
+            Some((_, existing_rule)) => existing_rule.extend(rule),
+            None => self.rules.push((selector, rule)),
+        }
+    }
+
+    /// Inserts a rule that only applies under the media query `media`.
+    pub fn insert_media(&mut self, media: &str, selector: String, rule: StyleRule) {
+        match self
+            .media
+            .iter_mut()
+            .find(|(existing, _)| existing == media)
+        {
+            Some((_, rules)) => rules.insert(selector, rule),
+            None => {
+                let mut rules = StyleRules::new();
+                rules.insert(selector, rule);
+                self.media.push((media.to_string(), rules));
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &StyleRules) {
+        for (selector, rule) in other.iter() {
+            self.insert(selector.clone(), rule.clone());
+        }
+
+        for (media, rules) in other.media.iter() {
+            for (selector, rule) in rules.iter() {
+                self.insert_media(media, selector.clone(), rule.clone());
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, StyleRule)> {
+        self.rules.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.media.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.media.clear();
+    }
+
+    /// Renders the rules as CSS. Neighbouring rules with the same declarations, like the
+    /// `:hover` and `:active` rules of a link style, are written once for both selectors,
+    /// which keeps the cascade as it is.
+    pub fn render(&self) -> String {
+        let mut groups: Vec<(Vec<&str>, &StyleRule)> = Vec::new();
+
+        for (selector, rule) in self.iter() {
+            match groups.last_mut() {
+                Some((selectors, last)) if *last == rule => selectors.push(selector),
+                _ => groups.push((vec![selector], rule)),
+            }
+        }
+
+        let mut output = String::new();
+
+        for (selectors, rule) in groups {
+            output.push_str(&format!("{} {{\n", selectors.join(", ")));
+
+            for (key, value) in rule.iter() {
+                output.push_str(&format!("\t{}: {};\n", key, value));
+            }
+
+            output.push_str("}\n\n");
+        }
+
+        for (media, rules) in self.media.iter() {
+            output.push_str(&format!("@media {} {{\n", media));
+
+            for line in rules.render().trim_end().lines() {
+                match line {
+                    "" => output.push('\n'),
+                    line => output.push_str(&format!("\t{}\n", line)),
+                }
+            }
+
+            output.push_str("}\n\n");
+        }
+
+        output
+    }
+}
+
+/// The selector of the dark variant of a rule when it follows the `dark` class on the root
+/// element instead of the system preference.
+pub fn dark_class_selector(selector: &str) -> String {
+    selector
+        .split(',')
+        .map(|selector| match selector.trim() {
+            ":root" => format!(":root.{}", DARK_CLASS),
+            selector => format!(":root.{} {}", DARK_CLASS, selector),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Output path of the generated styles of a page when each page gets its own, next to the
+/// page.
+pub fn page_style_path(page_path: &Path) -> PathBuf {
+    page_path.with_extension("generated.css")
+}
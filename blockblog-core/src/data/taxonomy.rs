@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::frontmatter::{self, Frontmatter};
+
+/// Tag pages built from the `tags` frontmatter of the markdown files matching `pattern`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TagsConfig {
+    #[serde(rename = "match", default = "default_pattern")]
+    pub pattern: String,
+    /// Block constructed once for every tag, with the tag available as `$tag`.
+    pub template: String,
+    /// Block constructed as the index page of the tags directory.
+    pub index_template: Option<String>,
+    /// Directory, relative to the output directory, the tag pages are written to.
+    #[serde(default = "default_output")]
+    pub output: PathBuf,
+}
+
+/// Posts related to a markdown file by the frontmatter values they share, available as
+/// `$page.related`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelatedConfig {
+    #[serde(rename = "match", default = "default_pattern")]
+    pub pattern: String,
+    /// How many related posts to list at most.
+    #[serde(default = "default_related_count")]
+    pub count: usize,
+    /// Frontmatter fields, each a list or a single value, whose shared values make posts
+    /// related.
+    #[serde(default = "default_related_fields")]
+    pub fields: Vec<String>,
+}
+
+fn default_related_count() -> usize {
+    5
+}
+
+fn default_related_fields() -> Vec<String> {
+    vec!["tags".to_string()]
+}
+
+impl RelatedConfig {
+    /// How many values of the related fields two pages share, ignoring case.
+    pub fn score(&self, page: &Frontmatter, other: &Frontmatter) -> usize {
+        self.fields
+            .iter()
+            .map(|field| {
+                let values = field_values(page, field);
+
+                field_values(other, field)
+                    .iter()
+                    .filter(|value| values.contains(value))
+                    .count()
+            })
+            .sum()
+    }
+}
+
+/// The lowercased values of a frontmatter field holding a list or a single value.
+fn field_values(page: &Frontmatter, field: &str) -> Vec<String> {
+    let mut values = match page.value(field) {
+        Some(serde_yaml::Value::Sequence(values)) => values
+            .iter()
+            .map(|value| frontmatter::value_to_string(value).to_lowercase())
+            .collect::<Vec<_>>(),
+        Some(value) => vec![frontmatter::value_to_string(&value).to_lowercase()],
+        None => Vec::new(),
+    };
+
+    values.retain(|value| !value.is_empty());
+    values.sort();
+    values.dedup();
+    values
+}
+
+fn default_pattern() -> String {
+    "**/*.md".to_string()
+}
+
+fn default_output() -> PathBuf {
+    PathBuf::from("tags")
+}
+
+impl TagsConfig {
+    /// Block name of the page for a tag, which is also its path in the output directory
+    /// without the `.html` extension.
+    pub fn page_name(&self, slug: &str) -> String {
+        format!("{}/{}", self.output_dir(), slug)
+    }
+
+    pub fn index_page_name(&self) -> String {
+        format!("{}/index", self.output_dir())
+    }
+
+    fn output_dir(&self) -> String {
+        self.output
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub slug: String,
+    /// Number of files tagged with it.
+    pub count: usize,
+}
+
+/// Turns a tag into a file name: lowercase, with whitespace as `-` and anything else that
+/// isn't alphanumeric, `-` or `_` left out.
+pub fn slug(tag: &str) -> String {
+    tag.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::colors::Color;
+use super::styles::StyleRule;
+
+/// Colors, fonts and spacing shared by the whole site. Each is available as `$theme.name`
+/// and exported as a CSS custom property in the generated styles.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Theme {
+    /// Exported as `--color-<name>`.
+    pub colors: BTreeMap<String, Color>,
+    /// Font stacks, exported as `--font-<name>`.
+    pub fonts: BTreeMap<String, String>,
+    /// CSS lengths, exported as `--spacing-<name>`.
+    pub spacing: BTreeMap<String, String>,
+    /// Dark variants of some of the colors. `$theme.name` is always the light color, so
+    /// styles that should follow dark mode use `var(--color-<name>)`.
+    pub dark: BTreeMap<String, Color>,
+}
+
+impl Theme {
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+            && self.fonts.is_empty()
+            && self.spacing.is_empty()
+            && self.dark.is_empty()
+    }
+
+    /// Looks up a `$theme.name` value in the colors, fonts and spacing, in that order.
+    pub fn value(&self, name: &str) -> Option<String> {
+        self.colors
+            .get(name)
+            .map(Color::to_css)
+            .or_else(|| self.fonts.get(name).cloned())
+            .or_else(|| self.spacing.get(name).cloned())
+    }
+
+    /// The custom properties of the theme, for a `:root` rule.
+    pub fn variables(&self) -> StyleRule {
+        let colors = self
+            .colors
+            .iter()
+            .map(|(name, color)| (format!("--color-{}", name), color.to_css()));
+        let fonts = self
+            .fonts
+            .iter()
+            .map(|(name, font)| (format!("--font-{}", name), font.clone()));
+        let spacing = self
+            .spacing
+            .iter()
+            .map(|(name, length)| (format!("--spacing-{}", name), length.clone()));
+
+        colors.chain(fonts).chain(spacing).collect()
+    }
+
+    /// The custom properties overridden in dark mode.
+    pub fn dark_variables(&self) -> StyleRule {
+        self.dark
+            .iter()
+            .map(|(name, color)| (format!("--color-{}", name), color.to_css()))
+            .collect()
+    }
+
+    /// Replaces the strings in a parsed block definition that are a whole `$theme.name`
+    /// reference with the value, so theme colors can be used where a color is parsed, like
+    /// in link styles. Returns whether anything was replaced.
+    pub fn resolve_references(&self, value: &mut serde_yaml::Value) -> bool {
+        match value {
+            serde_yaml::Value::String(text) => {
+                let resolved = text
+                    .trim()
+                    .strip_prefix("$theme.")
+                    .and_then(|name| self.value(name));
+
+                match resolved {
+                    Some(resolved) => {
+                        *text = resolved;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            serde_yaml::Value::Sequence(values) => {
+                let mut changed = false;
+                for value in values {
+                    changed |= self.resolve_references(value);
+                }
+                changed
+            }
+            serde_yaml::Value::Mapping(map) => {
+                let mut changed = false;
+                for (_, value) in map.iter_mut() {
+                    changed |= self.resolve_references(value);
+                }
+                changed
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    io::Read,
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// An external command that asset files matching `pattern` are piped through instead of
+/// being copied verbatim.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Transform {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    /// Shell command with `{input}` and `{output}` placeholders, run from the input root.
+    pub command: String,
+    pub output_ext: Option<String>,
+    /// Timeout in seconds.
+    pub timeout: Option<u64>,
+}
+
+impl Transform {
+    pub fn matches(&self, relative_path: &Path) -> Result<bool> {
+        Ok(glob::Pattern::new(&self.pattern)?.matches_path(relative_path))
+    }
+
+    pub fn output_file_name(&self, file_name: &str) -> String {
+        match &self.output_ext {
+            Some(ext) => Path::new(file_name)
+                .with_extension(ext)
+                .to_string_lossy()
+                .to_string(),
+            None => file_name.to_string(),
+        }
+    }
+
+    pub fn run(&self, input_root: &Path, input: &Path, output: &Path) -> Result<()> {
+        let command = self
+            .command
+            .replace("{input}", &shell_quote(input))
+            .replace("{output}", &shell_quote(output));
+
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .current_dir(input_root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let mut stderr = child.stderr.take().unwrap();
+        let stderr_reader = thread::spawn(move || {
+            let mut output = String::new();
+            stderr.read_to_string(&mut output).ok();
+            output
+        });
+
+        let timeout = Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS));
+        let started = Instant::now();
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+
+            if started.elapsed() > timeout {
+                child.kill()?;
+                child.wait()?;
+                return Err(eyre!(
+                    "Transform `{}` timed out after {}s",
+                    command,
+                    timeout.as_secs()
+                ));
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        let stderr = stderr_reader.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(eyre!(
+                "Transform `{}` failed with {}:\n{}",
+                command,
+                status,
+                stderr.trim_end()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The first of `transforms` matching a path relative to the input root.
+pub fn find<'a>(
+    transforms: &'a [Transform],
+    relative_path: &Path,
+) -> Result<Option<&'a Transform>> {
+    for transform in transforms {
+        if transform.matches(relative_path)? {
+            return Ok(Some(transform));
+        }
+    }
+
+    Ok(None)
+}
+
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "'\\''"))
+}
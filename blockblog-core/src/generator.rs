@@ -0,0 +1,2120 @@
+use color_eyre::{eyre::eyre, Result};
+use colored::*;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    fs::{read_dir, File},
+    io::Write,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use log::{error, info, warn};
+
+use crate::data::{
+    a11y::{A11yConfig, A11yFinding},
+    assets::{self, AssetMap, ASSET_MANIFEST_FILE},
+    block_builder::{BlockBuilder, BlockBuilderConfig, Page},
+    cache::{Cache, ParsedMarkdown},
+    components::COMPONENTS_DIR,
+    config::{SiteConfig, SITE_CONFIG_FILE},
+    data_files, error,
+    feed::{self, FeedEntry, FeedInfo},
+    frontmatter::Frontmatter,
+    html,
+    i18n::I18nConfig,
+    manifest::{
+        self, BuildManifest, BuiltPage, BuiltPages, InputHashes, ManifestOptions,
+        BUILD_MANIFEST_FILE,
+    },
+    minify, plugins,
+    search::{self, SearchEntry, SEARCH_INDEX_FILE},
+    sitemap::{self, SitemapEntry},
+    styles::{self, StyleRules, SHARED_STYLE_FILE},
+    transforms::{self, Transform},
+};
+use crate::watch;
+
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub safe: bool,
+    pub debug: bool,
+    pub inline_generated_css: bool,
+    pub css_vars: bool,
+    pub a11y: Option<A11yConfig>,
+    pub only: Vec<String>,
+    pub no_assets: bool,
+    pub drafts: bool,
+    /// Include markdown files dated in the future.
+    pub future: bool,
+    pub minify: bool,
+    /// Remove files in the output directory that the build no longer produces.
+    pub prune: bool,
+    /// Write every page that can be built instead of stopping at the first that fails,
+    /// then fail listing all the ones that did.
+    pub keep_going: bool,
+    /// Use pretty URLs even if the site config doesn't.
+    pub pretty_urls: bool,
+    /// Overrides the `base_url` of the site config, like for a preview deployed elsewhere.
+    pub base_url: Option<String>,
+    /// Profile of the site config merged into it. Its build options are turned on by
+    /// `with_profile`.
+    pub profile: Option<String>,
+}
+
+impl GenerateOptions {
+    pub fn new(input: PathBuf, output: PathBuf) -> Self {
+        Self {
+            input,
+            output,
+            safe: false,
+            debug: false,
+            inline_generated_css: false,
+            css_vars: false,
+            a11y: None,
+            only: Vec::new(),
+            no_assets: false,
+            drafts: false,
+            future: false,
+            minify: false,
+            prune: false,
+            keep_going: false,
+            pretty_urls: false,
+            base_url: None,
+            profile: None,
+        }
+    }
+
+    /// Turns on the build options the profile turns on, besides the ones already on.
+    pub fn with_profile(mut self) -> Result<Self> {
+        let profile = match &self.profile {
+            Some(profile) => profile,
+            None => return Ok(self),
+        };
+
+        let site_config = SiteConfig::load_profile(&self.input, Some(profile))?;
+        let options = &site_config.profiles[profile];
+
+        self.drafts |= options.drafts;
+        self.future |= options.future;
+        self.debug |= options.debug;
+        self.minify |= options.minify;
+
+        Ok(self)
+    }
+}
+
+/// Generates the site. If the output directory has a manifest from a previous build with
+/// the same options, only what the changed input files affect is built again.
+pub fn generate(options: &GenerateOptions) -> Result<()> {
+    build_incremental(options)?;
+    Ok(())
+}
+
+/// Loads the site config of the input directory, ignoring the output directory in it.
+pub(crate) fn load_site_config(options: &GenerateOptions) -> Result<SiteConfig> {
+    let mut site_config = SiteConfig::load_profile(&options.input, options.profile.as_deref())?;
+    site_config.ignore_output(&options.input, &options.output);
+    site_config.pretty_urls |= options.pretty_urls;
+
+    if let Some(base_url) = &options.base_url {
+        site_config.base_url = Some(base_url.clone());
+    }
+
+    Ok(site_config)
+}
+
+/// Partial (`--only`) and safe mode builds do not overwrite everything that changed, and
+/// accessibility checks have to see every page, so those always build from scratch.
+fn uses_manifest(options: &GenerateOptions) -> bool {
+    options.only.is_empty() && !options.safe && options.a11y.is_none()
+}
+
+fn manifest_options(options: &GenerateOptions) -> ManifestOptions {
+    ManifestOptions {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        debug: options.debug,
+        inline_generated_css: options.inline_generated_css,
+        css_vars: options.css_vars,
+        drafts: options.drafts,
+        future: options.future,
+        no_assets: options.no_assets,
+        minify: options.minify,
+        pretty_urls: options.pretty_urls,
+        base_url: options.base_url.clone(),
+        profile: options.profile.clone(),
+    }
+}
+
+fn build_incremental(options: &GenerateOptions) -> Result<BuiltPages> {
+    if !uses_manifest(options) {
+        let built = build(options)?;
+
+        if options.prune {
+            let site_config = load_site_config(options)?;
+            let previous = manifest::previous_hashes(&options.output);
+            let inputs = manifest::hash_inputs(
+                &options.input,
+                &options.output,
+                &site_config,
+                previous.as_ref(),
+            )?;
+            prune(options, &site_config, &inputs.hashes, &built)?;
+        }
+
+        return Ok(built);
+    }
+
+    let site_config = load_site_config(options)?;
+    let manifest = BuildManifest::load(&options.output);
+    let inputs = manifest::hash_inputs(
+        &options.input,
+        &options.output,
+        &site_config,
+        manifest.as_ref().map(|manifest| &manifest.inputs),
+    )?;
+    let manifest_options = manifest_options(options);
+
+    // pruning needs to know every output, so everything is built again
+    let previous =
+        manifest.filter(|manifest| manifest.options == manifest_options && !options.prune);
+
+    let built = match previous {
+        Some(manifest) => {
+            let changed = manifest.changed_inputs(&inputs);
+            let mut built = manifest.pages;
+
+            if changed.is_empty() {
+                info!("Nothing changed since the last build");
+            } else {
+                rebuild(options, &mut built, &changed)?;
+            }
+
+            built
+        }
+        None => build(options)?,
+    };
+
+    if options.prune {
+        prune(options, &site_config, &inputs.hashes, &built)?;
+    }
+
+    BuildManifest {
+        options: manifest_options,
+        inputs,
+        pages: built.clone(),
+    }
+    .write(&options.output)?;
+
+    Ok(built)
+}
+
+/// Generates the site, then keeps rebuilding the parts affected by each change to the input
+/// directory. Only returns if watching fails.
+pub fn watch(options: &GenerateOptions) -> Result<()> {
+    let mut built = match build_incremental(options) {
+        Ok(built) => Some(built),
+        Err(e) => {
+            error!("{:#}", e);
+            None
+        }
+    };
+
+    info!(
+        "Watching {} for changes...",
+        options.input.to_string_lossy().cyan().bold()
+    );
+
+    watch::watch(
+        &options.input,
+        std::slice::from_ref(&options.output),
+        |changed| {
+            let result = match built.as_mut() {
+                Some(built) => watch_rebuild(options, built, &changed),
+                None => build_incremental(options).map(|new| built = Some(new)),
+            };
+
+            if let Err(e) = result {
+                error!("{:#}", e);
+            }
+        },
+    )
+}
+
+fn watch_rebuild(
+    options: &GenerateOptions,
+    built: &mut BuiltPages,
+    changed: &[PathBuf],
+) -> Result<()> {
+    let input_root = options.input.canonicalize()?;
+    let changed = changed
+        .iter()
+        .filter_map(|path| path.strip_prefix(&input_root).ok())
+        .map(Path::to_owned)
+        .collect::<Vec<_>>();
+
+    rebuild(options, built, &changed)?;
+
+    if uses_manifest(options) {
+        BuildManifest {
+            options: manifest_options(options),
+            inputs: manifest::hash_inputs(
+                &options.input,
+                &options.output,
+                &load_site_config(options)?,
+                manifest::previous_hashes(&options.output).as_ref(),
+            )?,
+            pages: built.clone(),
+        }
+        .write(&options.output)?;
+    }
+
+    Ok(())
+}
+
+fn build(options: &GenerateOptions) -> Result<BuiltPages> {
+    let GenerateOptions {
+        input,
+        output,
+        safe,
+        inline_generated_css,
+        only,
+        no_assets,
+        minify,
+        ..
+    } = options;
+    let (safe, inline_generated_css, no_assets, minify) =
+        (*safe, *inline_generated_css, *no_assets, *minify);
+
+    let site_config = load_site_config(options)?;
+
+    // the previous build only tells which transform outputs are still current if it was
+    // built the same way
+    let previous = BuildManifest::load(output)
+        .filter(|manifest| manifest.options == manifest_options(options))
+        .map(|manifest| manifest.inputs);
+    let inputs = if site_config.fingerprint_assets || !site_config.transforms.is_empty() {
+        manifest::hash_inputs(input, output, &site_config, previous.as_ref())?
+    } else {
+        InputHashes::default()
+    };
+    let unchanged_inputs = unchanged_inputs(previous.as_ref(), &inputs);
+
+    let asset_map = if site_config.fingerprint_assets {
+        AssetMap::fingerprint(input, &site_config, &inputs.hashes)?
+    } else {
+        AssetMap::default()
+    };
+    let mut block_builder = new_block_builder(options, &site_config, &asset_map)?;
+
+    let markdown_outputs =
+        plan_markdown_outputs(input, &site_config, options, &mut block_builder.cache)?;
+
+    let pages = if only.is_empty() {
+        block_builder.construct_all()?
+    } else {
+        let block_names = block_builder.block_names_matching(only)?;
+        block_builder.construct_pages(block_names)
+    };
+
+    if !no_assets {
+        check_page_collisions(&markdown_outputs, page_outputs(&pages))?;
+    }
+
+    if no_assets {
+        info!("Skipping asset files");
+    } else {
+        build_asset_files(
+            input,
+            output,
+            &AssetOptions {
+                input_root: input,
+                safe,
+                minify,
+                asset_map: &asset_map,
+                site_config: &site_config,
+                unchanged_inputs: &unchanged_inputs,
+            },
+        )?;
+
+        if site_config.fingerprint_assets {
+            asset_map.write_manifest(output)?;
+        }
+
+        for markdown_output in markdown_outputs.iter() {
+            generate_html_from_md(markdown_output, output, safe, minify, &site_config)?;
+        }
+
+        write_feeds(input, output, &site_config, &markdown_outputs, safe)?;
+    }
+
+    let mut built = BuiltPages::new();
+    let mut failures = Vec::new();
+    let a11y_findings = write_pages(&block_builder, pages, options, &mut built, &mut failures)?;
+
+    if !only.is_empty() {
+        // the styles of the pages that were not built are unknown, so writing the
+        // merged styles would drop them
+        info!("Not updating generated styles because only some blocks were built");
+    } else {
+        if !inline_generated_css && !site_config.page_styles {
+            write_generated_styles(output, &merged_styles(&built), safe, minify)?;
+        }
+
+        let markdown_outputs = if no_assets {
+            &[][..]
+        } else {
+            &markdown_outputs[..]
+        };
+        write_sitemap(options, &site_config, &built, markdown_outputs)?;
+        write_search_index(options, &site_config, &built, markdown_outputs)?;
+    }
+
+    if let Some(a11y) = &block_builder.config.a11y {
+        report_a11y_findings(&a11y_findings, a11y.strict)?;
+    }
+
+    report_failures(&failures)?;
+    plugins::post_build(&site_config.plugins, input, output)?;
+
+    info!("{}", "Done!".green().bold());
+    Ok(built)
+}
+
+/// Rebuilds only what the changed files can affect: changed assets are copied again,
+/// markdown files are converted again and only the pages that use a changed block, or loop
+/// over files matching a changed path, are constructed again. Changes to the site config,
+/// to component sets or to fingerprinted assets rebuild everything. `changed` is relative to
+/// the input directory.
+fn rebuild(options: &GenerateOptions, built: &mut BuiltPages, changed: &[PathBuf]) -> Result<()> {
+    let site_config = load_site_config(options)?;
+
+    let changed = changed
+        .iter()
+        .filter(|path| !site_config.is_ignored(path))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for path in changed.iter() {
+        info!("Changed: {}", path.to_string_lossy().yellow());
+    }
+
+    let is_asset = |path: &&PathBuf| is_asset(&options.input, path);
+
+    if changed
+        .iter()
+        .any(|path| path == Path::new(SITE_CONFIG_FILE) || path.starts_with(COMPONENTS_DIR))
+        || (site_config.fingerprint_assets && changed.iter().any(|path| is_asset(&path)))
+    {
+        *built = build(options)?;
+        return Ok(());
+    }
+
+    let asset_map = load_asset_map(options, &site_config)?;
+    let mut block_builder = new_block_builder(options, &site_config, &asset_map)?;
+
+    if !options.no_assets {
+        let asset_options = AssetOptions {
+            input_root: &options.input,
+            safe: options.safe,
+            minify: options.minify,
+            asset_map: &asset_map,
+            site_config: &site_config,
+            unchanged_inputs: &BTreeSet::new(),
+        };
+
+        for path in changed.iter().filter(is_asset) {
+            let source = options.input.join(path);
+
+            if source.is_file() {
+                let output_dir = match path.parent() {
+                    Some(parent) => options.output.join(parent),
+                    None => options.output.clone(),
+                };
+
+                std::fs::create_dir_all(&output_dir)?;
+                build_asset_file(&source, &output_dir, &asset_options)?;
+            }
+        }
+    }
+
+    let markdown_outputs = if options.no_assets {
+        Vec::new()
+    } else {
+        plan_markdown_outputs(
+            &options.input,
+            &site_config,
+            options,
+            &mut block_builder.cache,
+        )?
+    };
+
+    let changed_blocks = changed
+        .iter()
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "yml") && !data_files::is_data_file(path)
+        })
+        .map(|path| {
+            path.with_extension("")
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .collect::<Vec<_>>();
+
+    let mut removed_pages = false;
+
+    for block_name in changed_blocks.iter() {
+        if block_builder.block_items.contains_key(block_name) {
+            continue;
+        }
+
+        if let Some(page) = built.remove(block_name) {
+            removed_pages = true;
+            let block_file = options.output.join(page.path(block_name));
+
+            if block_file.exists() {
+                info!("Removing block file {}", block_name.yellow().bold());
+                std::fs::remove_file(block_file)?;
+            }
+        }
+    }
+
+    let candidates = if options.only.is_empty() {
+        block_builder.page_names()?
+    } else {
+        block_builder.block_names_matching(&options.only)?
+    };
+
+    let mut block_names = candidates
+        .into_iter()
+        .filter(|block_name| match built.get(block_name) {
+            Some(page) => page.dependencies.affected_by(&changed_blocks, &changed),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+    block_names.sort();
+
+    let pages = block_builder.construct_pages(block_names);
+
+    if !options.no_assets {
+        let kept_outputs = built
+            .iter()
+            .filter(|(block_name, _)| !pages.iter().any(|(rebuilt, _)| rebuilt == *block_name))
+            .flat_map(|(block_name, page)| {
+                std::iter::once(page.path(block_name))
+                    .chain(page.outputs.iter().cloned())
+                    .map(|path| (block_name.clone(), path))
+            });
+
+        check_page_collisions(&markdown_outputs, page_outputs(&pages).chain(kept_outputs))?;
+    }
+
+    if !options.no_assets
+        && changed
+            .iter()
+            .any(|path| path.extension().is_some_and(|ext| ext == "md"))
+    {
+        for markdown_output in markdown_outputs.iter() {
+            generate_html_from_md(
+                markdown_output,
+                &options.output,
+                options.safe,
+                options.minify,
+                &site_config,
+            )?;
+        }
+
+        write_feeds(
+            &options.input,
+            &options.output,
+            &site_config,
+            &markdown_outputs,
+            options.safe,
+        )?;
+    }
+
+    let mut failures = Vec::new();
+    let mut a11y_findings = Vec::new();
+    let pages_changed = !pages.is_empty() || removed_pages;
+
+    if pages.is_empty() {
+        info!("No blocks affected");
+    } else {
+        a11y_findings = write_pages(&block_builder, pages, options, built, &mut failures)?;
+    }
+
+    // the rules only a removed page used go away with it
+    if pages_changed
+        && options.only.is_empty()
+        && !options.inline_generated_css
+        && !site_config.page_styles
+    {
+        write_generated_styles(
+            &options.output,
+            &merged_styles(built),
+            options.safe,
+            options.minify,
+        )?;
+    }
+
+    if let Some(a11y) = &block_builder.config.a11y {
+        report_a11y_findings(&a11y_findings, a11y.strict)?;
+    }
+
+    if options.only.is_empty() {
+        write_sitemap(options, &site_config, built, &markdown_outputs)?;
+        write_search_index(options, &site_config, built, &markdown_outputs)?;
+    }
+
+    report_failures(&failures)?;
+    plugins::post_build(&site_config.plugins, &options.input, &options.output)?;
+
+    info!("{}", "Done!".green().bold());
+    Ok(())
+}
+
+/// Whether a file, relative to the input directory, is copied into the output directory
+/// rather than being a block definition, markdown or data file.
+fn is_asset(input: &Path, path: &Path) -> bool {
+    !path
+        .extension()
+        .is_some_and(|ext| ext == "yml" || ext == "md")
+        && !data_files::is_data_file(path)
+        && !input.join(path).is_dir()
+}
+
+/// Removes every file in the output directory that the build just done didn't produce,
+/// along with the directories that leaves empty.
+fn prune(
+    options: &GenerateOptions,
+    site_config: &SiteConfig,
+    inputs: &BTreeMap<String, String>,
+    built: &BuiltPages,
+) -> Result<()> {
+    if !options.only.is_empty() || options.no_assets || options.safe {
+        return Err(eyre!(
+            "Pruning needs a full build, without --only, --no-assets or --safe"
+        ));
+    }
+
+    check_output_dir(options)?;
+
+    let mut expected = BTreeSet::from([PathBuf::from(BUILD_MANIFEST_FILE)]);
+
+    if !options.inline_generated_css && !site_config.page_styles {
+        expected.insert(PathBuf::from(SHARED_STYLE_FILE));
+    }
+
+    let asset_map = load_asset_map(options, site_config)?;
+
+    if site_config.fingerprint_assets {
+        expected.insert(PathBuf::from(ASSET_MANIFEST_FILE));
+    }
+
+    let asset_options = AssetOptions {
+        input_root: &options.input,
+        safe: options.safe,
+        minify: options.minify,
+        asset_map: &asset_map,
+        site_config,
+        unchanged_inputs: &BTreeSet::new(),
+    };
+
+    for path in inputs.keys().map(Path::new) {
+        if is_asset(&options.input, path) {
+            let output_name = asset_output_name(&options.input.join(path), &asset_options)?;
+            expected.insert(path.with_file_name(output_name));
+        }
+    }
+
+    let markdown_outputs = plan_markdown_outputs(
+        &options.input,
+        site_config,
+        options,
+        &mut Cache::new(site_config.typography),
+    )?;
+    expected.extend(markdown_outputs.into_iter().map(|output| output.output));
+
+    expected.extend(site_config.feeds.iter().map(|feed| feed.output.clone()));
+
+    if site_config.sitemap != Some(false) && site_config.base_url.is_some() {
+        expected.insert(PathBuf::from("sitemap.xml"));
+    }
+
+    if site_config.robots {
+        expected.insert(PathBuf::from("robots.txt"));
+    }
+
+    if site_config.search_index {
+        expected.insert(PathBuf::from(SEARCH_INDEX_FILE));
+    }
+
+    for page in built.values() {
+        expected.extend(page.outputs.iter().cloned());
+    }
+
+    prune_dir(&options.output, &options.output, &expected)?;
+
+    Ok(())
+}
+
+/// Removes the files under `dir` not in `expected`, returning whether `dir` is left empty.
+fn prune_dir(dir: &Path, output: &Path, expected: &BTreeSet<PathBuf>) -> Result<bool> {
+    let mut is_empty = true;
+
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        let relative = path.strip_prefix(output)?;
+
+        if path.is_dir() && !path.is_symlink() {
+            if prune_dir(&path, output, expected)? {
+                std::fs::remove_dir(&path)?;
+            } else {
+                is_empty = false;
+            }
+        } else if expected.contains(relative) {
+            is_empty = false;
+        } else {
+            info!(
+                "Removing stale file {}",
+                relative.to_string_lossy().yellow().bold()
+            );
+            std::fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(is_empty)
+}
+
+/// Deletes everything in the output directory.
+pub fn clean(options: &GenerateOptions) -> Result<()> {
+    if !options.output.exists() {
+        info!("Nothing to clean");
+        return Ok(());
+    }
+
+    check_output_dir(options)?;
+
+    for entry in read_dir(&options.output)? {
+        let path = entry?.path();
+
+        if path.is_dir() && !path.is_symlink() {
+            std::fs::remove_dir_all(&path)?;
+        } else {
+            std::fs::remove_file(&path)?;
+        }
+    }
+
+    info!("Cleaned {}", options.output.to_string_lossy().cyan().bold());
+
+    Ok(())
+}
+
+/// Refuses to delete files from an output directory the input directory is in.
+fn check_output_dir(options: &GenerateOptions) -> Result<()> {
+    let input = options.input.canonicalize()?;
+    let output = options.output.canonicalize()?;
+
+    if input.starts_with(&output) {
+        return Err(eyre!(
+            "Not deleting anything from {}, it contains the input directory",
+            options.output.to_string_lossy()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Writes sitemap.xml and robots.txt if the site config asks for them. Each page is dated
+/// by the newest of the files it was constructed from.
+fn write_sitemap(
+    options: &GenerateOptions,
+    site_config: &SiteConfig,
+    built: &BuiltPages,
+    markdown_outputs: &[MarkdownOutput],
+) -> Result<()> {
+    let sitemap_file = Path::new("sitemap.xml");
+
+    let sitemap_url = match (site_config.sitemap, site_config.absolute_url(sitemap_file)) {
+        (Some(false), _) | (None, None) => None,
+        (Some(true), None) => {
+            return Err(eyre!(
+                "The sitemap needs base_url to be set in {}",
+                SITE_CONFIG_FILE
+            ))
+        }
+        (_, Some(sitemap_url)) => {
+            let mut entries = Vec::new();
+
+            for (block_name, page) in built.iter() {
+                let sources = page
+                    .dependencies
+                    .blocks
+                    .iter()
+                    .map(|block| options.input.join(format!("{}.yml", block)))
+                    .chain(
+                        page.dependencies
+                            .files
+                            .iter()
+                            .map(|file| options.input.join(file)),
+                    );
+
+                entries.push(SitemapEntry {
+                    url: site_config
+                        .absolute_url(&page.path(block_name))
+                        .map(|url| site_config.page_url(url))
+                        .unwrap_or_default(),
+                    last_modified: newest_modified(sources),
+                });
+            }
+
+            for markdown_output in markdown_outputs {
+                entries.push(SitemapEntry {
+                    url: site_config
+                        .absolute_url(&markdown_output.output)
+                        .map(|url| site_config.page_url(url))
+                        .unwrap_or_default(),
+                    last_modified: newest_modified(std::iter::once(markdown_output.source.clone())),
+                });
+            }
+
+            entries.sort_by(|a, b| a.url.cmp(&b.url));
+
+            write_generated_file(
+                &options.output,
+                sitemap_file,
+                &sitemap::render(&entries),
+                options.safe,
+            )?;
+
+            Some(sitemap_url)
+        }
+    };
+
+    if site_config.robots {
+        write_generated_file(
+            &options.output,
+            Path::new("robots.txt"),
+            &sitemap::robots_txt(sitemap_url.as_deref()),
+            options.safe,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes search_index.json if the site config asks for it, from the pages as they were
+/// written, so pages an incremental build left alone are still indexed.
+fn write_search_index(
+    options: &GenerateOptions,
+    site_config: &SiteConfig,
+    built: &BuiltPages,
+    markdown_outputs: &[MarkdownOutput],
+) -> Result<()> {
+    if !site_config.search_index {
+        return Ok(());
+    }
+
+    let pages = built
+        .iter()
+        .map(|(block_name, page)| page.path(block_name))
+        .chain(markdown_outputs.iter().map(|output| output.output.clone()));
+
+    let mut entries = Vec::new();
+
+    for path in pages {
+        // pages skipped in safe mode or by --only may not be there
+        let contents = match std::fs::read_to_string(options.output.join(&path)) {
+            Ok(contents) => contents,
+            Err(_) => continue,
+        };
+
+        let (title, text) = search::page_text(&contents)?;
+        let path = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        entries.push(SearchEntry {
+            url: site_config.page_url(format!("{}/{}", site_config.base_path(), path)),
+            title,
+            text,
+        });
+    }
+
+    entries.sort_by(|a, b| a.url.cmp(&b.url));
+
+    write_generated_file(
+        &options.output,
+        Path::new(SEARCH_INDEX_FILE),
+        &search::render(&entries)?,
+        options.safe,
+    )
+}
+
+fn newest_modified(paths: impl Iterator<Item = PathBuf>) -> Option<chrono::DateTime<chrono::Utc>> {
+    paths
+        .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+        .map(chrono::DateTime::from)
+}
+
+/// Fingerprints the assets if the site config asks for it, reusing the hashes of the
+/// previous build for the files that didn't change since.
+pub(crate) fn load_asset_map(
+    options: &GenerateOptions,
+    site_config: &SiteConfig,
+) -> Result<AssetMap> {
+    if !site_config.fingerprint_assets {
+        return Ok(AssetMap::default());
+    }
+
+    let inputs = manifest::hash_inputs(
+        &options.input,
+        &options.output,
+        site_config,
+        manifest::previous_hashes(&options.output).as_ref(),
+    )?;
+
+    AssetMap::fingerprint(&options.input, site_config, &inputs.hashes)
+}
+
+pub(crate) fn new_block_builder<'a>(
+    options: &GenerateOptions,
+    site_config: &'a SiteConfig,
+    asset_map: &AssetMap,
+) -> Result<BlockBuilder<'a>> {
+    let defaults = BlockBuilderConfig::new(options.input.to_owned(), options.output.to_owned());
+
+    BlockBuilder::new(BlockBuilderConfig {
+        // the minifier would strip the indentation again
+        indent_string: if options.minify {
+            ""
+        } else {
+            site_config
+                .indent
+                .as_deref()
+                .unwrap_or(defaults.indent_string)
+        },
+        debug: options.debug,
+        inline_generated_css: options.inline_generated_css,
+        css_vars: options.css_vars,
+        a11y: options.a11y.clone(),
+        asset_map: asset_map.clone(),
+        drafts: options.drafts,
+        future: options.future,
+        site_config: site_config.clone(),
+        profile: options.profile.clone(),
+        ..defaults
+    })
+}
+
+fn merged_styles(built: &BuiltPages) -> StyleRules {
+    let mut generated_styles = StyleRules::new();
+
+    for page in built.values() {
+        generated_styles.merge(&page.generated_styles);
+    }
+
+    generated_styles
+}
+
+/// Writes constructed pages to the output directory, recording what each one was built
+/// from in `built`, and returns their accessibility findings. With `keep_going`, pages that
+/// failed to construct are added to `failures` instead of stopping.
+fn write_pages(
+    block_builder: &BlockBuilder,
+    pages: Vec<(String, Result<Page>)>,
+    options: &GenerateOptions,
+    built: &mut BuiltPages,
+    failures: &mut Vec<(String, String)>,
+) -> Result<Vec<A11yFinding>> {
+    let GenerateOptions {
+        safe,
+        minify,
+        keep_going,
+        ..
+    } = *options;
+    let mut a11y_findings = Vec::new();
+    let base_path = block_builder.config.site_config.base_path();
+
+    for (block_name, page) in pages {
+        info!("Building block: {}", block_name.cyan().bold());
+
+        let page = match page {
+            Ok(page) => page,
+            Err(e) if keep_going => {
+                failures.push((block_name, format!("{:#}", e)));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        if page.hidden {
+            info!(
+                "Skipping {}, which shows a draft or future-dated file",
+                block_name.cyan().bold()
+            );
+            continue;
+        }
+
+        let block_file = block_builder.config.output_dir.join(&page.path);
+
+        if block_file.exists() {
+            if safe {
+                error!(
+                    "{}",
+                    format!(
+                        "Block file {} already exists! Ignoring it because safe mode is on.",
+                        block_name.cyan().bold()
+                    )
+                    .red()
+                );
+                continue;
+            } else {
+                warn!(
+                    "{}",
+                    format!(
+                        "Block file {} already exists! File will be overwritten...",
+                        block_name.yellow().bold()
+                    )
+                );
+            }
+        }
+
+        a11y_findings.extend(page.a11y_findings);
+
+        if let Some(parent) = block_file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut block_file = File::create(block_file)?;
+
+        let html = html::prefix_root_urls(&page.html, &base_path)?;
+        let html = with_external_links(html, &block_builder.config.site_config);
+        let html = plugins::transform_page(
+            &block_builder.config.site_config.plugins,
+            &block_builder.config.input_dir,
+            &block_name,
+            &page.path,
+            html,
+        )?;
+        block_file.write_all(minified_html(&html, minify).as_bytes())?;
+
+        let mut outputs = BTreeSet::from([page.path.clone()]);
+
+        if block_builder.config.site_config.page_styles
+            && !options.inline_generated_css
+            && !page.generated_styles.is_empty()
+        {
+            let style_path = styles::page_style_path(&page.path);
+            let mut css = page.generated_styles.render();
+            if minify {
+                css = minify::css(&css);
+            }
+
+            write_generated_file(&block_builder.config.output_dir, &style_path, &css, safe)?;
+            outputs.insert(style_path);
+        }
+
+        for (path, html) in page.extra_pages.iter() {
+            let html = html::prefix_root_urls(html, &base_path)?;
+            let html = with_external_links(html, &block_builder.config.site_config);
+            let html = plugins::transform_page(
+                &block_builder.config.site_config.plugins,
+                &block_builder.config.input_dir,
+                &block_name,
+                path,
+                html,
+            )?;
+            let html = minified_html(&html, minify);
+            write_generated_file(&block_builder.config.output_dir, path, &html, safe)?;
+            outputs.insert(path.clone());
+        }
+
+        for (path, contents) in page.files.iter() {
+            write_generated_file(&block_builder.config.output_dir, path, contents, safe)?;
+            outputs.insert(path.clone());
+        }
+
+        for image in page.images.iter() {
+            outputs.insert(image.output.clone());
+
+            if image.write(
+                &block_builder.config.input_dir,
+                &block_builder.config.output_dir,
+            )? {
+                info!(
+                    "Generated image {}",
+                    image.output.to_string_lossy().cyan().bold()
+                );
+            }
+        }
+
+        built.insert(
+            block_name,
+            BuiltPage {
+                generated_styles: page.generated_styles,
+                dependencies: page.dependencies,
+                path: Some(page.path),
+                outputs,
+            },
+        );
+    }
+
+    Ok(a11y_findings)
+}
+
+/// Constructs a single YAML block definition, resolving includes against the blocks in the
+/// input directory, and returns the generated HTML without writing anything.
+pub fn render(options: &GenerateOptions, block_name: &str, definition: &str) -> Result<String> {
+    let site_config = load_site_config(options)?;
+    let block = error::parse_yaml_resolved(Path::new(block_name), definition, |value| {
+        site_config.theme.resolve_references(value)
+    })?;
+
+    let mut block_builder = new_block_builder(options, &site_config, &AssetMap::default())?;
+
+    block_builder.add_block(block_name, block);
+
+    Ok(block_builder.construct_page(block_name)?.html)
+}
+
+fn report_failures(failures: &[(String, String)]) -> Result<()> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    for (block_name, error) in failures {
+        error!(
+            "{} {}: {}",
+            "Failed:".red().bold(),
+            block_name.bold(),
+            error
+        );
+    }
+
+    Err(eyre!(
+        "{}",
+        format!("Failed to build {} pages!", failures.len()).red()
+    ))
+}
+
+fn report_a11y_findings(findings: &[A11yFinding], strict: bool) -> Result<()> {
+    for finding in findings {
+        if strict {
+            error!("{} {}", "Accessibility:".red().bold(), finding);
+        } else {
+            warn!("{} {}", "Accessibility:".yellow().bold(), finding);
+        }
+    }
+
+    if strict && !findings.is_empty() {
+        return Err(eyre!(
+            "{}",
+            format!(
+                "Found {} accessibility problems! Aborting because strict accessibility checks are on.",
+                findings.len()
+            )
+            .red()
+        ));
+    }
+
+    Ok(())
+}
+
+fn with_external_links(html: String, site_config: &SiteConfig) -> String {
+    match &site_config.external_links {
+        Some(external_links) => external_links.apply(&html, site_config.base_url.as_deref()),
+        None => html,
+    }
+}
+
+fn minified_html(html: &str, minify: bool) -> std::borrow::Cow<'_, str> {
+    if minify {
+        minify::html(html).into()
+    } else {
+        html.into()
+    }
+}
+
+fn write_generated_styles(
+    output: &Path,
+    generated_styles: &StyleRules,
+    safe: bool,
+    minify: bool,
+) -> Result<()> {
+    let generated_style_file = output.join(SHARED_STYLE_FILE);
+
+    if generated_style_file.exists() {
+        if safe {
+            error!(
+                "Generated style file {} already exists! Ignoring it because safe mode is on.",
+                generated_style_file.to_string_lossy().red().bold()
+            );
+            return Ok(());
+        } else {
+            warn!(
+                "Generated style file {} already exists! File will be overwritten...",
+                generated_style_file.to_string_lossy().cyan().bold()
+            );
+        }
+    }
+
+    let mut generated_style_file = File::create(generated_style_file)?;
+
+    let mut css = generated_styles.render();
+    if minify {
+        css = minify::css(&css);
+    }
+
+    generated_style_file.write_all(css.as_bytes())?;
+
+    Ok(())
+}
+
+struct AssetOptions<'a> {
+    input_root: &'a Path,
+    safe: bool,
+    minify: bool,
+    asset_map: &'a AssetMap,
+    site_config: &'a SiteConfig,
+    /// Inputs unchanged since the previous build, whose transforms don't need to run again
+    /// if their output is still there.
+    unchanged_inputs: &'a BTreeSet<String>,
+}
+
+/// Keys of the `inputs` with the same hash as in the `previous` build. None are unchanged
+/// if the site config, which declares the transforms, changed.
+fn unchanged_inputs(previous: Option<&InputHashes>, inputs: &InputHashes) -> BTreeSet<String> {
+    let previous = match previous {
+        Some(previous)
+            if previous.hashes.get(SITE_CONFIG_FILE) == inputs.hashes.get(SITE_CONFIG_FILE) =>
+        {
+            previous
+        }
+        _ => return BTreeSet::new(),
+    };
+
+    inputs
+        .hashes
+        .iter()
+        .filter(|(path, hash)| previous.hashes.get(*path) == Some(*hash))
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// Copies every asset file under `input` into `output`, mirroring the directory structure.
+///
+/// Symlinks are followed: a link to a directory is copied as a directory and a link to a file
+/// copies the file it points to. Broken symlinks are skipped with a warning.
+fn build_asset_files(input: &Path, output: &Path, options: &AssetOptions) -> Result<()> {
+    let input_files = read_dir(input)?;
+
+    if !output.exists() {
+        std::fs::create_dir_all(output)?;
+    }
+
+    let output_files = read_dir(output)?;
+
+    if output_files.count() != 0 {
+        if options.safe {
+            return Err(eyre!(
+                "{}",
+                "Output directory is not empty! Aborting because safe mode is on.".red()
+            ));
+        } else {
+            warn!(
+                "Output directory {} is not empty! Files will be overwritten...",
+                output.to_string_lossy().yellow().bold()
+            );
+        }
+    }
+    for file in input_files {
+        let file = file?;
+        let file_name = file.file_name();
+        let file_name = file_name.to_string_lossy();
+        let file_name = file_name.as_ref();
+
+        let relative = file.path();
+        let relative = relative.strip_prefix(options.input_root)?;
+
+        if options.site_config.is_ignored(relative) || data_files::is_data_file(relative) {
+            continue;
+        }
+
+        if file.file_type()?.is_symlink() && std::fs::metadata(file.path()).is_err() {
+            warn!(
+                "Skipping broken symlink {}",
+                file.path().to_string_lossy().yellow().bold()
+            );
+        } else if file_name.ends_with(".md") {
+            // markdown files are converted separately, once all their output paths are known
+        } else if file_name.ends_with(".yml") {
+            // we don't need to do anything with the block definitions
+        } else if file.path().is_dir() {
+            let mut new_input = input.to_owned();
+            new_input.push(file_name);
+            let mut new_output = output.to_owned();
+            new_output.push(file_name);
+            build_asset_files(&new_input, &new_output, options)?;
+        } else {
+            build_asset_file(&file.path(), output, options)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Transforms or copies a single asset file into the `output` directory.
+fn build_asset_file(source: &Path, output: &Path, options: &AssetOptions) -> Result<()> {
+    let file_name = source
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy())
+        .unwrap_or_default();
+
+    let output_name = asset_output_name(source, options)?;
+
+    if let Some(transform) = find_transform(source, options)? {
+        let output_file = output.join(output_name);
+        let key = assets::relative_key(source.strip_prefix(options.input_root)?);
+
+        if output_file.exists() && options.unchanged_inputs.contains(&key) {
+            info!("Skipping transform of unchanged file {}", file_name);
+        } else {
+            info!("Transforming file {}", file_name);
+            transform.run(options.input_root, source, &output_file)?;
+        }
+    } else {
+        let minifier = match source.extension().and_then(|ext| ext.to_str()) {
+            Some("css") if options.minify => Some(minify::css as fn(&str) -> String),
+            Some("js") if options.minify => Some(minify::js as fn(&str) -> String),
+            _ => None,
+        };
+
+        if let Some(minifier) = minifier {
+            info!("Minifying file {}", file_name);
+            let contents = std::fs::read_to_string(source)?;
+            std::fs::write(output.join(output_name), minifier(&contents))?;
+        } else {
+            info!("Copying file {}", file_name);
+            std::fs::copy(source, output.join(output_name))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Name an asset file is written under: renamed by the transform it goes through, or
+/// content-hashed when fingerprinting.
+fn asset_output_name(source: &Path, options: &AssetOptions) -> Result<String> {
+    let file_name = source
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy())
+        .unwrap_or_default();
+
+    Ok(match find_transform(source, options)? {
+        Some(transform) => {
+            let output_name = transform.output_file_name(&file_name);
+
+            options
+                .asset_map
+                .hashed_file_name(&source.with_file_name(&output_name))
+                .map(str::to_string)
+                .unwrap_or(output_name)
+        }
+        None => options
+            .asset_map
+            .hashed_file_name(source)
+            .unwrap_or(&file_name)
+            .to_string(),
+    })
+}
+
+fn find_transform<'a>(path: &Path, options: &AssetOptions<'a>) -> Result<Option<&'a Transform>> {
+    transforms::find(
+        &options.site_config.transforms,
+        path.strip_prefix(options.input_root)?,
+    )
+}
+
+pub struct MarkdownOutput {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub parsed: Rc<ParsedMarkdown>,
+}
+
+const DEFAULT_MD_OUTPUT: &str = "{dir}/{stem}.html";
+const PRETTY_MD_OUTPUT: &str = "{dir}/{stem}/index.html";
+
+/// Reads every markdown file under `input` and computes where its HTML will be written,
+/// failing if two files would end up at the same output path. Drafts and future-dated files
+/// are left out unless the options include them.
+fn plan_markdown_outputs(
+    input: &Path,
+    site_config: &SiteConfig,
+    options: &GenerateOptions,
+    cache: &mut Cache,
+) -> Result<Vec<MarkdownOutput>> {
+    let mut sources = Vec::new();
+    find_markdown_files(input, input, site_config, &mut sources)?;
+    sources.sort();
+
+    let template = match site_config.md_output.as_deref() {
+        Some(template) => template,
+        None if site_config.pretty_urls => PRETTY_MD_OUTPUT,
+        None => DEFAULT_MD_OUTPUT,
+    };
+
+    let mut planned: Vec<MarkdownOutput> = Vec::new();
+
+    for source in sources {
+        let parsed = cache.markdown(&source)?;
+
+        if parsed.frontmatter.is_hidden(options.drafts, options.future) {
+            let kind = if parsed.frontmatter.draft && !options.drafts {
+                "draft"
+            } else {
+                "future-dated"
+            };
+
+            info!("Skipping {} {}", kind, source.to_string_lossy().yellow());
+            continue;
+        }
+
+        // an index.md already is its directory's page
+        let template = match template {
+            PRETTY_MD_OUTPUT if source.file_stem() == Some(OsStr::new("index")) => {
+                DEFAULT_MD_OUTPUT
+            }
+            _ => template,
+        };
+
+        let output = md_output_path(
+            input,
+            &source,
+            parsed.frontmatter.output.as_deref().unwrap_or(template),
+            &parsed.frontmatter,
+            &site_config.i18n,
+        )?;
+
+        planned.push(MarkdownOutput {
+            output,
+            parsed,
+            source,
+        });
+    }
+
+    let mut collisions = Vec::new();
+    for (i, first) in planned.iter().enumerate() {
+        for second in planned[i + 1..].iter() {
+            if first.output == second.output {
+                collisions.push(format!(
+                    "{} and {} both output to {}",
+                    first.source.to_string_lossy(),
+                    second.source.to_string_lossy(),
+                    first.output.to_string_lossy()
+                ));
+            }
+        }
+    }
+
+    if !collisions.is_empty() {
+        return Err(eyre!(
+            "Markdown output paths collide:\n{}",
+            collisions.join("\n")
+        ));
+    }
+
+    Ok(planned)
+}
+
+/// The paths, relative to the output directory, constructed pages are written to.
+fn page_outputs(pages: &[(String, Result<Page>)]) -> impl Iterator<Item = (String, PathBuf)> + '_ {
+    pages
+        .iter()
+        .filter_map(|(block_name, page)| match page {
+            Ok(page) if !page.hidden => Some((block_name, page)),
+            _ => None,
+        })
+        .flat_map(|(block_name, page)| {
+            std::iter::once(page.path.clone())
+                .chain(page.extra_pages.iter().map(|(path, _)| path.clone()))
+                .map(move |path| (block_name.clone(), path))
+        })
+}
+
+/// Fails if a markdown file would be written to the same path as a page, before anything is.
+fn check_page_collisions(
+    markdown_outputs: &[MarkdownOutput],
+    page_outputs: impl Iterator<Item = (String, PathBuf)>,
+) -> Result<()> {
+    let collisions = page_outputs
+        .filter_map(|(block_name, path)| {
+            let markdown = markdown_outputs
+                .iter()
+                .find(|markdown| markdown.output == path)?;
+
+            Some(format!(
+                "{} and block {} both output to {}",
+                markdown.source.to_string_lossy(),
+                block_name,
+                path.to_string_lossy()
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    if !collisions.is_empty() {
+        return Err(eyre!(
+            "Markdown and page output paths collide:\n{}",
+            collisions.join("\n")
+        ));
+    }
+
+    Ok(())
+}
+
+fn find_markdown_files(
+    dir: &Path,
+    input: &Path,
+    site_config: &SiteConfig,
+    found: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+
+        if site_config.is_ignored(path.strip_prefix(input)?) {
+            continue;
+        }
+
+        if path.is_dir() {
+            find_markdown_files(&path, input, site_config, found)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            found.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Expands an output template for the markdown file at `source`, returning a path relative
+/// to the output directory.
+fn md_output_path(
+    input: &Path,
+    source: &Path,
+    template: &str,
+    frontmatter: &Frontmatter,
+    i18n: &I18nConfig,
+) -> Result<PathBuf> {
+    let relative = source.strip_prefix(input)?;
+
+    let dir = relative
+        .parent()
+        .map(|parent| parent.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = relative
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let language = frontmatter
+        .get("lang")
+        .filter(|language| i18n.is_language(language))
+        .unwrap_or_default();
+
+    let mut output = template
+        .replace("{dir}", &dir)
+        .replace("{stem}", &stem)
+        .replace("{lang}", i18n.path_prefix(&language));
+
+    if output.contains("{date}") {
+        let date = frontmatter.date.as_ref().ok_or_else(|| {
+            eyre!(
+                "{} uses {{date}} in its output path but has no date in its frontmatter",
+                source.to_string_lossy()
+            )
+        })?;
+        output = output.replace("{date}", date);
+    }
+
+    let output: PathBuf = output
+        .split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect();
+
+    if output.as_os_str().is_empty() || output.components().any(|c| c.as_os_str() == "..") {
+        return Err(eyre!(
+            "Invalid output path {} computed for {}",
+            template,
+            source.to_string_lossy()
+        ));
+    }
+
+    Ok(output)
+}
+
+/// Writes the feeds from the site config, with an entry for each planned markdown output
+/// matching the feed's pattern. Entries without a frontmatter date use the file's
+/// modification time.
+fn write_feeds(
+    input: &Path,
+    output: &Path,
+    site_config: &SiteConfig,
+    markdown_outputs: &[MarkdownOutput],
+    safe: bool,
+) -> Result<()> {
+    for feed_config in site_config.feeds.iter() {
+        let (Some(site_url), Some(feed_url)) = (
+            site_config.absolute_url(Path::new("")),
+            site_config.absolute_url(&feed_config.output),
+        ) else {
+            return Err(eyre!(
+                "Feed {} needs base_url to be set in {}",
+                feed_config.output.to_string_lossy(),
+                SITE_CONFIG_FILE
+            ));
+        };
+
+        let mut entries = Vec::new();
+
+        for markdown_output in markdown_outputs {
+            if !feed_config.matches(markdown_output.source.strip_prefix(input)?) {
+                continue;
+            }
+
+            let frontmatter = &markdown_output.parsed.frontmatter;
+
+            let date = match frontmatter.date.as_deref() {
+                Some(date) => feed::parse_date(date).ok_or_else(|| {
+                    eyre!(
+                        "{}: cannot read date {}",
+                        markdown_output.source.to_string_lossy(),
+                        date
+                    )
+                })?,
+                None => chrono::DateTime::<chrono::Utc>::from(
+                    std::fs::metadata(&markdown_output.source)?.modified()?,
+                )
+                .fixed_offset(),
+            };
+
+            entries.push(FeedEntry {
+                title: frontmatter.title.clone().unwrap_or_else(|| {
+                    markdown_output
+                        .source
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                }),
+                url: site_config
+                    .absolute_url(&markdown_output.output)
+                    .map(|url| site_config.page_url(url))
+                    .unwrap_or_default(),
+                date,
+                summary: frontmatter.get("summary"),
+            });
+        }
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+
+        if let Some(limit) = feed_config.limit {
+            entries.truncate(limit);
+        }
+
+        let info = FeedInfo {
+            title: feed_config
+                .title
+                .as_deref()
+                .or(site_config.title.as_deref())
+                .unwrap_or_default(),
+            description: feed_config.description.as_deref().unwrap_or_default(),
+            site_url: &site_url,
+            feed_url: &feed_url,
+        };
+
+        info!(
+            "Writing feed {}",
+            feed_config.output.to_string_lossy().cyan().bold()
+        );
+
+        write_generated_file(
+            output,
+            &feed_config.output,
+            &feed::render(feed_config.format, &info, &entries),
+            safe,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes a file generated from the whole site, like a feed, into the output directory.
+fn write_generated_file(output: &Path, relative: &Path, contents: &str, safe: bool) -> Result<()> {
+    let output_file = output.join(relative);
+
+    // leaving unchanged files alone keeps repeated builds quiet
+    if std::fs::read_to_string(&output_file).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+
+    if output_file.exists() {
+        if safe {
+            return Err(eyre!(
+                "Output file {} already exists! Aborting because safe mode is on.",
+                output_file.to_string_lossy().red()
+            ));
+        } else {
+            warn!(
+                "Output file {} already exists! File will be overwritten...",
+                relative.to_string_lossy().yellow().bold()
+            );
+        }
+    }
+
+    if let Some(parent) = output_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    File::create(output_file)?.write_all(contents.as_bytes())?;
+
+    Ok(())
+}
+
+pub fn generate_html_from_md(
+    markdown: &MarkdownOutput,
+    output: &Path,
+    safe: bool,
+    minify: bool,
+    site_config: &SiteConfig,
+) -> Result<()> {
+    let output_file = output.join(&markdown.output);
+    let output_filename = markdown.output.to_string_lossy();
+
+    if output_file.exists() {
+        if safe {
+            return Err(eyre!(
+                "Output file {} already exists! Aborting because safe mode is on.",
+                output_file.to_string_lossy().red()
+            ));
+        } else {
+            info!(
+                "Output file {} already exists! File will be overwritten...",
+                output_filename.yellow().bold()
+            );
+        }
+    }
+
+    if let Some(parent) = output_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut file = File::create(output_file)?;
+
+    if !markdown.parsed.shortcodes.is_empty() {
+        warn!(
+            "Leaving out the shortcodes in the standalone page of {}, as they only work in markdown used by a block",
+            markdown.source.to_string_lossy().yellow()
+        );
+    }
+
+    let html = html::prefix_root_urls(&markdown.parsed.html, &site_config.base_path())?;
+    let html = with_external_links(html, site_config);
+    file.write_all(minified_html(&html, minify).as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An input directory with the given files, and an output directory in it.
+    fn site(files: &[(&str, &str)]) -> (tempfile::TempDir, GenerateOptions) {
+        let input = tempfile::tempdir().unwrap();
+
+        for (path, contents) in files {
+            let path = input.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        let options = GenerateOptions::new(input.path().to_path_buf(), input.path().join("out"));
+
+        (input, options)
+    }
+
+    fn linked_page(colors: &[&str]) -> String {
+        let links = colors
+            .iter()
+            .map(|color| {
+                format!(
+                    "    - link:\n        text: go\n        url: /\n        link_style:\n          explicit:\n            underline: false\n            color:\n              normal: '{}'\n",
+                    color
+                )
+            })
+            .collect::<String>();
+
+        format!(
+            "html:\n  head:\n    lang: en\n    title: T\n  body:\n{}",
+            links
+        )
+    }
+
+    fn rule_count(css: &str, color: &str) -> usize {
+        css.matches(&format!(".link-{}-none:hover", color)).count()
+    }
+
+    /// A site fingerprinting its assets with a `style.css` at the root, linked from a page
+    /// at `path` by `url`, and the hashed name of the style.
+    fn styled_site(
+        path: &str,
+        url: &str,
+        permalink: &str,
+    ) -> (tempfile::TempDir, GenerateOptions, String) {
+        let css = "body { color: red; }";
+        let page = format!(
+            "html:\n  head:\n    lang: en\n    title: T\n    styles:\n      - {}\n{}  body:\n    - text: Hi\n",
+            url, permalink
+        );
+        let (input, options) = site(&[
+            ("blockblog.yml", "fingerprint_assets: true\n"),
+            ("style.css", css),
+            (path, &page),
+        ]);
+
+        (
+            input,
+            options,
+            format!(
+                "style.{}.css",
+                crate::data::hash::short_hash(css.as_bytes())
+            ),
+        )
+    }
+
+    fn planned_outputs(options: &GenerateOptions) -> Result<Vec<PathBuf>> {
+        let site_config = load_site_config(options)?;
+        let planned = plan_markdown_outputs(
+            &options.input,
+            &site_config,
+            options,
+            &mut Cache::new(site_config.typography),
+        )?;
+
+        Ok(planned.into_iter().map(|planned| planned.output).collect())
+    }
+
+    #[test]
+    fn assets_are_relative_to_permalink() {
+        let (_input, options, hashed) = styled_site(
+            "blog/post.yml",
+            "../../style.css",
+            "  permalink: posts/first/\n",
+        );
+
+        generate(&options).unwrap();
+        let html = std::fs::read_to_string(options.output.join("posts/first/index.html")).unwrap();
+
+        assert!(
+            html.contains(&format!("href=\"../../{}\"", hashed)),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn assets_are_relative_to_pretty_url() {
+        let (_input, mut options, hashed) = styled_site("post.yml", "../style.css", "");
+        options.pretty_urls = true;
+
+        generate(&options).unwrap();
+        let html = std::fs::read_to_string(options.output.join("post/index.html")).unwrap();
+
+        assert!(
+            html.contains(&format!("href=\"../{}\"", hashed)),
+            "{}",
+            html
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn asset_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let (input, options) = site(&[("real.css", "a {}"), ("shared/icon.svg", "<svg/>")]);
+        symlink(input.path().join("real.css"), input.path().join("link.css")).unwrap();
+        symlink(
+            input.path().join("missing.css"),
+            input.path().join("broken.css"),
+        )
+        .unwrap();
+        symlink(input.path().join("shared"), input.path().join("linked")).unwrap();
+
+        generate(&options).unwrap();
+
+        let link = options.output.join("link.css");
+        assert_eq!(std::fs::read_to_string(&link).unwrap(), "a {}");
+        assert!(!link.symlink_metadata().unwrap().file_type().is_symlink());
+        assert!(options.output.join("linked/icon.svg").is_file());
+        assert!(options
+            .output
+            .join("broken.css")
+            .symlink_metadata()
+            .is_err());
+    }
+
+    fn transform_config(command: &str) -> String {
+        format!(
+            "transforms:\n  - match: '**/*.txt'\n    command: '{}'\n    output_ext: up\n",
+            command
+        )
+    }
+
+    #[test]
+    fn transform_output() {
+        let (_input, options) = site(&[
+            (
+                "blockblog.yml",
+                &transform_config("tr a-z A-Z < {input} > {output}"),
+            ),
+            ("notes/a.txt", "hello"),
+        ]);
+
+        generate(&options).unwrap();
+
+        let output = options.output.join("notes/a.up");
+        assert_eq!(std::fs::read_to_string(output).unwrap(), "HELLO");
+        assert!(!options.output.join("notes/a.txt").exists());
+    }
+
+    #[test]
+    fn failing_transform() {
+        let (_input, options) = site(&[
+            (
+                "blockblog.yml",
+                &transform_config("echo no such tool >&2; exit 3"),
+            ),
+            ("a.txt", "hello"),
+        ]);
+
+        let error = format!("{:#}", generate(&options).unwrap_err());
+
+        assert!(error.contains("no such tool"), "{}", error);
+    }
+
+    #[test]
+    fn unchanged_transform_inputs_are_skipped() {
+        let (input, options) = site(&[
+            ("blockblog.yml", &transform_config("cp {input} {output}")),
+            ("a.txt", "a"),
+            ("b.txt", "b"),
+        ]);
+        generate(&options).unwrap();
+
+        std::fs::write(options.output.join("a.up"), "kept").unwrap();
+        std::fs::write(options.output.join("b.up"), "kept").unwrap();
+        std::fs::write(input.path().join("b.txt"), "changed").unwrap();
+        build(&options).unwrap();
+
+        let read = |name| std::fs::read_to_string(options.output.join(name)).unwrap();
+        assert_eq!(read("a.up"), "kept");
+        assert_eq!(read("b.up"), "changed");
+
+        std::fs::write(
+            input.path().join("blockblog.yml"),
+            transform_config("tr a-z A-Z < {input} > {output}"),
+        )
+        .unwrap();
+        build(&options).unwrap();
+
+        assert_eq!(read("a.up"), "A");
+    }
+
+    #[test]
+    fn transform_outputs_are_fingerprinted() {
+        let (_input, options) = site(&[
+            (
+                "blockblog.yml",
+                "fingerprint_assets: true\ntransforms:\n  - match: '*.scss'\n    command: 'cp {input} {output}'\n    output_ext: css\n",
+            ),
+            ("style.scss", "a {}"),
+            (
+                "index.yml",
+                "html:\n  head:\n    lang: en\n    title: T\n    styles:\n      - style.css\n  body:\n    - text: Hi\n",
+            ),
+        ]);
+
+        generate(&options).unwrap();
+
+        let styles = std::fs::read_dir(&options.output)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("style.") && name.ends_with(".css"))
+            .collect::<Vec<_>>();
+        let html = std::fs::read_to_string(options.output.join("index.html")).unwrap();
+
+        assert_eq!(styles.len(), 1, "{:?}", styles);
+        assert_ne!(styles[0], "style.css");
+        assert!(
+            html.contains(&format!("href=\"{}\"", styles[0])),
+            "{}",
+            html
+        );
+    }
+
+    /// Counts how often each file is read.
+    #[derive(Clone, Default)]
+    struct CountingReader(Rc<std::cell::RefCell<BTreeMap<PathBuf, usize>>>);
+
+    impl crate::data::cache::FileReader for CountingReader {
+        fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+            *self.0.borrow_mut().entry(path.to_owned()).or_default() += 1;
+            std::fs::read_to_string(path)
+        }
+    }
+
+    #[test]
+    fn markdown_files_are_read_once() {
+        let post = "---\ntitle: Post\n---\n# Post";
+        let (_input, options) = site(&[
+            ("posts/a.md", post),
+            ("posts/b.md", post),
+            (
+                "list.yml",
+                "block:\n  items:\n    - $for_each:\n        pattern: 'posts/*.md'\n        items:\n          - text: $page.title\n",
+            ),
+            ("list2.yml", "block:\n  items:\n    - include: list\n"),
+            ("first.yml", "block:\n  items:\n    - markdown_file: posts/a.md\n"),
+        ]);
+        let site_config = load_site_config(&options).unwrap();
+        let asset_map = AssetMap::default();
+        let mut block_builder = new_block_builder(&options, &site_config, &asset_map).unwrap();
+
+        let reader = CountingReader::default();
+        block_builder.cache = Cache::with_reader(site_config.typography, Box::new(reader.clone()));
+
+        plan_markdown_outputs(
+            &options.input,
+            &site_config,
+            &options,
+            &mut block_builder.cache,
+        )
+        .unwrap();
+        for (_, page) in block_builder.construct_all().unwrap() {
+            assert!(page.is_ok());
+        }
+
+        let reads = reader.0.borrow();
+        assert_eq!(reads.len(), 2, "{:?}", reads);
+        assert!(reads.values().all(|count| *count == 1), "{:?}", reads);
+    }
+
+    #[test]
+    fn markdown_outputs() {
+        let (_input, options) = site(&[
+            ("blog/index.md", "# Blog"),
+            ("notes/2024.03.md.backup.md", "# Backup"),
+        ]);
+
+        assert_eq!(
+            planned_outputs(&options).unwrap(),
+            [
+                PathBuf::from("blog/index.html"),
+                PathBuf::from("notes/2024.03.md.backup.html"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pretty_markdown_outputs() {
+        let (_input, mut options) =
+            site(&[("blog/index.md", "# Blog"), ("blog/post.md", "# Post")]);
+        options.pretty_urls = true;
+
+        assert_eq!(
+            planned_outputs(&options).unwrap(),
+            [
+                PathBuf::from("blog/index.html"),
+                PathBuf::from("blog/post/index.html"),
+            ]
+        );
+    }
+
+    #[test]
+    fn colliding_markdown_outputs() {
+        let (_input, options) = site(&[("a.md", "# A"), ("b.md", "---\noutput: a.html\n---\n# B")]);
+
+        let error = planned_outputs(&options).unwrap_err().to_string();
+
+        assert!(error.contains("both output to a.html"), "{}", error);
+    }
+
+    #[test]
+    fn markdown_colliding_with_page() {
+        let (_input, options) = site(&[
+            ("index.md", "# Home"),
+            ("index.yml", &linked_page(&["#123456"])),
+        ]);
+
+        let error = generate(&options).unwrap_err().to_string();
+
+        assert!(
+            error.contains("block index both output to index.html"),
+            "{}",
+            error
+        );
+        assert!(!options.output.join("index.html").exists());
+    }
+
+    #[test]
+    fn overlapping_styles_are_merged() {
+        let (_input, options) = site(&[
+            ("a.yml", &linked_page(&["#123456"])),
+            ("b.yml", &linked_page(&["#123456", "#654321"])),
+        ]);
+
+        generate(&options).unwrap();
+        let css = std::fs::read_to_string(options.output.join(SHARED_STYLE_FILE)).unwrap();
+
+        assert_eq!(rule_count(&css, "123456"), 1, "{}", css);
+        assert_eq!(rule_count(&css, "654321"), 1, "{}", css);
+    }
+
+    #[test]
+    fn removed_page_styles_are_dropped() {
+        let (input, options) = site(&[
+            ("a.yml", &linked_page(&["#123456"])),
+            ("b.yml", &linked_page(&["#654321"])),
+        ]);
+
+        generate(&options).unwrap();
+        std::fs::remove_file(input.path().join("b.yml")).unwrap();
+        generate(&options).unwrap();
+        let css = std::fs::read_to_string(options.output.join(SHARED_STYLE_FILE)).unwrap();
+
+        assert_eq!(rule_count(&css, "123456"), 1, "{}", css);
+        assert_eq!(rule_count(&css, "654321"), 0, "{}", css);
+    }
+
+    #[test]
+    fn css_var_link_rules_are_shared() {
+        let hover_rules = |colors: &[&str]| {
+            let (_input, mut options) = site(&[("a.yml", &linked_page(colors))]);
+            options.css_vars = true;
+
+            generate(&options).unwrap();
+            let css = std::fs::read_to_string(options.output.join(SHARED_STYLE_FILE)).unwrap();
+            css.matches(":hover").count()
+        };
+
+        assert_eq!(hover_rules(&["#123456"]), 1);
+        assert_eq!(hover_rules(&["#123456", "#654321", "#abcdef"]), 1);
+    }
+}
@@ -0,0 +1,10 @@
+pub mod check;
+pub mod data;
+pub mod generator;
+pub mod scaffold;
+pub mod serve;
+pub mod site;
+mod watch;
+
+pub use generator::GenerateOptions;
+pub use site::Site;
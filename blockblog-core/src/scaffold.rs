@@ -0,0 +1,259 @@
+use std::{
+    io::{self, Error},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::Result;
+
+use crate::data::{
+    components::{ComponentSource, COMPONENTS_DIR},
+    config::SiteConfig,
+    taxonomy,
+};
+
+/// Site templates `init` can start from, as file paths and their contents.
+const TEMPLATES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "blog",
+        &[
+            (
+                "blockblog.yml",
+                include_str!("../templates/blog/blockblog.yml"),
+            ),
+            ("index.yml", include_str!("../templates/blog/index.yml")),
+            ("style.css", include_str!("../templates/blog/style.css")),
+            (
+                "templates/post.yml",
+                include_str!("../templates/blog/templates/post.yml"),
+            ),
+            (
+                "posts/hello-world.md",
+                include_str!("../templates/blog/posts/hello-world.md"),
+            ),
+            (
+                "blog/hello-world.yml",
+                include_str!("../templates/blog/blog/hello-world.yml"),
+            ),
+        ],
+    ),
+    (
+        "minimal",
+        &[
+            (
+                "blockblog.yml",
+                include_str!("../templates/minimal/blockblog.yml"),
+            ),
+            ("index.yml", include_str!("../templates/minimal/index.yml")),
+            ("style.css", include_str!("../templates/minimal/style.css")),
+        ],
+    ),
+];
+
+pub const DEFAULT_TEMPLATE: &str = "blog";
+
+/// Block a post page is constructed through, if the site has one.
+const POST_LAYOUT: &str = "templates/post";
+/// Directory, relative to the input directory, of the pages showing posts through the
+/// post layout.
+const POST_PAGES_DIR: &str = "blog";
+
+pub fn template_names() -> Vec<&'static str> {
+    TEMPLATES.iter().map(|(name, _)| *name).collect()
+}
+
+/// Writes the files of a site template into `dir`, returning their paths. Nothing is
+/// written if any of them already exists.
+pub fn init(dir: &Path, template: &str) -> Result<Vec<PathBuf>> {
+    let files = TEMPLATES
+        .iter()
+        .find(|(name, _)| *name == template)
+        .map(|(_, files)| *files)
+        .ok_or_else(|| {
+            Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "Unknown site template {}, expected one of: {}",
+                    template,
+                    template_names().join(", ")
+                ),
+            )
+        })?;
+
+    let files = files
+        .iter()
+        .map(|(path, contents)| (dir.join(path), *contents))
+        .collect::<Vec<_>>();
+
+    if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+        return Err(Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "{} already exists! Not overwriting anything.",
+                path.to_string_lossy()
+            ),
+        )
+        .into());
+    }
+
+    for (path, contents) in files.iter() {
+        write_new_file(path, contents)?;
+    }
+
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Creates a markdown file for a new post, named after today's date and the title, in the
+/// site's posts directory. If the site has a post layout, a page showing the post through
+/// it is created too. Returns the paths of the new files.
+pub fn new_post(input: &Path, title: &str) -> Result<Vec<PathBuf>> {
+    let site_config = SiteConfig::load(input)?;
+    let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let name = format!("{}-{}", date, taxonomy::slug(title));
+
+    let posts_dir = site_config
+        .posts_dir
+        .unwrap_or_else(|| PathBuf::from("posts"));
+    let post = posts_dir.join(format!("{}.md", name));
+
+    // a JSON string is also a valid YAML one, with any quotes in the title escaped
+    let frontmatter = format!(
+        "---\ntitle: {}\ndate: {}\ntags: []\ndraft: true\n---\n",
+        serde_json::to_string(title)?,
+        date
+    );
+
+    let mut files = vec![(input.join(&post), format!("{}\n# {}\n", frontmatter, title))];
+
+    if input.join(format!("{}.yml", POST_LAYOUT)).is_file() {
+        let post_path = post
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        files.push((
+            input.join(POST_PAGES_DIR).join(format!("{}.yml", name)),
+            format!(
+                "include:\n  path: {}\n  params:\n    path: {}\n",
+                POST_LAYOUT, post_path
+            ),
+        ));
+    }
+
+    if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+        return Err(Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists!", path.to_string_lossy()),
+        )
+        .into());
+    }
+
+    for (path, contents) in files.iter() {
+        write_new_file(path, contents)?;
+    }
+
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Copies a component set from a directory, or clones it from a git repository, into the
+/// site's components directory under `namespace`, or a namespace named after the source.
+/// Returns the directory it was added as.
+pub fn add_component(input: &Path, source: &str, namespace: Option<&str>) -> Result<PathBuf> {
+    let source = ComponentSource::parse(source)?;
+    let namespace = match namespace {
+        Some(namespace) => namespace.to_string(),
+        None => source.default_namespace().ok_or_else(|| {
+            Error::new(
+                io::ErrorKind::InvalidInput,
+                "Could not name the component set after its source, give it a namespace",
+            )
+        })?,
+    };
+
+    if namespace.is_empty() || namespace.contains(['/', '\\']) || namespace.starts_with('.') {
+        return Err(Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid component namespace {}", namespace),
+        )
+        .into());
+    }
+
+    let dir = input.join(COMPONENTS_DIR).join(&namespace);
+
+    if dir.exists() {
+        return Err(Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists!", dir.to_string_lossy()),
+        )
+        .into());
+    }
+
+    std::fs::create_dir_all(input.join(COMPONENTS_DIR))?;
+
+    match source {
+        ComponentSource::Git(url) => {
+            let status = std::process::Command::new("git")
+                .args(["clone", "--depth", "1", "--quiet", "--", &url])
+                .arg(&dir)
+                .status()
+                .map_err(|why| {
+                    Error::new(
+                        why.kind(),
+                        format!("Could not run git to clone {}: {}", url, why),
+                    )
+                })?;
+
+            if !status.success() {
+                return Err(Error::other(format!("Cloning {} failed with {}", url, status)).into());
+            }
+
+            // the set becomes part of the site, to be committed along with it
+            std::fs::remove_dir_all(dir.join(".git"))?;
+        }
+        ComponentSource::Dir(source_dir) => {
+            if !source_dir.is_dir() {
+                return Err(Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is not a directory", source_dir.to_string_lossy()),
+                )
+                .into());
+            }
+
+            copy_dir(&source_dir, &dir)?;
+        }
+    }
+
+    Ok(dir)
+}
+
+/// Copies a directory with everything in it, leaving out dotfiles like `.git`.
+fn copy_dir(source: &Path, destination: &Path) -> Result<()> {
+    std::fs::create_dir_all(destination)?;
+
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        if path.is_dir() {
+            copy_dir(&path, &destination.join(entry.file_name()))?;
+        } else {
+            std::fs::copy(&path, destination.join(entry.file_name()))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_new_file(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, contents)?;
+
+    Ok(())
+}
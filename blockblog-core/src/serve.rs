@@ -0,0 +1,246 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use color_eyre::Result;
+use colored::*;
+use log::{error, info};
+
+use crate::generator::{self, GenerateOptions};
+use crate::watch;
+
+const VERSION_PATH: &str = "/__blockblog/version";
+
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+    var version = null;
+    setInterval(function () {
+        fetch("/__blockblog/version").then(function (response) {
+            return response.text();
+        }).then(function (current) {
+            if (version !== null && version !== current) {
+                location.reload();
+            }
+            version = current;
+        }).catch(function () {});
+    }, 1000);
+})();
+</script>
+"#;
+
+pub struct ServeOptions {
+    pub generate: GenerateOptions,
+    pub host: String,
+    pub port: u16,
+}
+
+/// Builds the site, serves the output directory over HTTP and rebuilds whenever the input
+/// changes. Served HTML pages reload themselves after each rebuild.
+pub fn serve(options: ServeOptions) -> Result<()> {
+    let ServeOptions {
+        generate,
+        host,
+        port,
+    } = options;
+
+    let input = generate.input.clone();
+    let output = generate.output.clone();
+    let version = Arc::new(AtomicU64::new(0));
+    // pages link to the base path of the deployed site, so it is served there as well
+    let base_path = Arc::new(generator::load_site_config(&generate)?.base_path());
+
+    rebuild(&generate, &version);
+
+    let listener = TcpListener::bind((host.as_str(), port))?;
+    info!(
+        "Serving {} on {}",
+        output.to_string_lossy().cyan().bold(),
+        format!("http://{}:{}", host, port).cyan().bold()
+    );
+
+    {
+        let output = output.clone();
+        let version = version.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let output = output.clone();
+                let version = version.clone();
+                let base_path = base_path.clone();
+
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &output, &base_path, &version) {
+                        error!("Failed to handle request: {}", e);
+                    }
+                });
+            }
+        });
+    }
+
+    watch::watch(&input, &[output], |changed| {
+        for path in changed.iter() {
+            info!("Changed: {}", path.to_string_lossy().yellow());
+        }
+
+        rebuild(&generate, &version);
+    })
+}
+
+fn rebuild(options: &GenerateOptions, version: &AtomicU64) {
+    match generator::generate(options) {
+        Ok(_) => {
+            version.fetch_add(1, Ordering::SeqCst);
+        }
+        Err(e) => error!("{:#}", e),
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    output: &Path,
+    base_path: &str,
+    version: &AtomicU64,
+) -> Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&mut stream).read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("/");
+    let path = target.split(['?', '#']).next().unwrap_or("/");
+    let path = match path.strip_prefix(base_path) {
+        Some(rest) if !base_path.is_empty() && (rest.is_empty() || rest.starts_with('/')) => rest,
+        _ => path,
+    };
+
+    if method != "GET" && method != "HEAD" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"");
+    }
+
+    if path == VERSION_PATH {
+        let version = version.load(Ordering::SeqCst).to_string();
+        return respond(&mut stream, "200 OK", "text/plain", version.as_bytes());
+    }
+
+    let file = match resolve(output, &percent_decode(path)) {
+        Some(file) => file,
+        None => return respond(&mut stream, "404 Not Found", "text/plain", b"Not found"),
+    };
+
+    let mut contents = Vec::new();
+    File::open(&file)?.read_to_end(&mut contents)?;
+
+    let content_type = content_type(&file);
+
+    if content_type.starts_with("text/html") {
+        contents = inject_live_reload(&String::from_utf8_lossy(&contents)).into_bytes();
+    }
+
+    respond(&mut stream, "200 OK", content_type, &contents)
+}
+
+/// Maps a request path to a file in the output directory, trying `index.html` for
+/// directories and a `.html` extension for extensionless paths.
+fn resolve(output: &Path, path: &str) -> Option<PathBuf> {
+    let relative = Path::new(path.trim_start_matches('/'));
+
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let file = output.join(relative);
+
+    if file.is_dir() {
+        Some(file.join("index.html")).filter(|index| index.is_file())
+    } else if file.is_file() {
+        Some(file)
+    } else {
+        Some(file.with_extension("html")).filter(|html| html.is_file())
+    }
+}
+
+fn inject_live_reload(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{}{}", &html[..index], LIVE_RELOAD_SCRIPT, &html[index..]),
+        None => format!("{}{}", html, LIVE_RELOAD_SCRIPT),
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+
+    Ok(())
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        _ => "application/octet-stream",
+    }
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use color_eyre::Result;
+
+use crate::check::{self, Problem};
+use crate::generator::{self, GenerateOptions};
+use crate::serve::{self, ServeOptions};
+
+/// A blockblog site: an input directory of block definitions, markdown files and assets,
+/// and the options used to generate it into an output directory.
+#[derive(Debug, Clone)]
+pub struct Site {
+    pub options: GenerateOptions,
+}
+
+impl Site {
+    pub fn new(input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        Self::with_options(GenerateOptions::new(input.into(), output.into()))
+    }
+
+    pub fn with_options(options: GenerateOptions) -> Self {
+        Self { options }
+    }
+
+    /// Generates every page, asset and markdown file into the output directory.
+    pub fn build(&self) -> Result<()> {
+        generator::generate(&self.options)
+    }
+
+    /// Builds the site, then rebuilds whatever each change to the input directory affects.
+    /// Only returns if watching fails.
+    pub fn watch(&self) -> Result<()> {
+        generator::watch(&self.options)
+    }
+
+    /// Builds the site and serves the output directory over HTTP with live reload. Only
+    /// returns if serving or watching fails.
+    pub fn serve(&self, host: &str, port: u16) -> Result<()> {
+        serve::serve(ServeOptions {
+            generate: self.options.clone(),
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    /// Deletes everything in the output directory.
+    pub fn clean(&self) -> Result<()> {
+        generator::clean(&self.options)
+    }
+
+    /// Checks every block definition without generating anything, returning all the
+    /// problems found.
+    pub fn check(&self) -> Result<Vec<Problem>> {
+        check::check(&self.options)
+    }
+
+    /// Renders a single YAML block definition against the site's blocks, returning the
+    /// HTML instead of writing it.
+    pub fn render(&self, block_name: &str, definition: &str) -> Result<String> {
+        generator::render(&self.options, block_name, definition)
+    }
+}
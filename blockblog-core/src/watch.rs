@@ -0,0 +1,66 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, RecvTimeoutError},
+    time::Duration,
+};
+
+use color_eyre::Result;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watches `input` recursively and calls `on_change` with the changed paths whenever files
+/// change, waiting for a short quiet period so that bursts of events cause a single call.
+/// The changed paths are absolute, and changes under any of the `ignored` paths are not
+/// reported. Never returns unless watching fails.
+pub fn watch(
+    input: &Path,
+    ignored: &[PathBuf],
+    mut on_change: impl FnMut(Vec<PathBuf>),
+) -> Result<()> {
+    let (sender, receiver) = channel();
+
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&input.canonicalize()?, RecursiveMode::Recursive)?;
+
+    let ignored = ignored
+        .iter()
+        .map(|path| path.canonicalize().unwrap_or_else(|_| path.to_owned()))
+        .collect::<Vec<_>>();
+
+    // reading files during a rebuild produces access events, which would trigger another
+    // rebuild, so only changes to the files themselves count
+    let relevant_paths = |event: Event| {
+        let paths = match event.kind {
+            EventKind::Access(_) => Vec::new(),
+            _ => event.paths,
+        };
+
+        paths
+            .into_iter()
+            .filter(|path| !ignored.iter().any(|ignored| path.starts_with(ignored)))
+            .collect::<Vec<_>>()
+    };
+
+    loop {
+        let mut changed: Vec<PathBuf> = Vec::new();
+
+        let event = receiver.recv()?;
+        changed.extend(relevant_paths(event?));
+
+        loop {
+            match receiver.recv_timeout(DEBOUNCE) {
+                Ok(event) => changed.extend(relevant_paths(event?)),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+
+        changed.sort();
+        changed.dedup();
+
+        if !changed.is_empty() {
+            on_change(changed);
+        }
+    }
+}
@@ -1,3 +0,0 @@
-pub mod block_builder;
-pub mod colors;
-pub mod blocks;
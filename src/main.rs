@@ -1,16 +1,54 @@
-use clap::Parser;
+use std::{
+    io::{Read, Write},
+    str::FromStr,
+    time::Instant,
+};
+
+use clap::{Parser, Subcommand};
 use color_eyre::Result;
-use log::{Level, LevelFilter};
+use log::{info, Level, LevelFilter};
 use simplelog::{Color, ConfigBuilder, TermLogger, TerminalMode};
 
-mod data;
-mod generator;
+use blockblog_core::{
+    data::{a11y::A11yConfig, colors, config::SiteConfig},
+    scaffold, GenerateOptions, Site,
+};
 
 #[derive(Parser, Debug, Clone)]
 #[clap(
     name = "blockblog",
     about = "YAML and Markdown based static HTML generator"
 )]
+struct Cli {
+    #[clap(
+        short = 'v',
+        long = "verbose",
+        global = true,
+        parse(from_occurrences),
+        help = "Log more, repeat for even more"
+    )]
+    verbose: u8,
+    #[clap(
+        short = 'q',
+        long = "quiet",
+        global = true,
+        parse(from_occurrences),
+        help = "Log only warnings and errors, repeat for only errors"
+    )]
+    quiet: u8,
+    #[clap(
+        long = "log-format",
+        global = true,
+        default_value = "text",
+        possible_values = ["text", "json"],
+        help = "Log as colored text or as one JSON object per line"
+    )]
+    log_format: String,
+    #[clap(subcommand)]
+    args: Args,
+}
+
+#[derive(Subcommand, Debug, Clone)]
 enum Args {
     #[clap(name = "generate", about = "Generate all static HTML pages")]
     Generate {
@@ -26,10 +64,9 @@ enum Args {
             short = 'o',
             long = "output",
             parse(from_os_str),
-            default_value = "./output",
-            help = "Output directory"
+            help = "Output directory (defaults to `output` in blockblog.yml, or ./output)"
         )]
-        output: std::path::PathBuf,
+        output: Option<std::path::PathBuf>,
         #[clap(
             short = 's',
             long = "safe",
@@ -42,46 +79,573 @@ enum Args {
             help = "Insert debug information in the generated HTML"
         )]
         debug: bool,
+        #[clap(
+            long = "inline-generated-css",
+            help = "Embed each page's generated styles in its head instead of generated_style.css"
+        )]
+        inline_generated_css: bool,
+        #[clap(
+            long = "css-vars",
+            help = "Generate link styles as CSS custom properties instead of per-color rules"
+        )]
+        css_vars: bool,
+        #[clap(
+            long = "a11y-check",
+            help = "Report accessibility problems in the generated pages"
+        )]
+        a11y_check: bool,
+        #[clap(
+            long = "a11y-strict",
+            help = "Fail the build on accessibility problems (implies --a11y-check)"
+        )]
+        a11y_strict: bool,
+        #[clap(
+            long = "a11y-background",
+            parse(try_from_str = parse_color),
+            default_value = "#ffffff",
+            help = "Background color to check link color contrast against"
+        )]
+        a11y_background: colors::Color,
+        #[clap(
+            long = "only",
+            multiple_occurrences = true,
+            help = "Only build blocks matching this name or glob (repeatable)"
+        )]
+        only: Vec<String>,
+        #[clap(
+            long = "no-assets",
+            help = "Do not copy asset files or convert standalone markdown files"
+        )]
+        no_assets: bool,
+        #[clap(
+            long = "drafts",
+            help = "Include markdown files marked as drafts in their frontmatter"
+        )]
+        drafts: bool,
+        #[clap(long = "future", help = "Include markdown files dated in the future")]
+        future: bool,
+        #[clap(
+            long = "minify",
+            help = "Minify the generated HTML and CSS, and copied CSS and JS files"
+        )]
+        minify: bool,
+        #[clap(
+            long = "prune",
+            conflicts_with_all = &["safe", "only", "no-assets"],
+            help = "Remove output files no longer produced by any block or asset"
+        )]
+        prune: bool,
+        #[clap(
+            short = 'k',
+            long = "keep-going",
+            help = "Build every page that can be built, then fail listing the ones that couldn't"
+        )]
+        keep_going: bool,
+        #[clap(
+            long = "pretty-urls",
+            help = "Write each page as name/index.html and link to it as name/"
+        )]
+        pretty_urls: bool,
+        #[clap(
+            long = "base-url",
+            help = "URL the site is deployed at, overriding base_url in the site config"
+        )]
+        base_url: Option<String>,
+        #[clap(
+            short = 'e',
+            long = "profile",
+            help = "Build with a profile of the site config, like dev or prod"
+        )]
+        profile: Option<String>,
+        #[clap(
+            short = 'w',
+            long = "watch",
+            conflicts_with = "safe",
+            help = "Keep running and rebuild the blocks affected by each change in the input directory"
+        )]
+        watch: bool,
+    },
+    #[clap(
+        name = "serve",
+        about = "Serve the site locally, rebuilding and reloading pages on changes"
+    )]
+    Serve {
+        #[clap(
+            short = 'i',
+            long = "input",
+            parse(from_os_str),
+            default_value = ".",
+            help = "Input directory"
+        )]
+        input: std::path::PathBuf,
+        #[clap(
+            short = 'o',
+            long = "output",
+            parse(from_os_str),
+            help = "Output directory (defaults to a temporary directory)"
+        )]
+        output: Option<std::path::PathBuf>,
+        #[clap(
+            short = 'p',
+            long = "port",
+            default_value = "8000",
+            help = "Port to serve on"
+        )]
+        port: u16,
+        #[clap(
+            long = "host",
+            default_value = "127.0.0.1",
+            help = "Address to serve on"
+        )]
+        host: String,
+        #[clap(
+            short = 'd',
+            long = "debug",
+            help = "Insert debug information in the generated HTML"
+        )]
+        debug: bool,
+        #[clap(
+            long = "drafts",
+            help = "Include markdown files marked as drafts in their frontmatter"
+        )]
+        drafts: bool,
+        #[clap(long = "future", help = "Include markdown files dated in the future")]
+        future: bool,
+        #[clap(
+            short = 'e',
+            long = "profile",
+            help = "Build with a profile of the site config, like dev or prod"
+        )]
+        profile: Option<String>,
+    },
+    #[clap(name = "clean", about = "Delete everything in the output directory")]
+    Clean {
+        #[clap(
+            short = 'i',
+            long = "input",
+            parse(from_os_str),
+            default_value = ".",
+            help = "Input directory"
+        )]
+        input: std::path::PathBuf,
+        #[clap(
+            short = 'o',
+            long = "output",
+            parse(from_os_str),
+            help = "Output directory (defaults to `output` in blockblog.yml, or ./output)"
+        )]
+        output: Option<std::path::PathBuf>,
+    },
+    #[clap(
+        name = "check",
+        about = "Check every block definition for problems without generating anything"
+    )]
+    Check {
+        #[clap(
+            short = 'i',
+            long = "input",
+            parse(from_os_str),
+            default_value = ".",
+            help = "Input directory"
+        )]
+        input: std::path::PathBuf,
+        #[clap(
+            long = "a11y-check",
+            help = "Construct every page and warn about its accessibility problems"
+        )]
+        a11y_check: bool,
+        #[clap(
+            long = "a11y-strict",
+            help = "Count accessibility problems as problems (implies --a11y-check)"
+        )]
+        a11y_strict: bool,
+        #[clap(
+            long = "a11y-background",
+            parse(try_from_str = parse_color),
+            default_value = "#ffffff",
+            help = "Background color to check link color contrast against"
+        )]
+        a11y_background: colors::Color,
+    },
+    #[clap(name = "init", about = "Create a new site from a template")]
+    Init {
+        #[clap(
+            default_value = scaffold::DEFAULT_TEMPLATE,
+            help = "Site template to start from: blog or minimal"
+        )]
+        template: String,
+        #[clap(
+            short = 'd',
+            long = "dir",
+            parse(from_os_str),
+            default_value = ".",
+            help = "Directory to create the site in"
+        )]
+        dir: std::path::PathBuf,
+    },
+    #[clap(
+        name = "add-component",
+        about = "Add a component set from a directory, a git URL or gh:user/repo to a site"
+    )]
+    AddComponent {
+        #[clap(help = "Directory, git URL or gh:user/repo to add the component set from")]
+        source: String,
+        #[clap(
+            short = 'n',
+            long = "namespace",
+            help = "Namespace to include the blocks of the set under, named after the source by default"
+        )]
+        namespace: Option<String>,
+        #[clap(
+            short = 'i',
+            long = "input",
+            parse(from_os_str),
+            default_value = ".",
+            help = "Input directory of the site"
+        )]
+        input: std::path::PathBuf,
+    },
+    #[clap(name = "new", about = "Create new content in a site")]
+    New {
+        #[clap(subcommand)]
+        content: NewContent,
+    },
+    #[clap(
+        name = "render",
+        about = "Render a single block definition from stdin or a file to stdout"
+    )]
+    Render {
+        #[clap(
+            short = 'i',
+            long = "input",
+            parse(from_os_str),
+            default_value = ".",
+            help = "Input directory to resolve includes against"
+        )]
+        input: std::path::PathBuf,
+        #[clap(
+            short = 'f',
+            long = "file",
+            parse(from_os_str),
+            help = "Block definition file to render instead of reading stdin"
+        )]
+        file: Option<std::path::PathBuf>,
+        #[clap(
+            short = 'd',
+            long = "debug",
+            help = "Insert debug information in the generated HTML"
+        )]
+        debug: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum NewContent {
+    #[clap(
+        name = "post",
+        about = "Create a dated markdown post with frontmatter in the posts directory"
+    )]
+    Post {
+        #[clap(help = "Title of the post")]
+        title: String,
+        #[clap(
+            short = 'i',
+            long = "input",
+            parse(from_os_str),
+            default_value = ".",
+            help = "Input directory of the site"
+        )]
+        input: std::path::PathBuf,
     },
 }
 
 fn main() -> Result<()> {
-    setup()?;
+    let Cli {
+        verbose,
+        quiet,
+        log_format,
+        args,
+    } = Cli::parse();
+
+    // rendered HTML goes to stdout, so logs have to stay out of its way
+    let terminal_mode = match args {
+        Args::Render { .. } => TerminalMode::Stderr,
+        _ => TerminalMode::Stdout,
+    };
+
+    let level = match 3 + verbose as i16 - quiet as i16 {
+        i16::MIN..=1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
 
-    let args = Args::parse();
-    match args {
+    setup(terminal_mode, level, log_format == "json")?;
+
+    let result = match args {
         Args::Generate {
             input,
             output,
             safe,
             debug,
-        } => match generator::generate(input, output, safe, debug) {
-            Ok(_) => (),
-            Err(e) => {
-                eprintln!("{}", e);
+            inline_generated_css,
+            css_vars,
+            a11y_check,
+            a11y_strict,
+            a11y_background,
+            only,
+            no_assets,
+            drafts,
+            future,
+            minify,
+            prune,
+            keep_going,
+            pretty_urls,
+            base_url,
+            profile,
+            watch,
+        } => {
+            let output = output.unwrap_or_else(|| default_output(&input, profile.as_deref()));
+
+            let options = GenerateOptions {
+                input,
+                output,
+                safe,
+                debug,
+                inline_generated_css,
+                css_vars,
+                a11y: (a11y_check || a11y_strict).then_some(A11yConfig {
+                    background: a11y_background,
+                    strict: a11y_strict,
+                }),
+                only,
+                no_assets,
+                drafts,
+                future,
+                minify,
+                prune,
+                keep_going,
+                pretty_urls,
+                base_url,
+                profile,
+            };
+
+            options.with_profile().and_then(|options| {
+                let site = Site::with_options(options);
+
+                if watch {
+                    site.watch()
+                } else {
+                    site.build()
+                }
+            })
+        }
+        Args::Serve {
+            input,
+            output,
+            port,
+            host,
+            debug,
+            drafts,
+            future,
+            profile,
+        } => {
+            let output = output.unwrap_or_else(|| {
+                std::env::temp_dir().join(format!("blockblog-serve-{}", std::process::id()))
+            });
+
+            let options = GenerateOptions {
+                debug,
+                drafts,
+                future,
+                profile,
+                ..GenerateOptions::new(input, output)
+            };
+
+            options
+                .with_profile()
+                .and_then(|options| Site::with_options(options).serve(&host, port))
+        }
+        Args::Clean { input, output } => {
+            let output = output.unwrap_or_else(|| default_output(&input, None));
+
+            Site::new(input, output).clean()
+        }
+        Args::Check {
+            input,
+            a11y_check,
+            a11y_strict,
+            a11y_background,
+        } => {
+            let output = default_output(&input, None);
+            let site = Site::with_options(GenerateOptions {
+                a11y: (a11y_check || a11y_strict).then_some(A11yConfig {
+                    background: a11y_background,
+                    strict: a11y_strict,
+                }),
+                ..GenerateOptions::new(input, output)
+            });
+
+            site.check().map(|problems| {
+                if problems.is_empty() {
+                    info!("No problems found");
+                } else {
+                    for problem in problems.iter() {
+                        eprintln!("{}", problem);
+                    }
+                    match problems.len() {
+                        1 => eprintln!("Found 1 problem"),
+                        count => eprintln!("Found {} problems", count),
+                    }
+                    std::process::exit(1);
+                }
+            })
+        }
+        Args::Init { template, dir } => scaffold::init(&dir, &template).map(|files| {
+            for file in files {
+                info!("Created {}", file.to_string_lossy());
+            }
+        }),
+        Args::AddComponent {
+            source,
+            namespace,
+            input,
+        } => scaffold::add_component(&input, &source, namespace.as_deref()).map(|dir| {
+            info!("Added component set {}", dir.to_string_lossy());
+        }),
+        Args::New {
+            content: NewContent::Post { title, input },
+        } => scaffold::new_post(&input, &title).map(|files| {
+            for file in files {
+                info!("Created {}", file.to_string_lossy());
             }
-        },
+        }),
+        Args::Render { input, file, debug } => {
+            render(input, file, debug).map(|html| print!("{}", html))
+        }
+    };
+
+    // the whole chain of causes on one line reads better than the full report
+    if let Err(e) = result {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
     }
 
     Ok(())
 }
 
-fn setup() -> Result<()> {
+/// Output directory set in the site config, or in the profile if one is given, or
+/// `./output`.
+fn default_output(input: &std::path::Path, profile: Option<&str>) -> std::path::PathBuf {
+    match SiteConfig::load_profile(input, profile) {
+        Ok(SiteConfig {
+            output: Some(output),
+            ..
+        }) => input.join(output),
+        _ => std::path::PathBuf::from("./output"),
+    }
+}
+
+/// Renders a block definition read from `file`, or from stdin if no file is given.
+fn render(
+    input: std::path::PathBuf,
+    file: Option<std::path::PathBuf>,
+    debug: bool,
+) -> Result<String> {
+    let (block_name, contents) = match &file {
+        Some(file) => {
+            let block_name = file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "render".to_string());
+
+            (block_name, std::fs::read_to_string(file)?)
+        }
+        None => {
+            let mut contents = String::new();
+            std::io::stdin().read_to_string(&mut contents)?;
+
+            ("stdin".to_string(), contents)
+        }
+    };
+
+    let output = input.join("output");
+    let site = Site::with_options(GenerateOptions {
+        debug,
+        ..GenerateOptions::new(input, output)
+    });
+
+    site.render(&block_name, &contents)
+}
+
+fn parse_color(value: &str) -> Result<colors::Color, String> {
+    colors::Color::from_str(value).map_err(|e| e.to_string())
+}
+
+fn setup(terminal_mode: TerminalMode, level: LevelFilter, json: bool) -> Result<()> {
     if std::env::var("RUST_BACKTRACE").is_err() {
         std::env::set_var("RUST_BACKTRACE", "1")
     }
 
-    TermLogger::init(
-        LevelFilter::Info,
-        ConfigBuilder::new()
-            .set_level_color(Level::Info, Some(Color::Cyan))
-            .set_level_color(Level::Warn, Some(Color::Yellow))
-            .set_level_color(Level::Error, Some(Color::Red))
-            .build(),
-        TerminalMode::Stdout,
-        simplelog::ColorChoice::Auto
-    )?;
+    if json {
+        // messages are colored where they are logged, which has no place in JSON
+        colored::control::set_override(false);
+
+        log::set_boxed_logger(Box::new(JsonLogger {
+            level,
+            start: Instant::now(),
+            stderr: matches!(terminal_mode, TerminalMode::Stderr),
+        }))?;
+        log::set_max_level(level);
+    } else {
+        TermLogger::init(
+            level,
+            ConfigBuilder::new()
+                .set_level_color(Level::Info, Some(Color::Cyan))
+                .set_level_color(Level::Warn, Some(Color::Yellow))
+                .set_level_color(Level::Error, Some(Color::Red))
+                .build(),
+            terminal_mode,
+            simplelog::ColorChoice::Auto,
+        )?;
+    }
     color_eyre::install()?;
 
     Ok(())
 }
+
+/// Logs one JSON object per line, for build pipelines to parse.
+struct JsonLogger {
+    level: LevelFilter,
+    start: Instant,
+    stderr: bool,
+}
+
+impl log::Log for JsonLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = serde_json::json!({
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+            "elapsed_ms": self.start.elapsed().as_millis() as u64,
+        });
+
+        // a reader that went away, like `head`, is no reason to crash
+        let _ = if self.stderr {
+            writeln!(std::io::stderr(), "{}", line)
+        } else {
+            writeln!(std::io::stdout(), "{}", line)
+        };
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,117 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::path::Path;
+
+const PAGE: &str = "html:\n  head:\n    lang: en\n    title: T\n  body:\n    - text: Hello\n";
+
+/// An input directory with a page for each of `names`.
+fn site(names: &[&str]) -> tempfile::TempDir {
+    let input = tempfile::tempdir().unwrap();
+
+    for name in names {
+        std::fs::write(input.path().join(format!("{}.yml", name)), PAGE).unwrap();
+    }
+
+    input
+}
+
+fn generate(input: &Path) -> Command {
+    let mut command = Command::cargo_bin("blockblog").unwrap();
+    command
+        .arg("-q")
+        .arg("generate")
+        .arg("-i")
+        .arg(input)
+        .arg("-o")
+        .arg(input.join("out"));
+    command
+}
+
+#[test]
+fn generate_only_builds_matching_blocks() {
+    let input = site(&["index", "about", "contact"]);
+
+    generate(input.path())
+        .args(["--only", "index", "--only", "ab*"])
+        .assert()
+        .success();
+
+    let output = input.path().join("out");
+    assert!(output.join("index.html").exists());
+    assert!(output.join("about.html").exists());
+    assert!(!output.join("contact.html").exists());
+}
+
+#[test]
+fn generate_only_unknown_block_fails() {
+    let input = site(&["index"]);
+
+    generate(input.path())
+        .args(["--only", "missing"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No block matches missing"));
+}
+
+#[test]
+fn render_reads_stdin() {
+    let input = site(&[]);
+
+    Command::cargo_bin("blockblog")
+        .unwrap()
+        .args(["render", "-i"])
+        .arg(input.path())
+        .write_stdin(PAGE)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hello").and(predicate::str::contains("<html")));
+}
+
+#[test]
+fn render_reads_file() {
+    let input = site(&["page"]);
+
+    Command::cargo_bin("blockblog")
+        .unwrap()
+        .args(["render", "-i"])
+        .arg(input.path())
+        .arg("-f")
+        .arg(input.path().join("page.yml"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Hello"));
+}
+
+#[test]
+fn render_reports_invalid_blocks() {
+    let input = site(&[]);
+
+    Command::cargo_bin("blockblog")
+        .unwrap()
+        .args(["render", "-i"])
+        .arg(input.path())
+        .write_stdin("html:\n  body:\n    - nonsense: 1\n")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn check_counts_problems() {
+    let input = site(&["index"]);
+    std::fs::write(
+        input.path().join("broken.yml"),
+        "html:\n  body:\n    - tytle: c\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("blockblog")
+        .unwrap()
+        .args(["check", "-i"])
+        .arg(input.path())
+        .assert()
+        .failure()
+        .stderr(
+            predicate::str::contains("broken.yml:3: unknown variant `tytle`")
+                .and(predicate::str::ends_with("Found 1 problem\n")),
+        );
+}